@@ -0,0 +1,28 @@
+use std::fs::File;
+use async_mp4::dump::dump;
+
+/// Prints the box tree of a file: `mp4dump <file>`.
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: mp4dump <file>");
+            std::process::exit(2);
+        }
+    };
+    let result = File::open(&path).map_err(Into::into).and_then(|file| {
+        let mut reader = futures::io::AllowStdIo::new(file);
+        futures::executor::block_on(dump(&mut reader))
+    });
+    match result {
+        Ok(boxes) => {
+            for dumped in boxes {
+                println!("{}", dumped);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::error::MP4Error;
+
+/// A codec parameter as found in the `codecs` attribute of a mime type (RFC 6381).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Codec {
+    /// `avc1.PPCCLL` (RFC 6381 § 3.3)
+    Avc {
+        fourcc: [u8; 4],
+        profile_indication: u8,
+        profile_compatibility: u8,
+        level_indication: u8,
+    },
+    /// `hvc1.1.6.L93.B0` (ISO 14496-15 Annex E)
+    Hevc {
+        fourcc: [u8; 4],
+        profile_space: u8,
+        profile_idc: u8,
+        profile_compatibility_flags: u32,
+        tier_flag: bool,
+        level_idc: u8,
+        constraint_indicator_flags: [u8; 6],
+    },
+    Opus,
+    /// Any codec this crate does not know about, kept verbatim.
+    Other(String),
+}
+
+impl Display for Codec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Codec::Avc { fourcc, profile_indication, profile_compatibility, level_indication } => {
+                write!(f, "{}.{:02x}{:02x}{:02x}", fourcc.escape_ascii(), profile_indication, profile_compatibility, level_indication)
+            }
+            Codec::Hevc { fourcc, profile_space, profile_idc, profile_compatibility_flags, tier_flag, level_idc, constraint_indicator_flags } => {
+                let space = match profile_space {
+                    1 => "A",
+                    2 => "B",
+                    3 => "C",
+                    _ => ""
+                };
+                let tier = if *tier_flag { 'H' } else { 'L' };
+                write!(f, "{}.{}{}.{:X}.{}{}", fourcc.escape_ascii(), space, profile_idc, profile_compatibility_flags.reverse_bits(), tier, level_idc)?;
+                let len = constraint_indicator_flags.iter().rposition(|it| *it != 0).map(|it| it + 1).unwrap_or_default();
+                for byte in &constraint_indicator_flags[..len] {
+                    write!(f, ".{:X}", byte)?;
+                }
+                Ok(())
+            }
+            Codec::Opus => write!(f, "Opus"),
+            Codec::Other(codec) => write!(f, "{}", codec)
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = MP4Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || MP4Error::Custom(format!("Invalid codec string: {}", s));
+        let mut parts = s.split('.');
+        let fourcc = parts.next().unwrap_or_default();
+        Ok(match fourcc {
+            "avc1" | "avc3" => {
+                let profile = parts.next().filter(|it| it.len() == 6).ok_or_else(invalid)?;
+                if parts.next().is_some() {
+                    return Err(invalid());
+                }
+                let [_, profile_indication, profile_compatibility, level_indication] = u32::from_str_radix(profile, 16).map_err(|_| invalid())?.to_be_bytes();
+                Codec::Avc {
+                    fourcc: fourcc.as_bytes().try_into().map_err(|_| invalid())?,
+                    profile_indication,
+                    profile_compatibility,
+                    level_indication
+                }
+            }
+            "hvc1" | "hev1" => {
+                let profile = parts.next().ok_or_else(invalid)?;
+                let (profile_space, profile_idc) = match profile.as_bytes().first() {
+                    Some(b'A') => (1, &profile[1..]),
+                    Some(b'B') => (2, &profile[1..]),
+                    Some(b'C') => (3, &profile[1..]),
+                    _ => (0, profile)
+                };
+                let compatibility = parts.next().ok_or_else(invalid)?;
+                let level = parts.next().ok_or_else(invalid)?;
+                let tier_flag = match level.as_bytes().first() {
+                    Some(b'L') => false,
+                    Some(b'H') => true,
+                    _ => return Err(invalid())
+                };
+                let mut constraint_indicator_flags = [0u8; 6];
+                for (i, part) in parts.enumerate() {
+                    *constraint_indicator_flags.get_mut(i).ok_or_else(invalid)? = u8::from_str_radix(part, 16).map_err(|_| invalid())?;
+                }
+                Codec::Hevc {
+                    fourcc: fourcc.as_bytes().try_into().map_err(|_| invalid())?,
+                    profile_space,
+                    profile_idc: profile_idc.parse().map_err(|_| invalid())?,
+                    profile_compatibility_flags: u32::from_str_radix(compatibility, 16).map_err(|_| invalid())?.reverse_bits(),
+                    tier_flag,
+                    level_idc: level[1..].parse().map_err(|_| invalid())?,
+                    constraint_indicator_flags
+                }
+            }
+            "Opus" | "opus" => {
+                if parts.next().is_some() {
+                    return Err(invalid());
+                }
+                Codec::Opus
+            }
+            "" => return Err(invalid()),
+            _ => Codec::Other(s.to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::codec::Codec;
+    use crate::error::MP4Error;
+
+    #[test]
+    pub fn test_round_trip() -> Result<(), MP4Error> {
+        for codec in ["avc1.64001f", "hvc1.1.6.L93.B0", "hev1.A4.10.H120.90.0.0.0.1", "Opus", "mp4a.40.2"] {
+            assert_eq!(codec.parse::<Codec>()?.to_string(), codec);
+        }
+        assert_eq!("hvc1.2.4.L153.B0".parse::<Codec>()?, Codec::Hevc {
+            fourcc: *b"hvc1",
+            profile_space: 0,
+            profile_idc: 2,
+            profile_compatibility_flags: 0x2000_0000,
+            tier_flag: false,
+            level_idc: 153,
+            constraint_indicator_flags: [0xB0, 0, 0, 0, 0, 0]
+        });
+        assert!("avc1.6400".parse::<Codec>().is_err());
+        Ok(())
+    }
+
+}
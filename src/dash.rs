@@ -0,0 +1,413 @@
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+use std::time::Duration;
+use chrono::{DateTime, SecondsFormat, Utc};
+use crate::mp4box::moof::Moof;
+use crate::mp4box::trak::Trak;
+use crate::mp4box::trex::Trex;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TimelineEntry {
+    pub time: u64,
+    pub duration: u64,
+    pub repeat: u32,
+}
+
+impl TimelineEntry {
+    pub fn end(&self) -> u64 {
+        self.time + self.duration * (self.repeat as u64 + 1)
+    }
+}
+
+/// A `SegmentTimeline`, consecutive segments of equal duration are merged into repeats.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct SegmentTimeline {
+    pub entries: Vec<TimelineEntry>,
+}
+
+impl SegmentTimeline {
+
+    pub fn push(&mut self, time: u64, duration: u64) {
+        if let Some(last) = self.entries.last_mut() {
+            if last.duration == duration && last.end() == time {
+                last.repeat += 1;
+                return;
+            }
+        }
+        self.entries.push(TimelineEntry { time, duration, repeat: 0 });
+    }
+
+    /// Adds the segment made of a fragment from its `tfdt` decode time and sample durations.
+    /// Returns false if the fragment has no decode time for the track.
+    pub fn push_fragment(&mut self, moof: &Moof, track_id: u32, trex: Option<&Trex>) -> bool {
+        let traf = match moof.traf(track_id) {
+            Some(traf) => traf,
+            None => return false
+        };
+        match &traf.tfdt {
+            Some(tfdt) => {
+                self.push(*tfdt.base_media_decode_time, traf.duration(trex));
+                true
+            }
+            None => false
+        }
+    }
+
+    pub fn end(&self) -> u64 {
+        self.entries.last().map(TimelineEntry::end).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum DashSegments {
+    /// A single file indexed by a `sidx` (on-demand profile), ranges are inclusive byte ranges.
+    Indexed {
+        base_url: String,
+        initialization: RangeInclusive<u64>,
+        index: RangeInclusive<u64>,
+    },
+    /// Separate segment files addressed by their decode time (live profile).
+    Template {
+        initialization: String,
+        media: String,
+        timeline: SegmentTimeline,
+    },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Representation {
+    pub id: String,
+    pub bandwidth: u64,
+    pub codecs: Vec<String>,
+    pub timescale: u32,
+    pub width: Option<u16>,
+    pub height: Option<u16>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    pub segments: DashSegments,
+}
+
+impl Representation {
+
+    /// Describes a single track output.
+    pub fn from_trak(id: impl Into<String>, bandwidth: u64, trak: &Trak, segments: DashSegments) -> Option<Self> {
+        Self::from_traks(id, bandwidth, [trak], segments)
+    }
+
+    /// Describes a multiplexed output, the timescale is taken from the first track.
+    pub fn from_traks<'a>(id: impl Into<String>, bandwidth: u64, traks: impl IntoIterator<Item=&'a Trak>, segments: DashSegments) -> Option<Self> {
+        let traks = traks.into_iter().collect::<Vec<_>>();
+        let mut representation = Self {
+            id: id.into(),
+            bandwidth,
+            codecs: vec![],
+            timescale: traks.first()?.timescale()?,
+            width: None,
+            height: None,
+            sample_rate: None,
+            channels: None,
+            segments
+        };
+        for entry in traks.iter().filter_map(|trak| trak.sample_entry()) {
+            representation.codecs.extend(entry.codec_string());
+            if let Some(visual) = entry.visual_sample_entry() {
+                representation.width = Some(visual.width);
+                representation.height = Some(visual.height);
+            }
+            if let Some(audio) = entry.audio_sample_entry() {
+                representation.sample_rate = Some(audio.sample_rate.to_bits() as u32 >> 16);
+                representation.channels = Some(audio.channel_count);
+            }
+        }
+        Some(representation)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ContentType {
+    Video,
+    Audio,
+}
+
+impl ContentType {
+
+    /// Guesses the content type from the `hdlr` handler type.
+    pub fn from_trak(trak: &Trak) -> Option<Self> {
+        match &trak.handler_type()? {
+            b"vide" => Some(Self::Video),
+            b"soun" => Some(Self::Audio),
+            _ => None
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ContentType::Video => "video",
+            ContentType::Audio => "audio"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct AdaptationSet {
+    /// `None` for multiplexed representations.
+    pub content_type: Option<ContentType>,
+    pub language: Option<String>,
+    pub representations: Vec<Representation>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DynamicMpd {
+    pub availability_start_time: DateTime<Utc>,
+    pub publish_time: DateTime<Utc>,
+    pub minimum_update_period: Duration,
+    pub time_shift_buffer_depth: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Mpd {
+    /// `None` for a static presentation.
+    pub dynamic: Option<DynamicMpd>,
+    pub media_presentation_duration: Option<Duration>,
+    pub min_buffer_time: Duration,
+    pub adaptation_sets: Vec<AdaptationSet>,
+}
+
+impl Default for Mpd {
+    fn default() -> Self {
+        Self {
+            dynamic: None,
+            media_presentation_duration: None,
+            min_buffer_time: Duration::from_secs(2),
+            adaptation_sets: vec![]
+        }
+    }
+}
+
+impl Mpd {
+    /// On-demand when every representation is `sidx` indexed, live otherwise.
+    pub fn profile(&self) -> &'static str {
+        let on_demand = self.adaptation_sets.iter()
+            .flat_map(|it| it.representations.iter())
+            .all(|it| matches!(it.segments, DashSegments::Indexed { .. }));
+        if on_demand {
+            "urn:mpeg:dash:profile:isoff-on-demand:2011"
+        } else {
+            "urn:mpeg:dash:profile:isoff-live:2011"
+        }
+    }
+}
+
+struct XsDuration(Duration);
+
+impl Display for XsDuration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PT{:.3}S", self.0.as_secs_f64())
+    }
+}
+
+struct Escaped<'a>(&'a str);
+
+impl Display for Escaped<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '&' => f.write_str("&amp;")?,
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '"' => f.write_str("&quot;")?,
+                c => write!(f, "{}", c)?
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Mpd {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        write!(f, "<MPD xmlns=\"urn:mpeg:dash:schema:mpd:2011\" profiles=\"{}\"", self.profile())?;
+        match &self.dynamic {
+            Some(dynamic) => {
+                write!(f, " type=\"dynamic\" availabilityStartTime=\"{}\" publishTime=\"{}\" minimumUpdatePeriod=\"{}\"",
+                       dynamic.availability_start_time.to_rfc3339_opts(SecondsFormat::Millis, true),
+                       dynamic.publish_time.to_rfc3339_opts(SecondsFormat::Millis, true),
+                       XsDuration(dynamic.minimum_update_period))?;
+                if let Some(depth) = dynamic.time_shift_buffer_depth {
+                    write!(f, " timeShiftBufferDepth=\"{}\"", XsDuration(depth))?;
+                }
+            }
+            None => write!(f, " type=\"static\"")?
+        }
+        if let Some(duration) = self.media_presentation_duration {
+            write!(f, " mediaPresentationDuration=\"{}\"", XsDuration(duration))?;
+        }
+        writeln!(f, " minBufferTime=\"{}\">", XsDuration(self.min_buffer_time))?;
+        writeln!(f, "  <Period id=\"0\" start=\"PT0S\">")?;
+        for set in &self.adaptation_sets {
+            write!(f, "    <AdaptationSet segmentAlignment=\"true\" startWithSAP=\"1\"")?;
+            if let Some(content_type) = set.content_type {
+                write!(f, " contentType=\"{0}\" mimeType=\"{0}/mp4\"", content_type.as_str())?;
+            } else {
+                write!(f, " mimeType=\"video/mp4\"")?;
+            }
+            if let Some(language) = &set.language {
+                write!(f, " lang=\"{}\"", Escaped(language))?;
+            }
+            writeln!(f, ">")?;
+            for representation in &set.representations {
+                write!(f, "      <Representation id=\"{}\" bandwidth=\"{}\"", Escaped(&representation.id), representation.bandwidth)?;
+                if !representation.codecs.is_empty() {
+                    write!(f, " codecs=\"{}\"", Escaped(&representation.codecs.join(",")))?;
+                }
+                if let (Some(width), Some(height)) = (representation.width, representation.height) {
+                    write!(f, " width=\"{}\" height=\"{}\"", width, height)?;
+                }
+                if let Some(sample_rate) = representation.sample_rate {
+                    write!(f, " audioSamplingRate=\"{}\"", sample_rate)?;
+                }
+                writeln!(f, ">")?;
+                if let Some(channels) = representation.channels {
+                    writeln!(f, "        <AudioChannelConfiguration schemeIdUri=\"urn:mpeg:dash:23003:3:audio_channel_configuration:2011\" value=\"{}\"/>", channels)?;
+                }
+                match &representation.segments {
+                    DashSegments::Indexed { base_url, initialization, index } => {
+                        writeln!(f, "        <BaseURL>{}</BaseURL>", Escaped(base_url))?;
+                        writeln!(f, "        <SegmentBase timescale=\"{}\" indexRange=\"{}-{}\">", representation.timescale, index.start(), index.end())?;
+                        writeln!(f, "          <Initialization range=\"{}-{}\"/>", initialization.start(), initialization.end())?;
+                        writeln!(f, "        </SegmentBase>")?;
+                    }
+                    DashSegments::Template { initialization, media, timeline } => {
+                        writeln!(f, "        <SegmentTemplate timescale=\"{}\" initialization=\"{}\" media=\"{}\">", representation.timescale, Escaped(initialization), Escaped(media))?;
+                        writeln!(f, "          <SegmentTimeline>")?;
+                        let mut end = None;
+                        for entry in &timeline.entries {
+                            write!(f, "            <S")?;
+                            if end != Some(entry.time) {
+                                write!(f, " t=\"{}\"", entry.time)?;
+                            }
+                            write!(f, " d=\"{}\"", entry.duration)?;
+                            if entry.repeat > 0 {
+                                write!(f, " r=\"{}\"", entry.repeat)?;
+                            }
+                            writeln!(f, "/>")?;
+                            end = Some(entry.end());
+                        }
+                        writeln!(f, "          </SegmentTimeline>")?;
+                        writeln!(f, "        </SegmentTemplate>")?;
+                    }
+                }
+                writeln!(f, "      </Representation>")?;
+            }
+            writeln!(f, "    </AdaptationSet>")?;
+        }
+        writeln!(f, "  </Period>")?;
+        writeln!(f, "</MPD>")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use chrono::{TimeZone, Utc};
+    use crate::dash::{AdaptationSet, ContentType, DashSegments, DynamicMpd, Mpd, Representation, SegmentTimeline, TimelineEntry};
+
+    fn representation(id: &str, segments: DashSegments) -> Representation {
+        Representation {
+            id: id.to_string(),
+            bandwidth: 1_000_000,
+            codecs: vec!["avc1.64001f".to_string()],
+            timescale: 90000,
+            width: Some(1280),
+            height: Some(720),
+            sample_rate: None,
+            channels: None,
+            segments
+        }
+    }
+
+    #[test]
+    pub fn test_segment_timeline() {
+        let mut timeline = SegmentTimeline::default();
+        for time in [0, 180000, 360000] {
+            timeline.push(time, 180000);
+        }
+        // a missing segment, then a shorter one
+        timeline.push(720000, 180000);
+        timeline.push(900000, 90000);
+        assert_eq!(timeline.entries, vec![
+            TimelineEntry { time: 0, duration: 180000, repeat: 2 },
+            TimelineEntry { time: 720000, duration: 180000, repeat: 0 },
+            TimelineEntry { time: 900000, duration: 90000, repeat: 0 },
+        ]);
+        assert_eq!(timeline.end(), 990000);
+
+        let mpd = Mpd {
+            dynamic: Some(DynamicMpd {
+                availability_start_time: Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap(),
+                publish_time: Utc.with_ymd_and_hms(2024, 1, 2, 3, 5, 0).unwrap(),
+                minimum_update_period: Duration::from_secs(2),
+                time_shift_buffer_depth: Some(Duration::from_secs(30))
+            }),
+            adaptation_sets: vec![AdaptationSet {
+                content_type: Some(ContentType::Video),
+                language: None,
+                representations: vec![representation("video&1", DashSegments::Template {
+                    initialization: "init-$RepresentationID$.mp4".to_string(),
+                    media: "$RepresentationID$-$Time$.m4s".to_string(),
+                    timeline
+                })]
+            }],
+            ..Default::default()
+        };
+        assert_eq!(mpd.to_string(), r#"<?xml version="1.0" encoding="UTF-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" profiles="urn:mpeg:dash:profile:isoff-live:2011" type="dynamic" availabilityStartTime="2024-01-02T03:04:05.000Z" publishTime="2024-01-02T03:05:00.000Z" minimumUpdatePeriod="PT2.000S" timeShiftBufferDepth="PT30.000S" minBufferTime="PT2.000S">
+  <Period id="0" start="PT0S">
+    <AdaptationSet segmentAlignment="true" startWithSAP="1" contentType="video" mimeType="video/mp4">
+      <Representation id="video&amp;1" bandwidth="1000000" codecs="avc1.64001f" width="1280" height="720">
+        <SegmentTemplate timescale="90000" initialization="init-$RepresentationID$.mp4" media="$RepresentationID$-$Time$.m4s">
+          <SegmentTimeline>
+            <S t="0" d="180000" r="2"/>
+            <S t="720000" d="180000"/>
+            <S d="90000"/>
+          </SegmentTimeline>
+        </SegmentTemplate>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>
+"#);
+    }
+
+    #[test]
+    pub fn test_on_demand() {
+        let mpd = Mpd {
+            media_presentation_duration: Some(Duration::from_millis(12500)),
+            adaptation_sets: vec![AdaptationSet {
+                content_type: None,
+                language: Some("en".to_string()),
+                representations: vec![representation("1", DashSegments::Indexed {
+                    base_url: "movie.mp4".to_string(),
+                    initialization: 0..=799,
+                    index: 800..=899
+                })]
+            }],
+            ..Default::default()
+        };
+        assert_eq!(mpd.to_string(), r#"<?xml version="1.0" encoding="UTF-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" type="static" mediaPresentationDuration="PT12.500S" minBufferTime="PT2.000S">
+  <Period id="0" start="PT0S">
+    <AdaptationSet segmentAlignment="true" startWithSAP="1" mimeType="video/mp4" lang="en">
+      <Representation id="1" bandwidth="1000000" codecs="avc1.64001f" width="1280" height="720">
+        <BaseURL>movie.mp4</BaseURL>
+        <SegmentBase timescale="90000" indexRange="800-899">
+          <Initialization range="0-799"/>
+        </SegmentBase>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>
+"#);
+    }
+
+}
@@ -0,0 +1,828 @@
+use std::collections::VecDeque;
+use std::io::{ErrorKind, SeekFrom};
+use std::time::Duration;
+use futures::{AsyncReadExt, AsyncSeekExt};
+use crate::bytes_read::ReadMp4;
+use crate::bytes_write::Mp4Writable;
+use crate::error::MP4Error;
+use crate::header::BoxHeader;
+use crate::mp4box::box_trait::{BoxRead, IBox};
+use crate::mp4box::box_unknown::UnknownBox;
+use crate::mp4box::emsg::{EmsgBox, EmsgPresentationTime};
+use crate::mp4box::ftyp::FtypBox;
+use crate::mp4box::mdat::MdatBox;
+use crate::mp4box::mfra::MfraBox;
+use crate::mp4box::mfro::MfroBox;
+use crate::mp4box::moof::MoofBox;
+use crate::mp4box::moov::{Moov, MoovBox};
+use crate::mp4box::traf::ResolvedSample;
+use crate::mp4box::trak::Trak;
+use crate::sample_table::SampleTable;
+use crate::size::BoxSize;
+use crate::types::duration::{duration_from_ticks, ticks_from_duration};
+
+/// An event message with its presentation time resolved on the media timeline.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct EmsgEvent {
+    pub emsg: EmsgBox,
+    /// Absolute presentation time in `emsg.timescale` units,
+    /// `None` when a version 0 message is not followed by a `moof` to anchor it to.
+    pub presentation_time: Option<u64>,
+}
+
+impl EmsgEvent {
+
+    pub fn start(&self) -> Option<Duration> {
+        duration_from_ticks(self.presentation_time?, self.emsg.timescale)
+    }
+
+    /// `None` when the duration is unknown (`0xFFFFFFFF`).
+    pub fn duration(&self) -> Option<Duration> {
+        match self.emsg.event_duration {
+            u32::MAX => None,
+            duration => duration_from_ticks(duration as u64, self.emsg.timescale)
+        }
+    }
+}
+
+/// A sample of a progressive file, see [`Demuxer::next_sample`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Mp4Sample {
+    pub track_id: u32,
+    /// 0-based sample number of the track
+    pub number: u32,
+    /// in the track timescale
+    pub decode_time: u64,
+    pub duration: u32,
+    pub sync: bool,
+    pub data: Vec<u8>,
+}
+
+/// The sync sample a track resumes from after [`Demuxer::seek`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SeekPoint {
+    pub track_id: u32,
+    /// 0-based sample number of the track in a progressive file, of the sample in its `traf` in a fragmented file
+    pub sample: u32,
+    /// in the track timescale
+    pub decode_time: u64,
+    /// position of the sample data in the file
+    pub offset: u64,
+    /// position of the `moof` that holds the sample in a fragmented file
+    pub moof_offset: Option<u64>,
+}
+
+/// The next sample to read of a progressive track.
+struct TrackCursor {
+    track_id: u32,
+    table: SampleTable,
+    next: u32,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Mp4Event {
+    Ftyp(FtypBox),
+    Moov(MoovBox),
+    Moof(MoofBox),
+    Mdat(MdatBox),
+    Emsg(EmsgEvent),
+    Unknown(UnknownBox),
+}
+
+/// Reads the top level boxes of a file one by one.
+///
+/// `emsg` boxes are held back until the following box is known so that version 0 messages
+/// can be resolved against the decode time of the segment they precede.
+pub struct Demuxer<R: ReadMp4> {
+    reader: R,
+    moov: Option<MoovBox>,
+    held_emsgs: Vec<EmsgBox>,
+    pending: VecDeque<Mp4Event>,
+    cursors: Option<Vec<TrackCursor>>,
+}
+
+impl<R: ReadMp4> Demuxer<R> {
+
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            moov: None,
+            held_emsgs: vec![],
+            pending: Default::default(),
+            cursors: None
+        }
+    }
+
+    /// The last `moov` read from the stream.
+    pub fn moov(&self) -> Option<&MoovBox> {
+        self.moov.as_ref()
+    }
+
+    /// Reads the `moov` wherever it is in the file, leaving the stream where it was.
+    pub async fn read_moov(&mut self) -> Result<&MoovBox, MP4Error> {
+        self.load_moov().await?;
+        self.moov.as_ref().ok_or_else(|| MP4Error::Custom("The file has no moov".to_string()))
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    pub async fn next(&mut self) -> Result<Option<Mp4Event>, MP4Error> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+            let header = match self.read_header().await? {
+                Some(header) => header,
+                None => {
+                    self.flush_emsgs(None);
+                    return Ok(self.pending.pop_front());
+                }
+            };
+            let start = self.reader.seek(SeekFrom::Current(0)).await?;
+            let event = match header.id {
+                EmsgBox::ID => {
+                    let emsg = EmsgBox::read(header, &mut self.reader).await?;
+                    self.held_emsgs.push(emsg);
+                    None
+                }
+                FtypBox::ID => Some(Mp4Event::Ftyp(FtypBox::read(header, &mut self.reader).await?)),
+                MoovBox::ID => {
+                    let moov = MoovBox::read(header, &mut self.reader).await?;
+                    self.moov = Some(moov.clone());
+                    Some(Mp4Event::Moov(moov))
+                }
+                MoofBox::ID => Some(Mp4Event::Moof(MoofBox::read(header, &mut self.reader).await?)),
+                MdatBox::ID => Some(Mp4Event::Mdat(MdatBox::read(header, &mut self.reader).await?)),
+                _ => Some(Mp4Event::Unknown(UnknownBox::read(header, &mut self.reader).await?)),
+            };
+            if let BoxSize::Known(size) = header.size_minus_self() {
+                self.reader.seek(SeekFrom::Start(start + size as u64)).await?;
+            }
+            if let Some(event) = event {
+                let segment = match &event {
+                    Mp4Event::Moof(moof) => Some(moof),
+                    _ => None
+                };
+                self.flush_emsgs(segment);
+                self.pending.push_back(event);
+            }
+        }
+    }
+
+    /// Reads the samples of a progressive file in the order of their data, interleaving the tracks.
+    /// Starts from the first samples, or from where [`Demuxer::seek`] left the tracks.
+    pub async fn next_sample(&mut self) -> Result<Option<Mp4Sample>, MP4Error> {
+        self.load_moov().await?;
+        let cursors = self.cursors()?;
+        let next = cursors.iter().enumerate()
+            .filter_map(|(i, cursor)| Some((i, cursor.table.sample(cursor.next)?)))
+            .min_by_key(|(_, sample)| sample.offset);
+        let (index, sample) = match next {
+            Some(next) => next,
+            None => return Ok(None)
+        };
+        cursors[index].next += 1;
+        let track_id = cursors[index].track_id;
+        self.reader.seek(SeekFrom::Start(sample.offset)).await?;
+        let mut data = vec![0u8; sample.size as usize];
+        self.reader.read_exact(&mut data).await?;
+        Ok(Some(Mp4Sample {
+            track_id,
+            number: sample.number,
+            decode_time: sample.decode_time,
+            duration: sample.duration,
+            sync: sample.sync,
+            data
+        }))
+    }
+
+    /// Moves every track to its last sync sample at or before `time`, on the movie timeline.
+    /// The time is mapped to each track through its edit list and `mdhd` timescale.
+    /// A track without such a sync sample, e.g. one starting after `time`, moves to its first sync sample,
+    /// or to its first sample if it has none.
+    ///
+    /// Progressive files use `stss` and resume with [`Demuxer::next_sample`].
+    /// Fragmented files use the `tfra` of each track when the file ends with a `mfra`, otherwise the `tfdt` and sample flags of every `moof`,
+    /// and resume with [`Demuxer::next`] from the first `moof` that holds a returned sample.
+    pub async fn seek(&mut self, time: Duration) -> Result<Vec<SeekPoint>, MP4Error> {
+        self.load_moov().await?;
+        let moov = self.moov.clone().ok_or_else(|| MP4Error::Custom("The file has no moov".to_string()))?;
+        self.held_emsgs.clear();
+        self.pending.clear();
+        let targets = moov.traks.iter()
+            .filter_map(|trak| Some((trak.track_id()?, media_time(&moov, trak, time)?)))
+            .collect::<Vec<_>>();
+        if moov.mvex.is_some() {
+            self.seek_fragmented(&moov, &targets).await
+        } else {
+            self.seek_progressive(&targets).await
+        }
+    }
+
+    async fn seek_progressive(&mut self, targets: &[(u32, u64)]) -> Result<Vec<SeekPoint>, MP4Error> {
+        let mut points = vec![];
+        for cursor in self.cursors()? {
+            let target = targets.iter().find(|(id, _)| *id == cursor.track_id).map(|(_, target)| *target);
+            cursor.next = match target.and_then(|it| cursor.table.sample_at(it)) {
+                Some(sample) => cursor.table.sync_sample_before(sample)
+                    .or_else(|| cursor.table.sync_sample_after(sample))
+                    .unwrap_or_default(),
+                None => cursor.table.sample_count()
+            };
+            if let Some(sample) = cursor.table.sample(cursor.next) {
+                points.push(SeekPoint {
+                    track_id: cursor.track_id,
+                    sample: sample.number,
+                    decode_time: sample.decode_time,
+                    offset: sample.offset,
+                    moof_offset: None
+                });
+            }
+        }
+        if let Some(offset) = points.iter().map(|it| it.offset).min() {
+            self.reader.seek(SeekFrom::Start(offset)).await?;
+        }
+        Ok(points)
+    }
+
+    async fn seek_fragmented(&mut self, moov: &Moov, targets: &[(u32, u64)]) -> Result<Vec<SeekPoint>, MP4Error> {
+        let mfra = self.read_mfra().await?;
+        let mut points = vec![];
+        let mut unresolved = vec![];
+        for &(track_id, target) in targets {
+            let entry = mfra.iter()
+                .flat_map(|it| it.tfras.iter())
+                .find(|it| it.track_id == track_id)
+                .and_then(|it| it.entry_before(target));
+            let point = match entry {
+                Some(entry) => {
+                    let moof = self.read_moof(entry.moof_offset).await?;
+                    track_samples(moov, &moof, entry.moof_offset, track_id)
+                        .and_then(|samples| sync_point(&samples, entry.moof_offset, track_id, target))
+                }
+                None => None
+            };
+            match point {
+                Some(point) => points.push(point),
+                None => unresolved.push((track_id, target))
+            }
+        }
+        if !unresolved.is_empty() {
+            points.extend(self.scan_fragments(moov, &unresolved).await?);
+        }
+        if let Some(offset) = points.iter().filter_map(|it| it.moof_offset).min() {
+            self.reader.seek(SeekFrom::Start(offset)).await?;
+        }
+        Ok(points)
+    }
+
+    async fn read_moof(&mut self, position: u64) -> Result<MoofBox, MP4Error> {
+        match self.top_level_header(position).await? {
+            Some((header, _)) if header.id == MoofBox::ID => MoofBox::read(header, &mut self.reader).await,
+            _ => Err(MP4Error::Custom(format!("No moof at {}", position)))
+        }
+    }
+
+    /// The last sync sample at or before its target of every track, looking through the `moof` boxes from the start of the file
+    /// until none of the tracks starts before its target. A track without such a sync sample gets the first point of its first `moof`.
+    async fn scan_fragments(&mut self, moov: &Moov, targets: &[(u32, u64)]) -> Result<Vec<SeekPoint>, MP4Error> {
+        let mut points: Vec<Option<SeekPoint>> = vec![None; targets.len()];
+        let mut firsts: Vec<Option<SeekPoint>> = vec![None; targets.len()];
+        let mut next = Some(0);
+        while let Some(position) = next {
+            let header = match self.top_level_header(position).await? {
+                Some((header, following)) => {
+                    next = following;
+                    header
+                }
+                None => break
+            };
+            if header.id != MoofBox::ID {
+                continue;
+            }
+            let moof = MoofBox::read(header, &mut self.reader).await?;
+            let mut has_tracks = false;
+            let mut started = false;
+            for (i, (track_id, target)) in targets.iter().enumerate() {
+                let samples = match track_samples(moov, &moof, position, *track_id) {
+                    Some(samples) => samples,
+                    None => continue
+                };
+                has_tracks = true;
+                if firsts[i].is_none() {
+                    firsts[i] = first_point(&samples, position, *track_id);
+                }
+                started |= samples.first().and_then(|it| it.decode_time).is_some_and(|it| it <= *target);
+                if let Some(point) = sync_point(&samples, position, *track_id, *target) {
+                    points[i] = Some(point);
+                }
+            }
+            if has_tracks && !started {
+                break;
+            }
+        }
+        Ok(points.into_iter().zip(firsts).filter_map(|(point, first)| point.or(first)).collect())
+    }
+
+    /// Reads the `mfra` at the end of the file, as located by its `mfro`.
+    async fn read_mfra(&mut self) -> Result<Option<MfraBox>, MP4Error> {
+        let end = self.reader.seek(SeekFrom::End(0)).await?;
+        if end < 16 {
+            return Ok(None);
+        }
+        self.reader.seek(SeekFrom::Start(end - 16)).await?;
+        match self.read_header().await? {
+            Some(header) if header.id == MfroBox::ID => {
+                let mfro = MfroBox::read(header, &mut self.reader).await?;
+                let start = match end.checked_sub(mfro.parent_size as u64) {
+                    Some(start) => start,
+                    None => return Ok(None)
+                };
+                match self.top_level_header(start).await? {
+                    Some((header, _)) if header.id == MfraBox::ID => Ok(Some(MfraBox::read(header, &mut self.reader).await?)),
+                    _ => Ok(None)
+                }
+            }
+            _ => Ok(None)
+        }
+    }
+
+    /// Reads the `moov` if it was not met yet, looking through the top level boxes from the start of the file.
+    async fn load_moov(&mut self) -> Result<(), MP4Error> {
+        if self.moov.is_some() {
+            return Ok(());
+        }
+        let position = self.reader.seek(SeekFrom::Current(0)).await?;
+        let mut next = Some(0);
+        while let Some((header, following)) = match next {
+            Some(position) => self.top_level_header(position).await?,
+            None => None
+        } {
+            if header.id == MoovBox::ID {
+                self.moov = Some(MoovBox::read(header, &mut self.reader).await?);
+                break;
+            }
+            next = following;
+        }
+        self.reader.seek(SeekFrom::Start(position)).await?;
+        match self.moov {
+            Some(_) => Ok(()),
+            None => Err(MP4Error::Custom("The file has no moov".to_string()))
+        }
+    }
+
+    fn cursors(&mut self) -> Result<&mut Vec<TrackCursor>, MP4Error> {
+        if self.cursors.is_none() {
+            let mut cursors = vec![];
+            for trak in self.moov.iter().flat_map(|it| it.traks.iter()) {
+                if let (Some(track_id), Some(stbl)) = (trak.track_id(), trak.stbl()) {
+                    cursors.push(TrackCursor { track_id, table: SampleTable::new(stbl)?, next: 0 });
+                }
+            }
+            self.cursors = Some(cursors);
+        }
+        Ok(self.cursors.get_or_insert_with(Vec::new))
+    }
+
+    /// Reads the header of the top level box at `position`, with the position of the following box if the size is known.
+    pub(crate) async fn top_level_header(&mut self, position: u64) -> Result<Option<(BoxHeader, Option<u64>)>, MP4Error> {
+        self.reader.seek(SeekFrom::Start(position)).await?;
+        let header = match self.read_header().await? {
+            Some(header) => header,
+            None => return Ok(None)
+        };
+        let next = match header.size_minus_self() {
+            BoxSize::Known(size) => Some(position + header.byte_size() as u64 + size as u64),
+            BoxSize::Unknown => None
+        };
+        Ok(Some((header, next)))
+    }
+
+    async fn read_header(&mut self) -> Result<Option<BoxHeader>, MP4Error> {
+        match self.reader.read().await {
+            Ok(header) => Ok(Some(header)),
+            Err(MP4Error::IO(e)) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn flush_emsgs(&mut self, segment: Option<&MoofBox>) {
+        for emsg in std::mem::take(&mut self.held_emsgs) {
+            let presentation_time = match emsg.presentation_time {
+                EmsgPresentationTime::Absolute(time) => Some(time),
+                EmsgPresentationTime::Delta(delta) => segment
+                    .and_then(|moof| self.segment_start(moof, emsg.timescale))
+                    .map(|start| start + delta as u64)
+            };
+            self.pending.push_back(Mp4Event::Emsg(EmsgEvent { emsg, presentation_time }));
+        }
+    }
+
+    /// Earliest presentation time of the fragment's tracks, expressed in `timescale` units.
+    /// It is the smallest decode time plus composition offset of the samples, the `tfdt` alone for a track without samples.
+    fn segment_start(&self, moof: &MoofBox, timescale: u32) -> Option<u64> {
+        let moov = self.moov.as_ref()?;
+        moof.trafs.iter().zip(moof.samples(0, Some(moov))).filter_map(|(traf, samples)| {
+            let track_id = traf.tfhd.as_ref()?.track_id;
+            let decode_time = *traf.tfdt.as_ref()?.base_media_decode_time;
+            let presentation_time = samples.iter()
+                .filter_map(|it| Some(it.decode_time?.saturating_add_signed(it.composition_offset)))
+                .min()
+                .unwrap_or(decode_time);
+            let track_timescale = moov.trak(track_id)?.timescale().filter(|it| *it > 0)?;
+            Some((presentation_time as u128 * timescale as u128 / track_timescale as u128) as u64)
+        }).min()
+    }
+}
+
+/// The samples of a track in a `moof`, `None` if it has no `traf` for the track.
+fn track_samples(moov: &Moov, moof: &MoofBox, moof_offset: u64, track_id: u32) -> Option<Vec<ResolvedSample>> {
+    moof.trafs.iter()
+        .zip(moof.samples(moof_offset, Some(moov)))
+        .find(|(traf, _)| traf.track_id() == Some(track_id))
+        .map(|(_, samples)| samples)
+}
+
+/// The last sync sample at or before `target` of the samples of a track in the `moof` at `moof_offset`.
+fn sync_point(samples: &[ResolvedSample], moof_offset: u64, track_id: u32, target: u64) -> Option<SeekPoint> {
+    let index = samples.iter().rposition(|it| it.flags.is_sync() && it.decode_time.is_some_and(|it| it <= target))?;
+    Some(seek_point(samples, index, moof_offset, track_id))
+}
+
+/// The first sync sample of the samples of a track in the `moof` at `moof_offset`, or the first sample if none is.
+fn first_point(samples: &[ResolvedSample], moof_offset: u64, track_id: u32) -> Option<SeekPoint> {
+    if samples.is_empty() {
+        return None;
+    }
+    let index = samples.iter().position(|it| it.flags.is_sync()).unwrap_or_default();
+    Some(seek_point(samples, index, moof_offset, track_id))
+}
+
+fn seek_point(samples: &[ResolvedSample], index: usize, moof_offset: u64, track_id: u32) -> SeekPoint {
+    SeekPoint {
+        track_id,
+        sample: index as u32,
+        decode_time: samples[index].decode_time.unwrap_or_default(),
+        offset: samples[index].offset,
+        moof_offset: Some(moof_offset)
+    }
+}
+
+/// Maps a time of the movie timeline to the media timeline of a track.
+pub(crate) fn media_time(moov: &Moov, trak: &Trak, time: Duration) -> Option<u64> {
+    let timescale = trak.timescale().filter(|it| *it > 0)?;
+    match (trak.elst(), moov.mvhd.as_ref()) {
+        (Some(elst), Some(mvhd)) => elst.media_time(ticks_from_duration(time, mvhd.timescale), mvhd.timescale, timescale),
+        _ => Some(ticks_from_duration(time, timescale))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::demux::{Demuxer, Mp4Event};
+    use crate::error::MP4Error;
+    use crate::fragment::{Fragment, FragmentWriter};
+    use crate::mp4box::box_trait::{BoxWrite, IBox};
+    use crate::mp4box::edts::Edts;
+    use crate::mp4box::elst::{Elst, ElstEntry};
+    use crate::mp4box::emsg::{Emsg, EmsgPresentationTime};
+    use crate::mp4box::ftyp::Ftyp;
+    use crate::mp4box::mdat::MdatBox;
+    use crate::mp4box::mdhd::Mdhd;
+    use crate::mp4box::mdia::Mdia;
+    use crate::mp4box::mfhd::Mfhd;
+    use crate::mp4box::mfra::{Mfra, MfraBox};
+    use crate::mp4box::mfro::Mfro;
+    use crate::mp4box::minf::Minf;
+    use crate::mp4box::moof::Moof;
+    use crate::mp4box::moov::{Moov, MoovBox};
+    use crate::mp4box::mvex::Mvex;
+    use crate::mp4box::stbl::Stbl;
+    use crate::mp4box::stco::{Stco, StcoEntry};
+    use crate::mp4box::stsc::{Stsc, StscEntry};
+    use crate::mp4box::stss::Stss;
+    use crate::mp4box::stts::{Stts, SttsEntry};
+    use crate::mp4box::tfdt::Tfdt;
+    use crate::mp4box::tfhd::Tfhd;
+    use crate::mp4box::tfra::{Tfra, TfraEntry};
+    use crate::mp4box::tkhd::Tkhd;
+    use crate::mp4box::traf::Traf;
+    use crate::mp4box::trak::Trak;
+    use crate::mp4box::trex::{SampleFlags, Trex};
+    use crate::mp4box::trun::{Trun, TrunEntry};
+    use crate::types::array::Mp4VersionedOffsetArray;
+    use crate::types::versioned_signed_int::VersionedSignedU32;
+
+    #[test]
+    pub fn test_emsg_resolution() -> Result<(), MP4Error> {
+        let ftyp = Ftyp { major_brand: *b"iso6", minor_version: 0, compatible_brands: vec![*b"iso6"] };
+        let moov = Moov {
+            mvhd: Some(Default::default()),
+            traks: vec![Trak {
+                tkhd: Some(Tkhd { track_id: 1, ..Default::default() }.into()),
+                edts: None,
+                mdia: Some(Mdia {
+                    mdhd: Some(Mdhd { timescale: 48000, ..Default::default() }.into()),
+                    hdlr: None,
+                    minf: None
+                }.into())
+            }.into()],
+            mvex: None
+        }.into();
+        let moof = Moof {
+            mfhd: Some(Mfhd { sequence_number: 1 }.into()),
+            trafs: vec![Traf {
+                tfhd: Some(Tfhd {
+                    track_id: 1,
+                    base_data_offset: Default::default(),
+                    sample_description_index: Default::default(),
+                    default_sample_duration: Default::default(),
+                    default_sample_size: Default::default(),
+                    default_sample_flags: Default::default(),
+                    flags: Default::default()
+                }.into()),
+                tfdt: Some(Tfdt { base_media_decode_time: 96000u32.into() }.into()),
+                sdtp: None,
+                sbgps: vec![],
+                sgpds: vec![],
+                truns: vec![]
+            }.into()]
+        }.into();
+        let emsg = |presentation_time| Emsg {
+            scheme_id_uri: "https://aomedia.org/emsg/ID3".to_string(),
+            timescale: 1000,
+            presentation_time,
+            ..Default::default()
+        };
+        let mut fragment = Fragment::new(moof, MdatBox(vec![0; 8]))
+            .with_emsg(emsg(EmsgPresentationTime::Delta(500)))
+            .with_emsg(emsg(EmsgPresentationTime::Absolute(1234)));
+        let mut writer = FragmentWriter::new(vec![]);
+        writer.write_init(&ftyp, &moov)?;
+        writer.write_fragment(&mut fragment)?;
+        let buf = writer.into_inner();
+        futures::executor::block_on(async {
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let mut times = vec![];
+            let mut ids = vec![];
+            while let Some(event) = demuxer.next().await? {
+                match event {
+                    Mp4Event::Emsg(event) => times.push(event.presentation_time),
+                    Mp4Event::Ftyp(_) => ids.push("ftyp"),
+                    Mp4Event::Moov(_) => ids.push("moov"),
+                    Mp4Event::Moof(_) => ids.push("moof"),
+                    Mp4Event::Mdat(_) => ids.push("mdat"),
+                    Mp4Event::Unknown(_) => ids.push("????"),
+                }
+            }
+            assert_eq!(ids, ["ftyp", "moov", "moof", "mdat"]);
+            assert_eq!(times, [Some(2500), Some(1234)]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_emsg_earliest_presentation_time() -> Result<(), MP4Error> {
+        let ftyp = Ftyp { major_brand: *b"iso6", minor_version: 0, compatible_brands: vec![*b"iso6"] };
+        let moov = fragmented_moov(&[1]);
+        // decoded at 2000 and 3000, presented at 3000 and 2500
+        let mut fragment = fragment(1, 2000).with_emsg(Emsg {
+            scheme_id_uri: "https://aomedia.org/emsg/ID3".to_string(),
+            timescale: 1000,
+            presentation_time: EmsgPresentationTime::Delta(500),
+            ..Default::default()
+        });
+        for (entry, offset) in fragment.moof.trafs[0].truns[0].entries.data.iter_mut().zip([1000, -500]) {
+            entry.sample_composition_time_offset = VersionedSignedU32::Signed(offset).into();
+        }
+        let mut writer = FragmentWriter::new(vec![]);
+        writer.write_init(&ftyp, &moov)?;
+        writer.write_fragment(&mut fragment)?;
+        let buf = writer.into_inner();
+        futures::executor::block_on(async {
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let mut times = vec![];
+            while let Some(event) = demuxer.next().await? {
+                if let Mp4Event::Emsg(event) = event {
+                    times.push(event.presentation_time);
+                }
+            }
+            assert_eq!(times, [Some(3000)]);
+            Ok(())
+        })
+    }
+
+    fn trak(stbl: Option<Stbl>, edts: Option<Edts>) -> Trak {
+        Trak {
+            tkhd: Some(Tkhd { track_id: 1, ..Default::default() }.into()),
+            edts: edts.map(Into::into),
+            mdia: Some(Mdia {
+                mdhd: Some(Mdhd { timescale: 1000, ..Default::default() }.into()),
+                hdlr: None,
+                minf: Some(Minf {
+                    vmhd: None,
+                    smhd: None,
+                    dinf: None,
+                    stbl: stbl.map(Into::into)
+                }.into())
+            }.into())
+        }
+    }
+
+    #[test]
+    pub fn test_seek_progressive() -> Result<(), MP4Error> {
+        let ftyp = Ftyp { major_brand: *b"isom", minor_version: 0, compatible_brands: vec![*b"isom"] };
+        let mdat = MdatBox((0..6u8).flat_map(|it| [it; 4]).collect());
+        let mut stbl = Stbl {
+            co64: None,
+            stco: Some(Stco { entries: vec![StcoEntry { chunk_offset: ftyp.byte_size() as u32 + 8 }].into() }.into()),
+            stsc: Some(Stsc { entries: vec![StscEntry { first_chunk: 1, samples_per_chunk: 6, sample_description_index: 1 }].into() }.into()),
+            stsd: None,
+            stss: Some(Stss { sample_numbers: vec![2, 4, 6].into() }.into()),
+            stsz: None,
+            stz2: None,
+            stts: Some(Stts { samples: vec![SttsEntry { sample_count: 6, sample_delta: 1000 }].into() }.into()),
+            ctts: None,
+            sdtp: None,
+            sbgps: vec![],
+            sgpds: vec![]
+        };
+        stbl.set_sample_sizes(&[4; 6], false);
+        // the media starts 1s into the movie
+        let edts = Edts {
+            elst: Some(Elst {
+                entries: vec![
+                    ElstEntry { segment_duration: 1000, media_time: -1, ..Default::default() },
+                    ElstEntry { segment_duration: 6000, media_time: 0, ..Default::default() },
+                ]
+            }.into())
+        };
+        let moov = Moov {
+            mvhd: Some(Default::default()),
+            traks: vec![trak(Some(stbl), Some(edts)).into()],
+            mvex: None
+        };
+        let mut buf = vec![];
+        ftyp.write(&mut buf)?;
+        mdat.write(&mut buf)?;
+        MoovBox::from(moov).write(&mut buf)?;
+        futures::executor::block_on(async {
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let first = demuxer.next_sample().await?.unwrap();
+            assert_eq!((first.number, first.data), (0, vec![0; 4]));
+            let points = demuxer.seek(Duration::from_millis(5500)).await?;
+            assert_eq!(points.len(), 1);
+            assert_eq!((points[0].sample, points[0].decode_time), (3, 3000));
+            let sample = demuxer.next_sample().await?.unwrap();
+            assert_eq!((sample.number, sample.sync, sample.data), (3, true, vec![3; 4]));
+            let sample = demuxer.next_sample().await?.unwrap();
+            assert_eq!((sample.number, sample.sync), (4, false));
+            assert!(demuxer.seek(Duration::from_secs(60)).await?.is_empty());
+            assert_eq!(demuxer.next_sample().await?, None);
+            // the first sample is not a sync sample
+            let points = demuxer.seek(Duration::ZERO).await?;
+            assert_eq!((points[0].sample, points[0].decode_time), (1, 1000));
+            Ok(())
+        })
+    }
+
+    fn fragmented_moov(track_ids: &[u32]) -> MoovBox {
+        Moov {
+            mvhd: Some(Default::default()),
+            traks: track_ids.iter().map(|track_id| Trak {
+                tkhd: Some(Tkhd { track_id: *track_id, ..Default::default() }.into()),
+                ..trak(None, None)
+            }.into()).collect(),
+            mvex: Some(Mvex {
+                mehd: None,
+                trex: track_ids.iter().map(|track_id| Trex {
+                    track_id: *track_id,
+                    default_sample_description_index: 1,
+                    default_sample_duration: 1000,
+                    default_sample_size: 4,
+                    default_sample_flags: Default::default()
+                }.into()).collect(),
+                trep: vec![]
+            }.into())
+        }.into()
+    }
+
+    /// A sync sample followed by a non sync one.
+    fn fragment(track_id: u32, decode_time: u32) -> Fragment {
+        Fragment::new(Moof {
+            mfhd: None,
+            trafs: vec![Traf {
+                tfhd: Some(Tfhd {
+                    track_id,
+                    base_data_offset: Default::default(),
+                    sample_description_index: Default::default(),
+                    default_sample_duration: Default::default(),
+                    default_sample_size: Default::default(),
+                    default_sample_flags: Default::default(),
+                    flags: Default::default()
+                }.into()),
+                tfdt: Some(Tfdt { base_media_decode_time: decode_time.into() }.into()),
+                sdtp: None,
+                sbgps: vec![],
+                sgpds: vec![],
+                truns: vec![Trun {
+                    entries: Mp4VersionedOffsetArray::new([SampleFlags::sync(), SampleFlags::non_sync_depends()].map(|flags| TrunEntry {
+                        sample_flags: flags.into(),
+                        ..Default::default()
+                    }).to_vec(), Default::default())
+                }.into()]
+            }.into()]
+        }.into(), MdatBox(vec![0; 8]))
+    }
+
+    #[test]
+    pub fn test_seek_fragmented() -> Result<(), MP4Error> {
+        let ftyp = Ftyp { major_brand: *b"iso6", minor_version: 0, compatible_brands: vec![*b"iso6"] };
+        let moov = fragmented_moov(&[1]);
+        let mut writer = FragmentWriter::new(vec![]);
+        writer.write_init(&ftyp, &moov)?;
+        let mut offsets = vec![];
+        let mut moof_sizes = vec![];
+        for decode_time in [0, 2000, 4000] {
+            let mut fragment = fragment(1, decode_time);
+            offsets.push(writer.position());
+            writer.write_fragment(&mut fragment)?;
+            moof_sizes.push(fragment.moof.byte_size() as u64);
+        }
+        let buf = writer.into_inner();
+        futures::executor::block_on(async {
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf.clone()));
+            let points = demuxer.seek(Duration::from_millis(3500)).await?;
+            assert_eq!(points.len(), 1);
+            assert_eq!((points[0].sample, points[0].decode_time), (0, 2000));
+            assert_eq!(points[0].offset, offsets[1] + moof_sizes[1] + 8);
+            assert_eq!(points[0].moof_offset, Some(offsets[1]));
+            match demuxer.next().await? {
+                Some(Mp4Event::Moof(moof)) => assert_eq!(moof.trafs[0].tfdt.as_ref().map(|it| *it.base_media_decode_time), Some(2000)),
+                event => panic!("expected a moof, got {:?}", event)
+            }
+
+            // the tfra only lists the first fragment
+            let mut buf = buf;
+            let mut mfra = Mfra {
+                tfras: vec![Tfra {
+                    track_id: 1,
+                    entries: vec![TfraEntry { time: 0, moof_offset: offsets[0], traf_number: 1, trun_number: 1, sample_number: 1 }]
+                }],
+                mfro: Some(Mfro { parent_size: 0 }.into())
+            };
+            let size = MfraBox::from(mfra.clone()).byte_size() as u32;
+            mfra.mfro = Some(Mfro { parent_size: size }.into());
+            MfraBox::from(mfra).write(&mut buf)?;
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let points = demuxer.seek(Duration::from_millis(3500)).await?;
+            assert_eq!((points[0].sample, points[0].decode_time), (0, 0));
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_seek_fragmented_tracks() -> Result<(), MP4Error> {
+        let ftyp = Ftyp { major_brand: *b"iso6", minor_version: 0, compatible_brands: vec![*b"iso6"] };
+        let mut writer = FragmentWriter::new(vec![]);
+        writer.write_init(&ftyp, &fragmented_moov(&[1, 2]))?;
+        let mut offsets = vec![];
+        for (track_id, decode_time) in [(1, 0), (2, 0), (1, 2000), (2, 2000), (1, 4000), (2, 4000)] {
+            offsets.push(writer.position());
+            writer.write_fragment(&mut fragment(track_id, decode_time))?;
+        }
+        let buf = writer.into_inner();
+        futures::executor::block_on(async {
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let points = demuxer.seek(Duration::from_millis(3500)).await?;
+            let found = points.iter().map(|it| (it.track_id, it.decode_time, it.moof_offset)).collect::<Vec<_>>();
+            assert_eq!(found, [(1, 2000, Some(offsets[2])), (2, 2000, Some(offsets[3]))]);
+            match demuxer.next().await? {
+                Some(Mp4Event::Moof(moof)) => assert_eq!(moof.trafs[0].track_id(), Some(1)),
+                event => panic!("expected a moof, got {:?}", event)
+            }
+
+            // track 2 starts after the target
+            let mut writer = FragmentWriter::new(vec![]);
+            writer.write_init(&ftyp, &fragmented_moov(&[1, 2]))?;
+            writer.write_fragment(&mut fragment(1, 0))?;
+            let offset = writer.position();
+            writer.write_fragment(&mut fragment(2, 2000))?;
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(writer.into_inner()));
+            let points = demuxer.seek(Duration::from_millis(1500)).await?;
+            let found = points.iter().map(|it| (it.track_id, it.sample, it.decode_time)).collect::<Vec<_>>();
+            assert_eq!(found, [(1, 0, 0), (2, 0, 2000)]);
+            assert_eq!(points[1].moof_offset, Some(offset));
+            assert_eq!(demuxer.seek(Duration::ZERO).await?.len(), 2);
+            Ok(())
+        })
+    }
+
+}
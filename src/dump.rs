@@ -0,0 +1,260 @@
+use std::fmt::{Display, Formatter};
+use std::io::SeekFrom;
+use futures::{AsyncReadExt, AsyncSeekExt};
+use crate::bytes_read::ReadMp4;
+use crate::bytes_reserve::Mp4Reservable;
+use crate::error::MP4Error;
+use crate::header::BoxHeader;
+use crate::mp4box::box_trait::{BoxRead, IBox};
+use crate::mp4box::co64::Co64Box;
+use crate::mp4box::ctts::CttsBox;
+use crate::mp4box::dinf::DinfBox;
+use crate::mp4box::dref::DrefBox;
+use crate::mp4box::edts::EdtsBox;
+use crate::mp4box::elst::ElstBox;
+use crate::mp4box::emsg::EmsgBox;
+use crate::mp4box::ftyp::FtypBox;
+use crate::mp4box::hdlr::HdlrBox;
+use crate::mp4box::mdat::MdatBox;
+use crate::mp4box::mdhd::MdhdBox;
+use crate::mp4box::mdia::MdiaBox;
+use crate::mp4box::mehd::MehdBox;
+use crate::mp4box::mfhd::MfhdBox;
+use crate::mp4box::mfra::MfraBox;
+use crate::mp4box::mfro::MfroBox;
+use crate::mp4box::minf::MinfBox;
+use crate::mp4box::moof::MoofBox;
+use crate::mp4box::moov::MoovBox;
+use crate::mp4box::mvex::MvexBox;
+use crate::mp4box::mvhd::MvhdBox;
+use crate::mp4box::sbgp::SbgpBox;
+use crate::mp4box::sdtp::SdtpBox;
+use crate::mp4box::sgpd::SgpdBox;
+use crate::mp4box::sidx::SidxBox;
+use crate::mp4box::smhd::SmhdBox;
+use crate::mp4box::stbl::StblBox;
+use crate::mp4box::stco::StcoBox;
+use crate::mp4box::stsc::StscBox;
+use crate::mp4box::stsd::StsdBox;
+use crate::mp4box::stss::StssBox;
+use crate::mp4box::stsz::StszBox;
+use crate::mp4box::stts::SttsBox;
+use crate::mp4box::stz2::Stz2Box;
+use crate::mp4box::tfdt::TfdtBox;
+use crate::mp4box::tfhd::TfhdBox;
+use crate::mp4box::tfra::TfraBox;
+use crate::mp4box::tkhd::TkhdBox;
+use crate::mp4box::traf::TrafBox;
+use crate::mp4box::trak::TrakBox;
+use crate::mp4box::trep::TrepBox;
+use crate::mp4box::trex::TrexBox;
+use crate::mp4box::trun::TrunBox;
+use crate::mp4box::vmhd::VmhdBox;
+use crate::r#type::BoxType;
+use crate::size::BoxSize;
+
+/// Number of payload bytes shown for boxes we can not decode.
+const PREVIEW_SIZE: usize = 32;
+
+/// A box of the tree walked by [`dump`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DumpedBox {
+    /// number of parent boxes
+    pub depth: usize,
+    /// position of the header in the file
+    pub offset: u64,
+    pub header: BoxHeader,
+    /// size of the header as read, 8 more bytes when it has a 64 bit size
+    pub header_size: u64,
+    /// size of the box, header included
+    pub size: u64,
+    /// `version` and `flags` of full boxes
+    pub version_flags: Option<(u8, u32)>,
+    /// `Debug` output of the boxes we decode, or the error met decoding them
+    pub fields: Option<String>,
+    /// first bytes of the payload of the boxes we do not know
+    pub preview: Option<Vec<u8>>,
+}
+
+impl Display for DumpedBox {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let indent = "  ".repeat(self.depth);
+        let header = match self.header.size {
+            BoxSize::Known(_) if self.header_size > (u32::BYTE_SIZE + self.header.id.byte_size()) as u64 => "large",
+            BoxSize::Known(_) => "compact",
+            BoxSize::Unknown => "to end of file"
+        };
+        write!(f, "{}[{}] offset={} size={} header={}", indent, self.header.id, self.offset, self.size, header)?;
+        if let Some((version, flags)) = self.version_flags {
+            write!(f, " version={} flags={:#08x}", version, flags)?;
+        }
+        if let Some(fields) = &self.fields {
+            for line in fields.lines() {
+                write!(f, "\n{}  {}", indent, line)?;
+            }
+        }
+        if let Some(preview) = &self.preview {
+            write!(f, "\n{}  ", indent)?;
+            for byte in preview {
+                write!(f, "{:02x} ", byte)?;
+            }
+            if self.size.saturating_sub(self.header_size) > preview.len() as u64 {
+                write!(f, "...")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Boxes whose payload is only made of boxes.
+const CONTAINERS: [BoxType; 11] = [
+    MoovBox::ID, TrakBox::ID, EdtsBox::ID, MdiaBox::ID, MinfBox::ID, DinfBox::ID, StblBox::ID,
+    MvexBox::ID, MoofBox::ID, TrafBox::ID, MfraBox::ID
+];
+
+/// Walks every box of a file, parents before their children, from the start to the end of the stream.
+/// Known boxes are decoded, a box that fails to decode is reported in its [`DumpedBox::fields`] without stopping the walk.
+/// A box too small for its own header is reported the same way, the walk goes on after its declared size.
+pub async fn dump<R: ReadMp4>(reader: &mut R) -> Result<Vec<DumpedBox>, MP4Error> {
+    let end = reader.seek(SeekFrom::End(0)).await?;
+    let mut boxes = vec![];
+    // position of the next box, end of its parent and depth
+    let mut stack = vec![(0u64, end, 0usize)];
+    while let Some((offset, parent_end, depth)) = stack.pop() {
+        if offset + 8 > parent_end {
+            continue;
+        }
+        reader.seek(SeekFrom::Start(offset)).await?;
+        let header: BoxHeader = reader.read().await?;
+        let start = reader.seek(SeekFrom::Current(0)).await?;
+        let box_end = match header.size {
+            BoxSize::Known(size) => offset.saturating_add(size as u64).min(parent_end),
+            BoxSize::Unknown => parent_end
+        };
+        // a box of size 0 gives no way to find the next one
+        stack.push((if box_end > offset { box_end } else { parent_end }, parent_end, depth));
+
+        let mut dumped = DumpedBox {
+            depth,
+            offset,
+            header,
+            header_size: start - offset,
+            size: box_end.saturating_sub(offset),
+            version_flags: None,
+            fields: None,
+            preview: None
+        };
+        if box_end < start {
+            dumped.fields = Some(format!("error: the box ends at {} before the end of its {} byte header", box_end, start - offset));
+        } else if CONTAINERS.contains(&header.id) {
+            stack.push((start, box_end, depth + 1));
+        } else if header.id != MdatBox::ID {
+            // the decoders find the payload from the header size, which is only right for the smallest header
+            let payload = BoxHeader::from_id_and_inner_size(header.id, (box_end - start) as usize);
+            match decode(payload, reader).await {
+                Ok(Some(fields)) => {
+                    dumped.fields = Some(fields.0);
+                    if fields.1 {
+                        reader.seek(SeekFrom::Start(start)).await?;
+                        let version_flags: u32 = reader.read().await?;
+                        dumped.version_flags = Some(((version_flags >> 24) as u8, version_flags & 0xFF_FF_FF));
+                    }
+                }
+                Ok(None) => {
+                    reader.seek(SeekFrom::Start(start)).await?;
+                    let mut preview = vec![0u8; (box_end - start).min(PREVIEW_SIZE as u64) as usize];
+                    reader.read_exact(&mut preview).await?;
+                    dumped.preview = Some(preview);
+                }
+                Err(e) => dumped.fields = Some(format!("error: {}", e))
+            }
+        }
+        boxes.push(dumped);
+    }
+    Ok(boxes)
+}
+
+/// The `Debug` output of a known box and whether it is a full box, `None` for the other boxes.
+/// Full boxes are printed without their `MP4Box` and `FullBox` wrappers, unless they implement `BoxRead` themselves.
+async fn decode<R: ReadMp4>(header: BoxHeader, reader: &mut R) -> Result<Option<(String, bool)>, MP4Error> {
+    macro_rules! decode {
+        (plain: $($plain:ty),*; full: $($full:ty),*; unwrapped: $($unwrapped:ty),*) => {
+            $(if header.id == <$plain>::ID {
+                return Ok(Some((format!("{:#?}", <$plain>::read(header, reader).await?), false)));
+            })*
+            $(if header.id == <$unwrapped>::ID {
+                return Ok(Some((format!("{:#?}", <$unwrapped>::read(header, reader).await?), true)));
+            })*
+            $(if header.id == <$full>::ID {
+                return Ok(Some((format!("{:#?}", <$full>::read(header, reader).await?.inner.inner), true)));
+            })*
+        };
+    }
+    decode!(
+        plain: FtypBox;
+        full: MvhdBox, TkhdBox, MdhdBox, HdlrBox, VmhdBox, SmhdBox, DrefBox, StsdBox, SttsBox, CttsBox, StscBox,
+            StszBox, Stz2Box, StcoBox, Co64Box, StssBox, ElstBox, MehdBox, TrexBox, TrepBox, MfhdBox, TfhdBox, TfdtBox,
+            TrunBox, MfroBox, SidxBox;
+        unwrapped: SdtpBox, SbgpBox, SgpdBox, TfraBox, EmsgBox
+    );
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dump::dump;
+    use crate::error::MP4Error;
+    use crate::id::BoxId;
+    use crate::mp4box::box_trait::BoxWrite;
+    use crate::mp4box::box_unknown::UnknownBox;
+    use crate::r#type::BoxType;
+    use crate::remux::test::progressive;
+
+    #[test]
+    pub fn test_dump() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let mut buf = progressive()?;
+            UnknownBox { id: BoxType::Id(BoxId(*b"free")), data: (0..40).collect() }.write(&mut buf)?;
+            let boxes = dump(&mut futures::io::Cursor::new(buf.clone())).await?;
+
+            let ids: Vec<_> = boxes.iter().filter(|it| it.depth <= 1).map(|it| it.header.id.to_string()).collect();
+            assert_eq!(ids, ["ftyp", "mdat", "moov", "mvhd", "trak", "trak", "free"]);
+            assert_eq!(boxes.iter().filter(|it| it.depth == 0).map(|it| it.size).sum::<u64>(), buf.len() as u64);
+            assert_eq!(boxes.iter().filter(|it| it.header.id.to_string() == "stbl").count(), 2);
+
+            let ctts = boxes.iter().find(|it| it.header.id.to_string() == "ctts").unwrap();
+            assert_eq!(ctts.depth, 5);
+            assert_eq!(ctts.version_flags, Some((1, 0)));
+            assert!(ctts.fields.as_ref().unwrap().contains("sample_offset: -1000"));
+
+            let free = boxes.last().unwrap();
+            assert_eq!(free.preview, Some((0..32).collect()));
+            assert!(free.to_string().ends_with("1e 1f ..."));
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_dump_headers() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let boxes = dump(&mut futures::io::Cursor::new(vec![0, 0, 0, 4, b'a', b'b', b'c', b'd'])).await?;
+            assert_eq!(boxes.len(), 1);
+            assert_eq!(boxes[0].size, 4);
+            assert!(boxes[0].fields.as_ref().unwrap().starts_with("error: "));
+
+            let mut buf = vec![0, 0, 0, 1, b'm', b'f', b'h', b'd', 0, 0, 0, 0, 0, 0, 0, 24, 0, 0, 0, 0, 0, 0, 0, 7];
+            buf.extend_from_slice(&[0, 0, 0, 1, b'f', b'r', b'e', b'e', 0, 0, 0, 0, 0, 0, 0, 20, 1, 2, 3, 4]);
+            buf.extend_from_slice(&[0, 0, 0, 3, b'f', b'r', b'e', b'e', 0, 0, 0, 8, b's', b'k', b'i', b'p']);
+            let boxes = dump(&mut futures::io::Cursor::new(buf)).await?;
+            assert_eq!(boxes.iter().map(|it| it.offset).collect::<Vec<_>>(), [0, 24, 44, 47]);
+            assert_eq!(boxes[0].header_size, 16);
+            assert!(boxes[0].to_string().contains("header=large"));
+            assert_eq!(boxes[0].version_flags, Some((0, 0)));
+            assert!(boxes[0].fields.as_ref().unwrap().contains("sequence_number: 7"));
+            assert_eq!(boxes[1].preview, Some(vec![1, 2, 3, 4]));
+            assert!(boxes[1].to_string().contains("header=large"));
+            assert!(boxes[2].fields.as_ref().unwrap().starts_with("error: "));
+            Ok(())
+        })
+    }
+}
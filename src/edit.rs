@@ -0,0 +1,603 @@
+use std::io::SeekFrom;
+use std::time::Duration;
+use futures::{AsyncReadExt, AsyncSeekExt};
+use crate::bytes_read::ReadMp4;
+use crate::bytes_write::WriteMp4;
+use crate::demux::{media_time, Demuxer};
+use crate::error::MP4Error;
+use crate::fragment::{Fragment, FragmentWriter};
+use crate::mp4box::edts::Edts;
+use crate::mp4box::elst::{Elst, ElstEntry};
+use crate::mp4box::mdat::MdatBox;
+use crate::mp4box::mfhd::Mfhd;
+use crate::mp4box::moof::{Moof, MoofBox};
+use crate::mp4box::moov::{Moov, MoovBox};
+use crate::mp4box::trak::Trak;
+use crate::mp4box::traf::{ResolvedSample, Traf};
+use crate::mp4box::trex::Trex;
+use crate::remux::{fragment_traf, fragmented_ftyp, interleaved_chunks, read_boxes, write_progressive, ProgressiveTrack};
+use crate::sample_table::SampleTable;
+use crate::types::duration::{duration_from_ticks, ticks_from_duration};
+
+/// Copies the part of a file between `start` and `end`, or the end of the file, on the movie timeline, without re-encoding.
+///
+/// Every track starts at its last sync sample at or before `start` and its decode times are shifted to start at 0.
+/// An edit list hides the samples before `start` so that playback begins at the requested time, it replaces the source edit list.
+/// Progressive sources give a progressive file, fragmented sources a fragmented one. Returns the number of bytes written.
+/// `allow_stz2` lets a progressive file store its sample sizes in a `stz2` when it is smaller than a `stsz`.
+pub async fn cut<R: ReadMp4, W: WriteMp4>(reader: R, start: Duration, end: Option<Duration>, allow_stz2: bool, writer: &mut W) -> Result<usize, MP4Error> {
+    let mut demuxer = Demuxer::new(reader);
+    let (source, moofs) = read_boxes(&mut demuxer).await?;
+    let movie_timescale = source.mvhd.as_ref().map(|it| it.timescale).unwrap_or(1000);
+    let duration = end.map(|end| ticks_from_duration(end.saturating_sub(start), movie_timescale));
+    // the media times of the range on the timeline of each track
+    let ranges = source.traks.iter().map(|trak| {
+        let first = media_time(&source, trak, start)?;
+        let last = match end {
+            Some(end) => media_time(&source, trak, end)?,
+            None => u64::MAX
+        };
+        Some((first, last))
+    }).collect::<Vec<_>>();
+    if source.mvex.is_none() {
+        cut_progressive(demuxer.get_mut(), &source, &ranges, duration, allow_stz2, writer).await
+    } else {
+        cut_fragmented(demuxer.get_mut(), &source, &moofs, &ranges, duration, writer).await
+    }
+}
+
+async fn cut_progressive<R: ReadMp4, W: WriteMp4>(
+    reader: &mut R,
+    source: &Moov,
+    ranges: &[Option<(u64, u64)>],
+    duration: Option<u64>,
+    allow_stz2: bool,
+    writer: &mut W
+) -> Result<usize, MP4Error> {
+    let mut moov = source.clone();
+    let mut tracks = vec![];
+    let mut chunks = vec![];
+    for (index, (trak, range)) in moov.traks.iter_mut().zip(ranges).enumerate() {
+        let mut samples = vec![];
+        if let (Some(stbl), Some((first, last))) = (trak.stbl(), range) {
+            let table = SampleTable::new(stbl)?;
+            if let Some(number) = table.sample_at(*first).and_then(|it| table.sync_sample_before(it)) {
+                samples = (number..).map_while(|it| table.sample(it))
+                    .take_while(|it| it.decode_time < *last)
+                    .collect();
+            }
+        }
+        let timescale = trak.timescale().filter(|it| *it > 0).unwrap_or(1000);
+        match (samples.first().map(|it| it.decode_time), range) {
+            (Some(shift), Some((first, _))) => {
+                for sample in &mut samples {
+                    sample.decode_time -= shift;
+                }
+                let media_duration = samples.iter().map(|it| it.duration as u64).sum::<u64>();
+                set_edit(trak, source, duration, first - shift, media_duration);
+            }
+            _ => trak.edts = None
+        }
+        chunks.extend(interleaved_chunks(0, index, timescale, &samples));
+        tracks.push(ProgressiveTrack {
+            track_id: trak.track_id().unwrap_or_default(),
+            samples: samples.into_iter().map(ResolvedSample::from).collect()
+        });
+    }
+    chunks.sort_by_key(|(start, _)| *start);
+    let chunks = chunks.into_iter().map(|(_, chunk)| chunk).collect::<Vec<_>>();
+    write_progressive(std::slice::from_mut(reader), &moov, &tracks, &chunks, allow_stz2, writer).await
+}
+
+async fn cut_fragmented<R: ReadMp4, W: WriteMp4>(
+    reader: &mut R,
+    source: &Moov,
+    moofs: &[(u64, MoofBox)],
+    ranges: &[Option<(u64, u64)>],
+    duration: Option<u64>,
+    writer: &mut W
+) -> Result<usize, MP4Error> {
+    let samples = moofs.iter()
+        .map(|(position, moof)| moof.samples(*position, Some(source)))
+        .collect::<Vec<_>>();
+    let track_samples = |track_id: u32| moofs.iter().zip(&samples)
+        .flat_map(|((_, moof), samples)| moof.trafs.iter().zip(samples))
+        .filter(move |(traf, _)| traf.track_id() == Some(track_id))
+        .flat_map(|(_, samples)| samples.iter());
+
+    let mut moov = source.clone();
+    // the decode time each track starts at, its last sync sample at or before the start of the range
+    let mut shifts = vec![];
+    for (trak, range) in moov.traks.iter_mut().zip(ranges) {
+        let track_id = trak.track_id().unwrap_or_default();
+        let shift = range.and_then(|(first, _)| {
+            let mut syncs = track_samples(track_id)
+                .filter(|it| it.flags.is_sync())
+                .filter_map(|it| it.decode_time);
+            let earliest = syncs.next()?;
+            Some(syncs.take_while(|it| *it <= first).last().unwrap_or(earliest))
+        });
+        match (shift, range) {
+            (Some(shift), Some((first, last))) => {
+                let media_duration = track_samples(track_id)
+                    .filter(|it| it.decode_time.is_some_and(|time| time >= shift && time < *last))
+                    .map(|it| it.duration as u64)
+                    .sum::<u64>();
+                set_edit(trak, source, duration, first.saturating_sub(shift), media_duration);
+            }
+            _ => trak.edts = None
+        }
+        shifts.push(shift.zip(*range).map(|(shift, (_, last))| (track_id, shift, last)));
+    }
+    if let Some(mehd) = moov.mvex.as_mut().map(|it| &mut it.mehd) {
+        *mehd = None;
+    }
+
+    let mut fragment_writer = FragmentWriter::new(writer);
+    fragment_writer.write_init(&fragmented_ftyp(), &MoovBox::from(moov.clone()))?;
+    let mut sequence_number = 0;
+    for ((_, moof), samples) in moofs.iter().zip(&samples) {
+        let mut trafs = vec![];
+        let mut data = vec![];
+        for (traf, samples) in moof.trafs.iter().zip(samples) {
+            let (track_id, shift, last) = match shifts.iter().flatten().find(|(id, _, _)| Some(*id) == traf.track_id()) {
+                Some(track) => *track,
+                None => continue
+            };
+            let kept = samples.iter()
+                .filter(|it| it.decode_time.is_some_and(|time| time >= shift && time < last))
+                .map(|it| ResolvedSample { decode_time: it.decode_time.map(|time| time - shift), ..*it })
+                .collect::<Vec<_>>();
+            if kept.is_empty() {
+                continue;
+            }
+            let trex = moov.trex(track_id).map(|it| it.inner.inner.clone()).unwrap_or(Trex {
+                track_id,
+                default_sample_description_index: 1,
+                default_sample_duration: 0,
+                default_sample_size: 0,
+                default_sample_flags: Default::default()
+            });
+            let sample_description_index = traf.tfhd.as_ref().and_then(|it| *it.sample_description_index)
+                .unwrap_or(trex.default_sample_description_index);
+            for sample in &kept {
+                reader.seek(SeekFrom::Start(sample.offset)).await?;
+                let start = data.len();
+                data.resize(start + sample.size as usize, 0);
+                reader.read_exact(&mut data[start..]).await?;
+            }
+            trafs.push(fragment_traf(&trex, sample_description_index, &kept));
+        }
+        if trafs.is_empty() {
+            continue;
+        }
+        sequence_number += 1;
+        let moof = Moof {
+            mfhd: Some(Mfhd { sequence_number }.into()),
+            trafs
+        };
+        fragment_writer.write_fragment(&mut Fragment::new(moof.into(), MdatBox(data)))?;
+    }
+    Ok(fragment_writer.position() as usize)
+}
+
+/// Replaces the edit list of a track with a single edit starting at `media_time`,
+/// lasting `duration` in the `mvhd` timescale or until the end of the media.
+fn set_edit(trak: &mut Trak, source: &Moov, duration: Option<u64>, media_time: u64, media_duration: u64) {
+    let movie_timescale = source.mvhd.as_ref().map(|it| it.timescale).unwrap_or(1000);
+    let timescale = trak.timescale().filter(|it| *it > 0).unwrap_or(1000);
+    let available = (media_duration.saturating_sub(media_time) as u128 * movie_timescale as u128 / timescale as u128) as u64;
+    let segment_duration = duration.map(|it| it.min(available)).unwrap_or(available);
+    trak.edts = Some(Edts {
+        elst: Some(Elst {
+            entries: vec![ElstEntry { segment_duration, media_time: media_time as i64, ..Default::default() }]
+        }.into())
+    }.into());
+}
+
+/// Joins files one after the other, without re-encoding. Returns the number of bytes written.
+///
+/// The inputs must have the same tracks, in the same order and with the same handlers and timescales, they keep the `track_id`s of the first input.
+/// Sample descriptions are shared when they are identical, codec configuration included, the others are added to the `stsd`
+/// and the samples point at them through their sample description index.
+/// Every input starts at the end of the longest track of the previous one, the edit list of the first input is kept and extended to the end.
+/// Progressive inputs give a progressive file, fragmented inputs a fragmented one, the media data is copied sample by sample.
+/// `allow_stz2` lets a progressive file store its sample sizes in a `stz2` when it is smaller than a `stsz`.
+pub async fn concat<R: ReadMp4, W: WriteMp4>(inputs: Vec<R>, allow_stz2: bool, writer: &mut W) -> Result<usize, MP4Error> {
+    let mut sources = vec![];
+    let mut readers = vec![];
+    for reader in inputs {
+        let mut demuxer = Demuxer::new(reader);
+        sources.push(read_boxes(&mut demuxer).await?);
+        readers.push(demuxer.into_inner());
+    }
+    let mut moov = sources.first()
+        .map(|(moov, _)| (**moov).clone())
+        .ok_or_else(|| MP4Error::Custom("No input to concatenate".to_string()))?;
+    let fragmented = moov.mvex.is_some();
+
+    // the output sample description index of every sample description of every input track
+    let mut descriptions = vec![];
+    for (input, (source, _)) in sources.iter().enumerate() {
+        if source.mvex.is_some() != fragmented {
+            return Err(MP4Error::Custom("Can not concatenate progressive and fragmented inputs".to_string()));
+        }
+        if source.traks.len() != moov.traks.len() {
+            return Err(MP4Error::Custom(format!("Input {} has {} tracks instead of {}", input, source.traks.len(), moov.traks.len())));
+        }
+        let mut indices = vec![];
+        for (index, (trak, output)) in source.traks.iter().zip(moov.traks.iter_mut()).enumerate() {
+            if trak.handler_type() != output.handler_type() || trak.timescale() != output.timescale() {
+                return Err(MP4Error::Custom(format!("Track {} of input {} does not match the first input", index, input)));
+            }
+            let entries = trak.stbl().and_then(|it| it.stsd.as_ref()).map(|it| it.entries.0.clone()).unwrap_or_default();
+            let stsd = output.mdia.as_mut()
+                .and_then(|it| it.minf.as_mut())
+                .and_then(|it| it.stbl.as_mut())
+                .and_then(|it| it.stsd.as_mut());
+            indices.push(match stsd {
+                Some(stsd) => entries.into_iter().map(|entry| {
+                    let position = stsd.entries.0.iter().position(|it| *it == entry).unwrap_or_else(|| {
+                        stsd.entries.0.push(entry);
+                        stsd.entries.0.len() - 1
+                    });
+                    position as u32 + 1
+                }).collect(),
+                None => vec![]
+            });
+        }
+        descriptions.push(indices);
+    }
+    let description = |input: usize, track: usize, index: u32| descriptions[input][track]
+        .get((index as usize).wrapping_sub(1))
+        .copied()
+        .unwrap_or(index);
+    for trak in &mut moov.traks {
+        if let Some(last) = trak.edts.as_mut().and_then(|it| it.elst.as_mut()).and_then(|it| it.entries.last_mut()) {
+            if !last.is_empty() {
+                last.segment_duration = 0;
+            }
+        }
+    }
+    let timescales = moov.traks.iter()
+        .map(|it| it.timescale().filter(|it| *it > 0).unwrap_or(1000))
+        .collect::<Vec<_>>();
+
+    if !fragmented {
+        let mut tracks = moov.traks.iter()
+            .map(|it| ProgressiveTrack { track_id: it.track_id().unwrap_or_default(), samples: vec![] })
+            .collect::<Vec<_>>();
+        let mut ends = vec![0u64; tracks.len()];
+        let mut chunks = vec![];
+        let mut offset = Duration::ZERO;
+        for (input, (source, _)) in sources.iter().enumerate() {
+            let mut duration = Duration::ZERO;
+            for (index, trak) in source.traks.iter().enumerate() {
+                let table = match trak.stbl() {
+                    Some(stbl) => SampleTable::new(stbl)?,
+                    None => continue
+                };
+                let track = &mut tracks[index];
+                let start = ends[index].max(ticks_from_duration(offset, timescales[index]));
+                if let Some(last) = track.samples.last_mut() {
+                    last.duration = last.duration.saturating_add((start - ends[index]).min(u32::MAX as u64) as u32);
+                }
+                let mut samples = table.samples().collect::<Vec<_>>();
+                for sample in &mut samples {
+                    sample.decode_time += start;
+                    sample.sample_description_index = description(input, index, sample.sample_description_index);
+                }
+                chunks.extend(interleaved_chunks(input, index, timescales[index], &samples).into_iter().map(|(time, mut chunk)| {
+                    chunk.first_sample += track.samples.len();
+                    (time, chunk)
+                }));
+                ends[index] = start + table.duration();
+                duration = duration.max(duration_from_ticks(table.duration(), timescales[index]).unwrap_or_default());
+                track.samples.extend(samples.into_iter().map(ResolvedSample::from));
+            }
+            offset += duration;
+        }
+        chunks.sort_by_key(|(start, _)| *start);
+        let chunks = chunks.into_iter().map(|(_, chunk)| chunk).collect::<Vec<_>>();
+        return write_progressive(&mut readers, &moov, &tracks, &chunks, allow_stz2, writer).await;
+    }
+
+    if let Some(mvex) = &mut moov.mvex {
+        mvex.mehd = None;
+    }
+    let mut fragment_writer = FragmentWriter::new(writer);
+    fragment_writer.write_init(&fragmented_ftyp(), &MoovBox::from(moov.clone()))?;
+    let mut ends = vec![0u64; moov.traks.len()];
+    let mut offset = Duration::ZERO;
+    let mut sequence_number = 0;
+    for (input, ((source, moofs), reader)) in sources.iter().zip(&mut readers).enumerate() {
+        let samples = moofs.iter()
+            .map(|(position, moof)| moof.samples(*position, Some(source)))
+            .collect::<Vec<_>>();
+        let track_index = |traf: &Traf| source.traks.iter().position(|it| it.track_id().is_some() && it.track_id() == traf.track_id());
+        // the first decode time of every track in this input, and where it goes in the output
+        let mut firsts = vec![None; moov.traks.len()];
+        for ((_, moof), samples) in moofs.iter().zip(&samples) {
+            for (traf, samples) in moof.trafs.iter().zip(samples) {
+                if let (Some(index), Some(time)) = (track_index(traf), samples.first().and_then(|it| it.decode_time)) {
+                    firsts[index] = Some(firsts[index].map_or(time, |it: u64| it.min(time)));
+                }
+            }
+        }
+        let starts = ends.iter().zip(&timescales)
+            .map(|(end, timescale)| (*end).max(ticks_from_duration(offset, *timescale)))
+            .collect::<Vec<_>>();
+
+        for ((_, moof), samples) in moofs.iter().zip(&samples) {
+            let mut trafs = vec![];
+            let mut data = vec![];
+            for (traf, samples) in moof.trafs.iter().zip(samples) {
+                let index = match track_index(traf) {
+                    Some(index) => index,
+                    None => continue
+                };
+                let shifted = samples.iter()
+                    .filter_map(|it| Some(ResolvedSample { decode_time: Some(it.decode_time? - firsts[index]? + starts[index]), ..*it }))
+                    .collect::<Vec<_>>();
+                let last = match shifted.last() {
+                    Some(last) => last,
+                    None => continue
+                };
+                ends[index] = ends[index].max(last.decode_time.unwrap_or_default() + last.duration as u64);
+                let track_id = moov.traks[index].track_id().unwrap_or_default();
+                let trex = moov.trex(track_id).map(|it| it.inner.inner.clone()).unwrap_or(Trex {
+                    track_id,
+                    default_sample_description_index: 1,
+                    default_sample_duration: 0,
+                    default_sample_size: 0,
+                    default_sample_flags: Default::default()
+                });
+                let sample_description_index = traf.tfhd.as_ref().and_then(|it| *it.sample_description_index)
+                    .or(traf.track_id().and_then(|id| source.trex(id)).map(|it| it.default_sample_description_index))
+                    .unwrap_or(1);
+                for sample in &shifted {
+                    reader.seek(SeekFrom::Start(sample.offset)).await?;
+                    let start = data.len();
+                    data.resize(start + sample.size as usize, 0);
+                    reader.read_exact(&mut data[start..]).await?;
+                }
+                trafs.push(fragment_traf(&trex, description(input, index, sample_description_index), &shifted));
+            }
+            if trafs.is_empty() {
+                continue;
+            }
+            sequence_number += 1;
+            let moof = Moof {
+                mfhd: Some(Mfhd { sequence_number }.into()),
+                trafs
+            };
+            fragment_writer.write_fragment(&mut Fragment::new(moof.into(), MdatBox(data)))?;
+        }
+        offset += ends.iter().zip(&starts).zip(&timescales)
+            // a track without samples in this input did not move its end past its start
+            .filter_map(|((end, start), timescale)| duration_from_ticks(end.saturating_sub(*start), *timescale))
+            .max()
+            .unwrap_or_default();
+    }
+    Ok(fragment_writer.position() as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::demux::{Demuxer, Mp4Event};
+    use crate::edit::{concat, cut};
+    use crate::error::MP4Error;
+    use crate::fragment::{Fragment, FragmentWriter};
+    use crate::id::BoxId;
+    use crate::mp4box::box_trait::{BoxWrite, IBox};
+    use crate::mp4box::box_unknown::UnknownBox;
+    use crate::mp4box::mdat::MdatBox;
+    use crate::mp4box::stsd::{Stsd, StsdSampleEntry};
+    use crate::r#type::BoxType;
+    use crate::remux::Remuxer;
+    use crate::remux::test::progressive;
+    use crate::sample_table::SampleTable;
+
+    /// The test file with a sample description for the audio track.
+    async fn with_description(configuration: u8) -> Result<Vec<u8>, MP4Error> {
+        let mut buf = progressive()?;
+        let mut moov = Demuxer::new(futures::io::Cursor::new(buf.clone())).read_moov().await?.clone();
+        buf.truncate(buf.len() - moov.byte_size());
+        let entry = StsdSampleEntry::Unknown(UnknownBox { id: BoxType::Id(BoxId(*b"mp4a")), data: vec![configuration] });
+        if let Some(stbl) = moov.traks[1].mdia.as_mut().and_then(|it| it.minf.as_mut()).and_then(|it| it.stbl.as_mut()) {
+            stbl.stsd = Some(Stsd { entries: vec![entry].into() }.into());
+        }
+        moov.write(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// The fragmented file without the fragments of `track_id`.
+    async fn without_track(buf: Vec<u8>, track_id: u32) -> Result<Vec<u8>, MP4Error> {
+        let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+        let mut writer = FragmentWriter::new(vec![]);
+        let mut ftyp = None;
+        let mut moof = None;
+        while let Some(event) = demuxer.next().await? {
+            match event {
+                Mp4Event::Ftyp(it) => ftyp = Some(it),
+                Mp4Event::Moov(moov) => { writer.write_init(ftyp.as_ref().unwrap(), &moov)?; }
+                Mp4Event::Moof(it) => moof = Some(it),
+                Mp4Event::Mdat(mdat) => {
+                    let mut moof = moof.take().unwrap();
+                    let start = moof.byte_size() as u64 + 8;
+                    let samples = moof.samples(0, demuxer.moov().map(|it| &**it));
+                    let data = moof.trafs.iter().zip(samples)
+                        .filter(|(traf, _)| traf.track_id() != Some(track_id))
+                        .flat_map(|(_, samples)| samples)
+                        .flat_map(|it| mdat.0[(it.offset - start) as usize..][..it.size as usize].to_vec())
+                        .collect();
+                    moof.trafs.retain(|traf| traf.track_id() != Some(track_id));
+                    writer.write_fragment(&mut Fragment::new(moof, MdatBox(data)))?;
+                }
+                _ => {}
+            }
+        }
+        Ok(writer.into_inner())
+    }
+
+    async fn fragmented(buf: Vec<u8>) -> Result<Vec<u8>, MP4Error> {
+        let mut remuxer = Remuxer::new(futures::io::Cursor::new(buf), Duration::from_secs(2)).await?;
+        let mut writer = FragmentWriter::new(vec![]);
+        remuxer.remux(&mut writer).await?;
+        Ok(writer.into_inner())
+    }
+
+    #[test]
+    pub fn test_cut_progressive() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let mut buf = vec![];
+            cut(futures::io::Cursor::new(progressive()?), Duration::from_millis(2500), Some(Duration::from_millis(4500)), false, &mut buf).await?;
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let moov = demuxer.read_moov().await?.clone();
+            // the video starts at its sync sample at 3s, 0.5s before the requested start
+            let edit = |track_id: u32| moov.trak(track_id).and_then(|it| it.elst()).map(|it| (it.entries[0].segment_duration, it.entries[0].media_time));
+            assert_eq!(edit(1), Some((2000, 500)));
+            assert_eq!(edit(2), Some((2000, 0)));
+            assert_eq!(moov.mvhd.as_ref().map(|it| it.duration.0), Some(Some(2000)));
+            let mut samples = vec![];
+            while let Some(sample) = demuxer.next_sample().await? {
+                samples.push((sample.track_id, sample.decode_time, sample.data[0]));
+            }
+            samples.sort();
+            assert_eq!(samples, [
+                (1, 0, 3), (1, 1000, 4), (1, 2000, 5),
+                (2, 0, 105), (2, 1000, 106), (2, 2000, 107), (2, 3000, 108)
+            ]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_cut_fragmented() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let mut buf = vec![];
+            cut(futures::io::Cursor::new(fragmented(progressive()?).await?), Duration::from_millis(2500), Some(Duration::from_millis(4500)), false, &mut buf).await?;
+
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let mut fragments = vec![];
+            let mut moof = None;
+            while let Some(event) = demuxer.next().await? {
+                match event {
+                    Mp4Event::Moov(moov) => {
+                        assert_eq!(moov.trak(1).and_then(|it| it.elst()).map(|it| it.entries[0].media_time), Some(500));
+                    }
+                    Mp4Event::Moof(it) => moof = Some(it),
+                    Mp4Event::Mdat(mdat) => {
+                        let moof = moof.take().unwrap();
+                        let start = moof.byte_size() as u64 + 8;
+                        let samples = moof.samples(0, demuxer.moov().map(|it| &**it));
+                        fragments.push(moof.trafs.iter().zip(samples).map(|(traf, samples)| (
+                            traf.track_id().unwrap(),
+                            samples.iter().map(|it| (it.decode_time.unwrap(), mdat.0[(it.offset - start) as usize])).collect::<Vec<_>>()
+                        )).collect::<Vec<_>>());
+                    }
+                    _ => {}
+                }
+            }
+            assert_eq!(fragments, [
+                vec![(2, vec![(0, 105)])],
+                vec![(1, vec![(0, 3), (1000, 4), (2000, 5)]), (2, vec![(1000, 106), (2000, 107), (3000, 108)])]
+            ]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_concat_progressive() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let inputs = vec![
+                futures::io::Cursor::new(with_description(1).await?),
+                futures::io::Cursor::new(with_description(2).await?),
+                futures::io::Cursor::new(with_description(1).await?),
+            ];
+            let mut buf = vec![];
+            concat(inputs, false, &mut buf).await?;
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let moov = demuxer.read_moov().await?.clone();
+            let audio = moov.trak(2).and_then(|it| it.stbl()).unwrap();
+            assert_eq!(audio.stsd.as_ref().map(|it| it.entries.0.len()), Some(2));
+            let table = SampleTable::new(audio)?;
+            let indices = table.samples().map(|it| it.sample_description_index).collect::<Vec<_>>();
+            assert_eq!(indices, [[1; 12], [2; 12], [1; 12]].concat());
+            assert_eq!(table.sample(30).map(|it| it.decode_time), Some(30000));
+            // the edit list of the first input now runs to the end
+            assert_eq!(moov.trak(1).and_then(|it| it.elst()).map(|it| it.entries[0].segment_duration), Some(17000));
+            assert_eq!(moov.mvhd.as_ref().map(|it| it.duration.0), Some(Some(18000)));
+            let mut data = vec![];
+            while let Some(sample) = demuxer.next_sample().await? {
+                data.push((sample.track_id, sample.decode_time, sample.data[0]));
+            }
+            data.sort();
+            assert_eq!(data.len(), 54);
+            assert_eq!(data[6..8], [(1, 6000, 0), (1, 7000, 1)]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_concat_fragmented() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let inputs = vec![
+                futures::io::Cursor::new(fragmented(with_description(1).await?).await?),
+                futures::io::Cursor::new(fragmented(with_description(2).await?).await?),
+            ];
+            let mut buf = vec![];
+            concat(inputs, false, &mut buf).await?;
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let mut trafs = vec![];
+            while let Some(event) = demuxer.next().await? {
+                match event {
+                    Mp4Event::Moov(moov) => {
+                        let stsd = moov.trak(2).and_then(|it| it.stbl()).and_then(|it| it.stsd.as_ref());
+                        assert_eq!(stsd.map(|it| it.entries.0.len()), Some(2));
+                    }
+                    Mp4Event::Moof(moof) => {
+                        assert_eq!(moof.mfhd.as_ref().map(|it| it.sequence_number), Some(trafs.len() as u32 / 2 + 1));
+                        trafs.extend(moof.trafs.iter().map(|traf| (
+                            traf.track_id().unwrap(),
+                            *traf.tfdt.as_ref().unwrap().base_media_decode_time,
+                            traf.tfhd.as_ref().and_then(|it| *it.sample_description_index).unwrap_or(1)
+                        )));
+                    }
+                    _ => {}
+                }
+            }
+            assert_eq!(trafs, [
+                (1, 0, 1), (2, 0, 1), (1, 3000, 1), (2, 6000, 1),
+                (1, 6000, 1), (2, 12000, 2), (1, 9000, 1), (2, 18000, 2)
+            ]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_concat_missing_track() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let full = fragmented(progressive()?).await?;
+            let video_only = without_track(full.clone(), 2).await?;
+            let inputs = [full.clone(), video_only.clone(), video_only, full].map(futures::io::Cursor::new).to_vec();
+            let mut buf = vec![];
+            concat(inputs, false, &mut buf).await?;
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let mut decode_times = [vec![], vec![]];
+            while let Some(event) = demuxer.next().await? {
+                if let Mp4Event::Moof(moof) = event {
+                    for traf in &moof.trafs {
+                        decode_times[traf.track_id().unwrap() as usize - 1].push(*traf.tfdt.as_ref().unwrap().base_media_decode_time);
+                    }
+                }
+            }
+            assert_eq!(decode_times[0], [0, 3000, 6000, 9000, 12000, 15000, 18000, 21000]);
+            // the audio resumes with the last input, 18s in
+            assert_eq!(decode_times[1], [0, 6000, 36000, 42000]);
+            Ok(())
+        })
+    }
+
+}
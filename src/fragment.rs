@@ -0,0 +1,333 @@
+use std::io::{Seek, SeekFrom};
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::error::MP4Error;
+use crate::mp4box::box_trait::{BoxWrite, IBox};
+use crate::mp4box::emsg::EmsgBox;
+use crate::mp4box::ftyp::FtypBox;
+use crate::mp4box::mdat::MdatBox;
+use crate::mp4box::moof::MoofBox;
+use crate::mp4box::moov::{Moov, MoovBox};
+use crate::timeline::{Timeline, TimelineIssue};
+
+/// A media fragment: the `moof`/`mdat` pair, preceded by the event messages that apply to it.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Fragment {
+    pub emsgs: Vec<EmsgBox>,
+    pub moof: MoofBox,
+    pub mdat: MdatBox,
+}
+
+impl Fragment {
+
+    pub fn new(moof: MoofBox, mdat: MdatBox) -> Self {
+        Self {
+            emsgs: vec![],
+            moof,
+            mdat
+        }
+    }
+
+    pub fn with_emsg(mut self, emsg: EmsgBox) -> Self {
+        self.emsgs.push(emsg);
+        self
+    }
+
+    /// Points every `trun` at its samples in the `mdat`, relative to the start of the `moof` (`DEFAULT_BASE_IS_MOOF`).
+    /// The `mdat` must hold the data of the `traf`s, then of their `trun`s, in order; sizes are resolved with the `trex` defaults of `moov`.
+    pub fn update_data_offsets(&mut self, moov: Option<&Moov>) {
+        for traf in &mut self.moof.trafs {
+            if let Some(tfhd) = &mut traf.tfhd {
+                tfhd.base_data_offset = None.into();
+                tfhd.flags.unset_has_base_data_offset();
+                tfhd.flags.set_default_base_is_moof();
+            }
+            for trun in &mut traf.truns {
+                trun.entries.offset.data_offset = 0.into();
+            }
+        }
+        let samples = self.moof.samples(0, moov);
+        let mut offset = (self.moof.byte_size() + self.mdat.header().byte_size()) as u64;
+        for (traf, samples) in self.moof.trafs.iter_mut().zip(samples) {
+            let mut samples = samples.iter();
+            for trun in &mut traf.truns {
+                trun.entries.offset.data_offset = (offset as i32).into();
+                offset += samples.by_ref()
+                    .take(trun.entries.data.len())
+                    .map(|it| it.size as u64)
+                    .sum::<u64>();
+            }
+        }
+    }
+
+    pub fn byte_size(&self) -> usize {
+        self.emsgs.iter().map(IBox::byte_size).sum::<usize>() + self.moof.byte_size() + self.mdat.byte_size()
+    }
+
+    pub fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let mut count = 0;
+        for emsg in &self.emsgs {
+            count += emsg.write(writer)?;
+        }
+        count += self.moof.write(writer)?;
+        count += self.mdat.write(writer)?;
+        Ok(count)
+    }
+}
+
+/// Writes a fragmented mp4 stream: an init segment followed by any number of fragments.
+pub struct FragmentWriter<W: WriteMp4> {
+    writer: W,
+    position: u64,
+    moov: Option<MoovBox>,
+    moov_position: u64,
+    timeline: Timeline,
+    issues: Vec<TimelineIssue>,
+    reserve_mehd: bool,
+}
+
+impl<W: WriteMp4> FragmentWriter<W> {
+
+    pub fn new(writer: W) -> Self {
+        Self { writer, position: 0, moov: None, moov_position: 0, timeline: Default::default(), issues: vec![], reserve_mehd: false }
+    }
+
+    /// Reserves a 64 bit `mehd` in the `mvex` written by [`FragmentWriter::write_init`],
+    /// so that [`FragmentWriter::finalize`] can fill in the duration of the stream.
+    pub fn reserve_mehd(mut self) -> Self {
+        self.reserve_mehd = true;
+        self
+    }
+
+    /// Writes the `ftyp` and `moov` boxes.
+    /// With [`FragmentWriter::reserve_mehd`], a `mehd` is reserved in the `mvex` of the `moov`.
+    pub fn write_init(&mut self, ftyp: &FtypBox, moov: &MoovBox) -> Result<usize, MP4Error> {
+        let mut moov = moov.clone();
+        if let Some(mvex) = moov.mvex.as_mut().filter(|_| self.reserve_mehd) {
+            mvex.mehd.get_or_insert_with(Default::default).long_duration = true;
+        }
+        let mut count = 0;
+        count += ftyp.write(&mut self.writer)?;
+        self.moov_position = self.position + count as u64;
+        count += moov.write(&mut self.writer)?;
+        self.position += count as u64;
+        self.timeline = Timeline::from_moov(&moov);
+        self.moov = Some(moov);
+        Ok(count)
+    }
+
+    /// Writes a standalone box such as a `sidx` between the init segment and the fragments.
+    pub fn write_box<B: BoxWrite>(&mut self, mp4box: &B) -> Result<usize, MP4Error> {
+        let count = mp4box.write(&mut self.writer)?;
+        self.position += count as u64;
+        Ok(count)
+    }
+
+    /// Writes the fragment's `emsg` boxes, then its `moof` and `mdat`.
+    /// Its decode times are checked against the previous fragments, see [`FragmentWriter::issues`].
+    /// The `sdtp` boxes are made to agree with the sample flags and the `trun` data offsets are computed beforehand,
+    /// see [`Traf::update_sdtp`](crate::mp4box::traf::Traf::update_sdtp) and [`Fragment::update_data_offsets`].
+    pub fn write_fragment(&mut self, fragment: &mut Fragment) -> Result<usize, MP4Error> {
+        for traf in &mut fragment.moof.trafs {
+            let trex = traf.track_id()
+                .and_then(|id| self.moov.as_ref()?.trex(id))
+                .map(|it| &it.inner.inner);
+            traf.update_sdtp(trex);
+        }
+        fragment.update_data_offsets(self.moov.as_deref());
+        let issues = self.timeline.check(&fragment.moof);
+        self.issues.extend(issues);
+        let count = fragment.write(&mut self.writer)?;
+        self.position += count as u64;
+        Ok(count)
+    }
+
+    /// Decode times of the tracks, up to the end of the last written fragment.
+    pub fn timeline(&self) -> &Timeline {
+        &self.timeline
+    }
+
+    /// Gaps and overlaps between the decode times of the written fragments, see [`Timeline::check`].
+    pub fn issues(&self) -> &[TimelineIssue] {
+        &self.issues
+    }
+
+    /// Number of bytes written so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Sets the `mehd` fragment duration to the duration written so far and rewrites the `moov` in place.
+    /// The writer is left at the end of the stream, nothing is done without a `mehd`, see [`FragmentWriter::reserve_mehd`].
+    /// Fails if the duration does not fit a `mehd` that was not reserved.
+    pub fn finalize(&mut self) -> Result<(), MP4Error> where W: Seek {
+        let moov = match &mut self.moov {
+            Some(moov) => moov,
+            None => return Ok(())
+        };
+        let size = moov.byte_size();
+        let timescale = moov.mvhd.as_ref().map(|it| it.timescale).unwrap_or(1000);
+        let mehd = match moov.mvex.as_mut().and_then(|it| it.mehd.as_mut()) {
+            Some(mehd) => mehd,
+            None => return Ok(())
+        };
+        mehd.fragment_duration = self.timeline.fragment_duration(timescale).into();
+        if moov.byte_size() != size {
+            return Err(MP4Error::Custom("The fragment duration does not fit the reserved mehd box".to_string()));
+        }
+        let end = self.position - self.moov_position - size as u64;
+        self.writer.seek(SeekFrom::Current(self.moov_position as i64 - self.position as i64))?;
+        moov.write(&mut self.writer)?;
+        self.writer.seek(SeekFrom::Current(end as i64))?;
+        Ok(())
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::demux::{Demuxer, Mp4Event};
+    use crate::error::MP4Error;
+    use crate::fragment::{Fragment, FragmentWriter};
+    use crate::mp4box::box_trait::IBox;
+    use crate::mp4box::ftyp::Ftyp;
+    use crate::mp4box::mdat::MdatBox;
+    use crate::mp4box::mdhd::Mdhd;
+    use crate::mp4box::mdia::Mdia;
+    use crate::mp4box::moof::Moof;
+    use crate::mp4box::moov::Moov;
+    use crate::mp4box::mvex::Mvex;
+    use crate::mp4box::tfdt::Tfdt;
+    use crate::mp4box::tfhd::Tfhd;
+    use crate::mp4box::tkhd::Tkhd;
+    use crate::mp4box::trak::Trak;
+    use crate::mp4box::trex::Trex;
+    use crate::mp4box::traf::Traf;
+    use crate::mp4box::trun::{Trun, TrunEntry};
+    use crate::timeline::TimelineIssue;
+    use crate::types::array::Mp4VersionedOffsetArray;
+
+    #[test]
+    pub fn test_data_offsets() {
+        let traf = |track_id: u32, sizes: &[&[u32]]| Traf {
+            tfhd: Some(Tfhd {
+                track_id,
+                base_data_offset: 1234u64.into(),
+                sample_description_index: Default::default(),
+                default_sample_duration: Default::default(),
+                default_sample_size: Default::default(),
+                default_sample_flags: Default::default(),
+                flags: Default::default()
+            }.into()),
+            tfdt: None,
+            sdtp: None,
+            sbgps: vec![],
+            sgpds: vec![],
+            truns: sizes.iter().map(|sizes| Trun {
+                entries: Mp4VersionedOffsetArray::new(sizes.iter().map(|size| TrunEntry {
+                    sample_size: (*size).into(),
+                    ..Default::default()
+                }).collect(), Default::default())
+            }.into()).collect()
+        }.into();
+        let moof = Moof {
+            mfhd: None,
+            trafs: vec![traf(1, &[&[3, 4], &[5]]), traf(2, &[&[6]])]
+        }.into();
+        let mut fragment = Fragment::new(moof, MdatBox(vec![0; 18]));
+        fragment.update_data_offsets(None);
+        let base = fragment.moof.byte_size() as u64 + 8;
+        let offsets = fragment.moof.samples(0, None).into_iter()
+            .map(|samples| samples.iter().map(|it| it.offset).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec![vec![base, base + 3, base + 7], vec![base + 12]]);
+        assert!(fragment.moof.trafs.iter().all(|it| it.tfhd.as_ref().unwrap().flags.default_base_is_moof()));
+    }
+
+    #[test]
+    pub fn test_finalize() -> Result<(), MP4Error> {
+        let ftyp = Ftyp { major_brand: *b"iso6", minor_version: 0, compatible_brands: vec![*b"iso6"] };
+        let moov = Moov {
+            mvhd: Some(Default::default()),
+            traks: vec![Trak {
+                tkhd: Some(Tkhd { track_id: 1, ..Default::default() }.into()),
+                edts: None,
+                mdia: Some(Mdia {
+                    mdhd: Some(Mdhd { timescale: 90000, ..Default::default() }.into()),
+                    hdlr: None,
+                    minf: None
+                }.into())
+            }.into()],
+            mvex: Some(Mvex {
+                mehd: None,
+                trex: vec![Trex {
+                    track_id: 1,
+                    default_sample_description_index: 1,
+                    default_sample_duration: 3000,
+                    default_sample_size: 0,
+                    default_sample_flags: Default::default()
+                }.into()],
+                trep: vec![]
+            }.into())
+        }.into();
+        let fragment = |decode_time: u32| Fragment::new(Moof {
+            mfhd: None,
+            trafs: vec![Traf {
+                tfhd: Some(Tfhd {
+                    track_id: 1,
+                    base_data_offset: Default::default(),
+                    sample_description_index: Default::default(),
+                    default_sample_duration: Default::default(),
+                    default_sample_size: Default::default(),
+                    default_sample_flags: Default::default(),
+                    flags: Default::default()
+                }.into()),
+                tfdt: Some(Tfdt { base_media_decode_time: decode_time.into() }.into()),
+                sdtp: None,
+                sbgps: vec![],
+                sgpds: vec![],
+                truns: vec![Trun {
+                    entries: Mp4VersionedOffsetArray::new(vec![TrunEntry {
+                        sample_size: 4.into(),
+                        ..Default::default()
+                    }; 2], Default::default())
+                }.into()]
+            }.into()]
+        }.into(), MdatBox(vec![0; 8]));
+        let mut writer = FragmentWriter::new(std::io::Cursor::new(vec![]));
+        writer.write_init(&ftyp, &moov)?;
+        assert!(writer.moov.as_ref().and_then(|it| it.mvex.as_ref()).is_some_and(|it| it.mehd.is_none()));
+        let mut writer = FragmentWriter::new(std::io::Cursor::new(vec![])).reserve_mehd();
+        writer.write_init(&ftyp, &moov)?;
+        writer.write_fragment(&mut fragment(0))?;
+        writer.write_fragment(&mut fragment(9000))?;
+        assert_eq!(writer.issues(), [TimelineIssue::Gap { track_id: 1, expected: 6000, actual: 9000 }]);
+        writer.finalize()?;
+        assert_eq!(writer.get_ref().position(), writer.position());
+        let buf = writer.into_inner().into_inner();
+        futures::executor::block_on(async {
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let mut fragment_duration = None;
+            let mut moofs = 0;
+            while let Some(event) = demuxer.next().await? {
+                match event {
+                    Mp4Event::Moov(moov) => fragment_duration = moov.mvex.as_ref()
+                        .and_then(|it| it.mehd.as_ref())
+                        .map(|it| (*it.fragment_duration, it.long_duration)),
+                    Mp4Event::Moof(_) => moofs += 1,
+                    _ => {}
+                }
+            }
+            assert_eq!(fragment_duration, Some((15000 * 1000 / 90000, true)));
+            assert_eq!(moofs, 2);
+            Ok(())
+        })
+    }
+
+}
@@ -27,7 +27,7 @@ impl BoxHeader {
 
     pub fn size_minus_self(&self) -> BoxSize {
         match self.size {
-            Known(size) => Known(self.byte_size() - size),
+            Known(size) => Known(size - self.byte_size()),
             Unknown => Unknown
         }
     }
@@ -0,0 +1,387 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use crate::fragment::Fragment;
+use crate::mp4box::moov::Moov;
+use crate::mp4box::trex::Trex;
+use crate::types::duration::duration_from_ticks;
+
+/// A byte range within a resource, rendered as `length[@offset]`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ByteRange {
+    pub length: u64,
+    pub offset: Option<u64>,
+}
+
+impl Display for ByteRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.offset {
+            Some(offset) => write!(f, "{}@{}", self.length, offset),
+            None => write!(f, "{}", self.length)
+        }
+    }
+}
+
+/// A partial segment of a low latency playlist (`EXT-X-PART`).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct HlsPart {
+    pub uri: String,
+    pub duration: Duration,
+    pub byte_range: Option<ByteRange>,
+    pub independent: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct HlsSegment {
+    pub uri: String,
+    pub duration: Duration,
+    pub byte_range: Option<ByteRange>,
+    pub independent: bool,
+    pub parts: Vec<HlsPart>,
+}
+
+/// The timing information of a track needed to describe its fragments.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct HlsTrack {
+    pub track_id: u32,
+    pub timescale: u32,
+    pub trex: Option<Trex>,
+}
+
+impl HlsTrack {
+
+    pub fn from_moov(moov: &Moov, track_id: u32) -> Option<Self> {
+        let timescale = moov.trak(track_id)?.timescale()?;
+        let trex = moov.trex(track_id).map(|it| it.inner.inner.clone());
+        Some(Self { track_id, timescale, trex })
+    }
+
+    /// Duration of the track in the fragment and whether it starts with a sync sample.
+    fn describe(&self, fragment: &Fragment) -> Option<(Duration, bool)> {
+        let traf = fragment.moof.traf(self.track_id)?;
+        let duration = duration_from_ticks(traf.duration(self.trex.as_ref()), self.timescale)?;
+        let independent = traf.first_sample_flags(self.trex.as_ref())
+            .map(|flags| flags.is_sync())
+            .unwrap_or(true);
+        Some((duration, independent))
+    }
+
+    /// Describes a fragment, `offset` is its position in `uri` when several fragments share a resource.
+    pub fn part(&self, uri: impl Into<String>, fragment: &Fragment, offset: Option<u64>) -> Option<HlsPart> {
+        let (duration, independent) = self.describe(fragment)?;
+        Some(HlsPart {
+            uri: uri.into(),
+            duration,
+            byte_range: offset.map(|offset| ByteRange { length: fragment.byte_size() as u64, offset: Some(offset) }),
+            independent
+        })
+    }
+
+    /// Describes a segment made of a single fragment.
+    pub fn segment(&self, uri: impl Into<String>, fragment: &Fragment, offset: Option<u64>) -> Option<HlsSegment> {
+        let part = self.part(uri, fragment, offset)?;
+        Some(HlsSegment {
+            uri: part.uri,
+            duration: part.duration,
+            byte_range: part.byte_range,
+            independent: part.independent,
+            parts: vec![]
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PlaylistType {
+    Event,
+    Vod,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct MediaPlaylist {
+    pub map_uri: String,
+    pub map_byte_range: Option<ByteRange>,
+    pub media_sequence: u64,
+    pub playlist_type: Option<PlaylistType>,
+    pub segments: Vec<HlsSegment>,
+    /// Parts of the segment that is still being produced.
+    pub pending_parts: Vec<HlsPart>,
+    pub ended: bool,
+}
+
+impl MediaPlaylist {
+
+    pub fn new(map_uri: impl Into<String>) -> Self {
+        Self {
+            map_uri: map_uri.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn push_part(&mut self, part: HlsPart) {
+        self.pending_parts.push(part);
+    }
+
+    /// Adds a segment, the pending parts are attached to it if it has none.
+    pub fn push_segment(&mut self, mut segment: HlsSegment) {
+        if segment.parts.is_empty() {
+            segment.parts = std::mem::take(&mut self.pending_parts);
+        }
+        self.segments.push(segment);
+    }
+
+    /// Closes the segment made of the pending parts, they must be contiguous in `uri` if they use byte ranges.
+    pub fn close_segment(&mut self, uri: impl Into<String>) {
+        let parts = std::mem::take(&mut self.pending_parts);
+        let byte_range = parts.iter().map(|part| part.byte_range).collect::<Option<Vec<_>>>()
+            .and_then(|ranges| Some(ByteRange {
+                length: ranges.iter().map(|it| it.length).sum(),
+                offset: ranges.first()?.offset
+            }));
+        self.segments.push(HlsSegment {
+            uri: uri.into(),
+            duration: parts.iter().map(|part| part.duration).sum(),
+            byte_range,
+            independent: parts.first().map(|part| part.independent).unwrap_or_default(),
+            parts
+        });
+    }
+
+    /// Largest segment duration rounded to the nearest second.
+    pub fn target_duration(&self) -> u64 {
+        self.segments.iter().map(|it| it.duration.as_secs_f64().round() as u64).max().unwrap_or_default()
+    }
+
+    pub fn part_target(&self) -> Option<Duration> {
+        self.segments.iter().flat_map(|it| it.parts.iter()).chain(self.pending_parts.iter()).map(|it| it.duration).max()
+    }
+}
+
+impl Display for MediaPlaylist {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "#EXTM3U")?;
+        writeln!(f, "#EXT-X-VERSION:6")?;
+        writeln!(f, "#EXT-X-TARGETDURATION:{}", self.target_duration())?;
+        if let Some(part_target) = self.part_target() {
+            // Clients have to stay at least this far from the live edge, the spec recommends three part targets.
+            writeln!(f, "#EXT-X-SERVER-CONTROL:PART-HOLD-BACK={:.5}", (part_target * 3).as_secs_f64())?;
+            writeln!(f, "#EXT-X-PART-INF:PART-TARGET={:.5}", part_target.as_secs_f64())?;
+        }
+        writeln!(f, "#EXT-X-MEDIA-SEQUENCE:{}", self.media_sequence)?;
+        match self.playlist_type {
+            Some(PlaylistType::Event) => writeln!(f, "#EXT-X-PLAYLIST-TYPE:EVENT")?,
+            Some(PlaylistType::Vod) => writeln!(f, "#EXT-X-PLAYLIST-TYPE:VOD")?,
+            None => {}
+        }
+        if !self.segments.is_empty() && self.segments.iter().all(|it| it.independent) {
+            writeln!(f, "#EXT-X-INDEPENDENT-SEGMENTS")?;
+        }
+        write!(f, "#EXT-X-MAP:URI=\"{}\"", self.map_uri)?;
+        if let Some(byte_range) = self.map_byte_range {
+            write!(f, ",BYTERANGE=\"{}\"", byte_range)?;
+        }
+        writeln!(f)?;
+        for segment in &self.segments {
+            for part in &segment.parts {
+                writeln!(f, "{}", PartTag(part))?;
+            }
+            writeln!(f, "#EXTINF:{:.5},", segment.duration.as_secs_f64())?;
+            if let Some(byte_range) = segment.byte_range {
+                writeln!(f, "#EXT-X-BYTERANGE:{}", byte_range)?;
+            }
+            writeln!(f, "{}", segment.uri)?;
+        }
+        for part in &self.pending_parts {
+            writeln!(f, "{}", PartTag(part))?;
+        }
+        if self.ended {
+            writeln!(f, "#EXT-X-ENDLIST")?;
+        }
+        Ok(())
+    }
+}
+
+struct PartTag<'a>(&'a HlsPart);
+
+impl Display for PartTag<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let part = self.0;
+        write!(f, "#EXT-X-PART:DURATION={:.5},URI=\"{}\"", part.duration.as_secs_f64(), part.uri)?;
+        if part.independent {
+            write!(f, ",INDEPENDENT=YES")?;
+        }
+        if let Some(byte_range) = part.byte_range {
+            write!(f, ",BYTERANGE=\"{}\"", byte_range)?;
+        }
+        Ok(())
+    }
+}
+
+/// An alternative rendition (`EXT-X-MEDIA`), e.g. an audio track referenced by the variants.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct HlsRendition {
+    pub media_type: HlsMediaType,
+    pub group_id: String,
+    pub name: String,
+    pub language: Option<String>,
+    pub uri: Option<String>,
+    pub default: bool,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum HlsMediaType {
+    Audio,
+    Video,
+    Subtitles,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HlsVariant {
+    pub uri: String,
+    pub bandwidth: u64,
+    pub average_bandwidth: Option<u64>,
+    pub codecs: Vec<String>,
+    pub resolution: Option<(u16, u16)>,
+    pub frame_rate: Option<f64>,
+    pub audio: Option<String>,
+}
+
+impl HlsVariant {
+
+    /// Derives the codecs and resolution of a variant from its init segment.
+    pub fn from_moov(uri: impl Into<String>, bandwidth: u64, moov: &Moov) -> Self {
+        let entries = moov.traks.iter().filter_map(|trak| trak.sample_entry()).collect::<Vec<_>>();
+        let resolution = entries.iter()
+            .find_map(|entry| entry.visual_sample_entry())
+            .map(|it| (it.width, it.height));
+        Self {
+            uri: uri.into(),
+            bandwidth,
+            average_bandwidth: None,
+            codecs: entries.into_iter().filter_map(|it| it.codec_string()).collect(),
+            resolution,
+            frame_rate: None,
+            audio: None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MasterPlaylist {
+    pub renditions: Vec<HlsRendition>,
+    pub variants: Vec<HlsVariant>,
+}
+
+impl Display for MasterPlaylist {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "#EXTM3U")?;
+        writeln!(f, "#EXT-X-VERSION:6")?;
+        writeln!(f, "#EXT-X-INDEPENDENT-SEGMENTS")?;
+        for rendition in &self.renditions {
+            let media_type = match rendition.media_type {
+                HlsMediaType::Audio => "AUDIO",
+                HlsMediaType::Video => "VIDEO",
+                HlsMediaType::Subtitles => "SUBTITLES"
+            };
+            write!(f, "#EXT-X-MEDIA:TYPE={},GROUP-ID=\"{}\",NAME=\"{}\"", media_type, rendition.group_id, rendition.name)?;
+            if let Some(language) = &rendition.language {
+                write!(f, ",LANGUAGE=\"{}\"", language)?;
+            }
+            if rendition.default {
+                write!(f, ",DEFAULT=YES,AUTOSELECT=YES")?;
+            }
+            if let Some(uri) = &rendition.uri {
+                write!(f, ",URI=\"{}\"", uri)?;
+            }
+            writeln!(f)?;
+        }
+        for variant in &self.variants {
+            write!(f, "#EXT-X-STREAM-INF:BANDWIDTH={}", variant.bandwidth)?;
+            if let Some(average_bandwidth) = variant.average_bandwidth {
+                write!(f, ",AVERAGE-BANDWIDTH={}", average_bandwidth)?;
+            }
+            if !variant.codecs.is_empty() {
+                write!(f, ",CODECS=\"{}\"", variant.codecs.join(","))?;
+            }
+            if let Some((width, height)) = variant.resolution {
+                write!(f, ",RESOLUTION={}x{}", width, height)?;
+            }
+            if let Some(frame_rate) = variant.frame_rate {
+                write!(f, ",FRAME-RATE={:.3}", frame_rate)?;
+            }
+            if let Some(audio) = &variant.audio {
+                write!(f, ",AUDIO=\"{}\"", audio)?;
+            }
+            writeln!(f)?;
+            writeln!(f, "{}", variant.uri)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::hls::{ByteRange, HlsMediaType, HlsPart, HlsRendition, HlsVariant, MasterPlaylist, MediaPlaylist};
+
+    #[test]
+    pub fn test_low_latency_playlist() {
+        let part = |offset, independent| HlsPart {
+            uri: "seg0.m4s".to_string(),
+            duration: Duration::from_millis(500),
+            byte_range: Some(ByteRange { length: 1000, offset: Some(offset) }),
+            independent
+        };
+        let mut playlist = MediaPlaylist::new("init.mp4");
+        playlist.push_part(part(0, true));
+        playlist.push_part(part(1000, false));
+        playlist.close_segment("seg0.m4s");
+        playlist.push_part(HlsPart { uri: "seg1.m4s".to_string(), byte_range: None, ..part(0, true) });
+        assert_eq!(playlist.to_string(), "\
+#EXTM3U
+#EXT-X-VERSION:6
+#EXT-X-TARGETDURATION:1
+#EXT-X-SERVER-CONTROL:PART-HOLD-BACK=1.50000
+#EXT-X-PART-INF:PART-TARGET=0.50000
+#EXT-X-MEDIA-SEQUENCE:0
+#EXT-X-INDEPENDENT-SEGMENTS
+#EXT-X-MAP:URI=\"init.mp4\"
+#EXT-X-PART:DURATION=0.50000,URI=\"seg0.m4s\",INDEPENDENT=YES,BYTERANGE=\"1000@0\"
+#EXT-X-PART:DURATION=0.50000,URI=\"seg0.m4s\",BYTERANGE=\"1000@1000\"
+#EXTINF:1.00000,
+#EXT-X-BYTERANGE:2000@0
+seg0.m4s
+#EXT-X-PART:DURATION=0.50000,URI=\"seg1.m4s\",INDEPENDENT=YES
+");
+    }
+
+    #[test]
+    pub fn test_master_playlist() {
+        let playlist = MasterPlaylist {
+            renditions: vec![HlsRendition {
+                media_type: HlsMediaType::Audio,
+                group_id: "aac".to_string(),
+                name: "English".to_string(),
+                language: Some("en".to_string()),
+                uri: Some("audio.m3u8".to_string()),
+                default: true
+            }],
+            variants: vec![HlsVariant {
+                uri: "video.m3u8".to_string(),
+                bandwidth: 2_000_000,
+                average_bandwidth: Some(1_500_000),
+                codecs: vec!["avc1.64001f".to_string(), "mp4a.40.2".to_string()],
+                resolution: Some((1280, 720)),
+                frame_rate: Some(30.0),
+                audio: Some("aac".to_string())
+            }]
+        };
+        assert_eq!(playlist.to_string(), "\
+#EXTM3U
+#EXT-X-VERSION:6
+#EXT-X-INDEPENDENT-SEGMENTS
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aac\",NAME=\"English\",LANGUAGE=\"en\",DEFAULT=YES,AUTOSELECT=YES,URI=\"audio.m3u8\"
+#EXT-X-STREAM-INF:BANDWIDTH=2000000,AVERAGE-BANDWIDTH=1500000,CODECS=\"avc1.64001f,mp4a.40.2\",RESOLUTION=1280x720,FRAME-RATE=30.000,AUDIO=\"aac\"
+video.m3u8
+");
+    }
+
+}
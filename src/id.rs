@@ -63,3 +63,72 @@ impl From<&[u8;4]> for BoxId {
         Self(*id)
     }
 }
+
+/// Serialized as its FourCC, or as its bytes when they are not valid UTF-8.
+#[cfg(feature = "serde")]
+impl serde::Serialize for BoxId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match std::str::from_utf8(&self.0) {
+            Ok(str) => serializer.serialize_str(str),
+            Err(_) => self.0.serialize(serializer)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BoxId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BoxIdVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for BoxIdVisitor {
+            type Value = BoxId;
+
+            fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                f.write_str("a four character code")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                let id: [u8; 4] = value.as_bytes().try_into().map_err(|_| E::invalid_length(value.len(), &self))?;
+                Ok(BoxId(id))
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut id = [0u8; 4];
+                for (i, byte) in id.iter_mut().enumerate() {
+                    *byte = seq.next_element()?.ok_or_else(|| serde::de::Error::invalid_length(i, &self))?;
+                }
+                Ok(BoxId(id))
+            }
+        }
+
+        deserializer.deserialize_any(BoxIdVisitor)
+    }
+}
+
+/// `serde(with)` helpers for the `[u8; 4]` fields holding a FourCC, such as brands and handler types.
+#[cfg(feature = "serde")]
+pub(crate) mod fourcc {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use crate::id::BoxId;
+
+    pub fn serialize<S: Serializer>(value: &[u8; 4], serializer: S) -> Result<S::Ok, S::Error> {
+        BoxId(*value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 4], D::Error> {
+        Ok(BoxId::deserialize(deserializer)?.0)
+    }
+
+    pub mod vec {
+        use serde::{Deserialize, Deserializer, Serializer};
+        use crate::id::BoxId;
+
+        pub fn serialize<S: Serializer>(values: &[[u8; 4]], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(values.iter().map(|it| BoxId(*it)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<[u8; 4]>, D::Error> {
+            Ok(Vec::<BoxId>::deserialize(deserializer)?.into_iter().map(|it| it.0).collect())
+        }
+    }
+}
@@ -11,5 +11,18 @@ pub mod bytes_write;
 pub mod bytes_read;
 pub mod bytes_reserve;
 pub mod types;
+pub mod fragment;
+pub mod timeline;
+pub mod sample_table;
+pub mod demux;
+pub mod remux;
+pub mod mux;
+pub mod edit;
+pub mod dump;
+pub mod validate;
+pub mod hls;
+pub mod dash;
+pub mod codec;
+pub mod nal;
 
 pub use fixed;
@@ -4,6 +4,7 @@ use crate::mp4_data;
 
 mp4_data! {
     #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct MP4Matrix {
         pub a: I16F16,
         pub b: I16F16,
@@ -1,6 +1,10 @@
 use crate::{base_box};
-use crate::mp4box::avcc::AvcCBox;
-use crate::types::sample::VisualSampleEntry;
+use crate::codec::Codec;
+use fixed::types::I16F16;
+use crate::error::MP4Error;
+use crate::mp4box::avcc::{AvcC, AVCDecoderConfigurationRecord, AvcCBox};
+use crate::nal::avc::AvcSps;
+use crate::types::sample::{SampleEntry, VisualSampleEntry};
 
 base_box! {
     box (b"avc1", Avc1, Avc1Box) data {
@@ -19,12 +23,47 @@ impl Default for Avc1 {
     }
 }
 
+impl Avc1 {
+
+    /// Builds a sample entry from raw SPS and PPS NAL units, the dimensions are taken from the first SPS.
+    pub fn from_parameter_sets(sps: &[&[u8]], pps: &[&[u8]]) -> Result<Self, MP4Error> {
+        let avc_config = AVCDecoderConfigurationRecord::from_parameter_sets(sps, pps)?;
+        let parsed = AvcSps::parse(sps[0])?;
+        Ok(Self {
+            visual_sample_entry: VisualSampleEntry {
+                sample_entry: SampleEntry { data_reference_index: 1, ..Default::default() },
+                width: parsed.width() as u16,
+                height: parsed.height() as u16,
+                horizresolution: I16F16::from_num(72),
+                vertresolution: I16F16::from_num(72),
+                framecount: 1,
+                depth: 0x0018,
+                ..Default::default()
+            },
+            avcc: Some(AvcC { avc_config })
+        })
+    }
+    pub fn codec(&self) -> Option<Codec> {
+        let config = &self.avcc.as_ref()?.avc_config;
+        Some(Codec::Avc {
+            fourcc: *b"avc1",
+            profile_indication: config.profile_indication,
+            profile_compatibility: config.profile_compatibility,
+            level_indication: config.level_indication
+        })
+    }
+
+    pub fn codec_string(&self) -> Option<String> {
+        self.codec().map(|it| it.to_string())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::bytes_read::Mp4Readable;
     use crate::error::MP4Error;
     use crate::header::BoxHeader;
-    use crate::mp4box::avc1::{Avc1Box};
+    use crate::mp4box::avc1::{Avc1, Avc1Box};
     use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
 
     #[test]
@@ -46,4 +85,27 @@ mod test {
         })
     }
 
+    #[test]
+    pub fn test_from_parameter_sets() -> Result<(), MP4Error> {
+        let sps: &[u8] = &[
+            0x67, 0x64, 0x00, 0x1f, 0xac, 0xd9, 0x40, 0x50, 0x05, 0xbb, 0x01, 0x10, 0x00, 0x00,
+            0x03, 0x00, 0x10, 0x00, 0x00, 0x03, 0x03, 0x28, 0x40
+        ];
+        let pps: &[u8] = &[0x68, 0xeb, 0xe3, 0xcb, 0x22, 0xc0];
+        futures::executor::block_on(async {
+            let base: Avc1Box = Avc1::from_parameter_sets(&[sps], &[pps])?.into();
+            assert_eq!((base.visual_sample_entry.width, base.visual_sample_entry.height), (1280, 720));
+            assert_eq!(base.codec_string().as_deref(), Some("avc1.64001f"));
+            assert!(base.avcc.as_ref().and_then(|it| it.avc_config.ext.as_ref()).is_some());
+            let mut buf = vec![];
+            let mut cursor = std::io::Cursor::new(&mut buf);
+            base.write(&mut cursor)?;
+            let mut cursor = futures::io::Cursor::new(&mut buf);
+            let header = BoxHeader::read(&mut cursor).await?;
+            let new = Avc1Box::read(header, &mut cursor).await?;
+            assert_eq!(base, new);
+            Ok(())
+        })
+    }
+
 }
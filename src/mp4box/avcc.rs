@@ -1,32 +1,159 @@
-use crate::{base_box, mp4_data};
+use std::io::SeekFrom;
+use async_trait::async_trait;
+use futures::AsyncSeekExt;
+use crate::mp4_data;
+use crate::bytes_read::ReadMp4;
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::error::MP4Error;
+use crate::header::BoxHeader;
+use crate::id::BoxId;
+use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+use crate::nal::avc::AvcSps;
 use crate::types::array::Mp4Array;
 use crate::types::padded_byte::PaddedByte;
+use crate::r#type::BoxType;
+use crate::size::BoxSize;
+
+#[derive(Default, Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AVCDecoderConfigurationRecord {
+    pub configuartion_version: u8,
+    pub profile_indication: u8,
+    pub profile_compatibility: u8,
+    pub level_indication: u8,
+    pub length_size_minus_one: PaddedByte<6, 1>,
+    pub sps: Mp4Array<PaddedByte<3, 1>, Mp4Array<u16, u8>>,
+    pub pps: Mp4Array<u8, Mp4Array<u16, u8>>,
+    /// only present for the high profiles, see [`AVCDecoderConfigurationRecord::has_ext`]
+    pub ext: Option<AVCDecoderConfigurationExt>,
+}
 
 mp4_data! {
+    /// 14496-15 § 5.2.4.1.1
     #[derive(Default, Debug, Clone, Eq, PartialEq, Hash)]
-    pub struct AVCDecoderConfigurationRecord {
-        pub configuartion_version: u8,
-        pub profile_indication: u8,
-        pub profile_compatibility: u8,
-        pub level_indication: u8,
-        pub length_size_minus_one: PaddedByte<6, 1>,
-        pub sps: Mp4Array<PaddedByte<3, 1>, Mp4Array<u16, u8>>,
-        pub pps: Mp4Array<u8, Mp4Array<u16, u8>>,
-        // Todo: profile idc ext (14496-15 § 5.2.4.1.1)
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct AVCDecoderConfigurationExt {
+        pub chroma_format: PaddedByte<6, 1>,
+        pub bit_depth_luma_minus8: PaddedByte<5, 1>,
+        pub bit_depth_chroma_minus8: PaddedByte<5, 1>,
+        pub sps_ext: Mp4Array<u8, Mp4Array<u16, u8>>,
+    }
+}
+
+impl AVCDecoderConfigurationRecord {
+    /// Whether the profile carries the chroma format and bit depth extension (High profiles).
+    pub fn has_ext(profile_indication: u8) -> bool {
+        matches!(profile_indication, 100 | 110 | 122 | 144)
+    }
+
+    /// Builds the configuration from raw SPS and PPS NAL units, the profile and level are taken from the first SPS.
+    pub fn from_parameter_sets(sps: &[&[u8]], pps: &[&[u8]]) -> Result<Self, MP4Error> {
+        let first = sps.first().ok_or_else(|| MP4Error::Custom("At least one SPS is required".to_string()))?;
+        let parsed = AvcSps::parse(first)?;
+        let ext = if Self::has_ext(parsed.profile_idc) {
+            Some(AVCDecoderConfigurationExt {
+                chroma_format: (parsed.chroma_format_idc as u8).into(),
+                bit_depth_luma_minus8: (parsed.bit_depth_luma_minus8 as u8).into(),
+                bit_depth_chroma_minus8: (parsed.bit_depth_chroma_minus8 as u8).into(),
+                sps_ext: Default::default()
+            })
+        } else {
+            None
+        };
+        Ok(Self {
+            configuartion_version: 1,
+            profile_indication: parsed.profile_idc,
+            profile_compatibility: parsed.constraint_flags,
+            level_indication: parsed.level_idc,
+            length_size_minus_one: 3.into(),
+            sps: sps.iter().map(|it| it.to_vec().into()).collect::<Vec<_>>().into(),
+            pps: pps.iter().map(|it| it.to_vec().into()).collect::<Vec<_>>().into(),
+            ext
+        })
     }
 }
 
-base_box! {
-    box (b"avcC", AvcC, AvcCBox) data {
-        avc_config: AVCDecoderConfigurationRecord
-    } children {
+impl AVCDecoderConfigurationRecord {
+    /// Reads a record spanning `size` bytes, the extension is only read if bytes remain after the PPS.
+    pub async fn read_sized<R: ReadMp4>(reader: &mut R, size: BoxSize) -> Result<Self, MP4Error> {
+        let start = reader.seek(SeekFrom::Current(0)).await?;
+        let configuartion_version = reader.read().await?;
+        let profile_indication = reader.read().await?;
+        let mut record = Self {
+            configuartion_version,
+            profile_indication,
+            profile_compatibility: reader.read().await?,
+            level_indication: reader.read().await?,
+            length_size_minus_one: reader.read().await?,
+            sps: reader.read().await?,
+            pps: reader.read().await?,
+            ext: None
+        };
+        if Self::has_ext(profile_indication) && !size.ended(start, reader).await? {
+            record.ext = Some(reader.read().await?);
+        }
+        Ok(record)
+    }
+}
+
+impl Mp4Writable for AVCDecoderConfigurationRecord {
+    fn byte_size(&self) -> usize {
+        4 + self.length_size_minus_one.byte_size() + self.sps.byte_size() + self.pps.byte_size() + self.ext.as_ref().map(Mp4Writable::byte_size).unwrap_or_default()
+    }
 
+    fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let mut count = 0;
+        count += self.configuartion_version.write(writer)?;
+        count += self.profile_indication.write(writer)?;
+        count += self.profile_compatibility.write(writer)?;
+        count += self.level_indication.write(writer)?;
+        count += self.length_size_minus_one.write(writer)?;
+        count += self.sps.write(writer)?;
+        count += self.pps.write(writer)?;
+        if let Some(ext) = &self.ext {
+            count += ext.write(writer)?;
+        }
+        Ok(count)
     }
 }
 
-impl Default for AvcC {
-    fn default() -> Self {
-        Self { avc_config: Default::default() }
+pub type AvcCBox = AvcC;
+
+#[derive(Default, Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvcC {
+    pub avc_config: AVCDecoderConfigurationRecord,
+}
+
+impl AvcC {
+    fn header(&self) -> BoxHeader {
+        BoxHeader::from_id_and_inner_size(Self::ID, self.avc_config.byte_size())
+    }
+}
+
+impl IBox for AvcC {
+    fn byte_size(&self) -> usize {
+        self.header().byte_size() + self.avc_config.byte_size()
+    }
+
+    const ID: BoxType = BoxType::Id(BoxId(*b"avcC"));
+}
+
+#[async_trait]
+impl BoxRead for AvcC {
+    async fn read<R: ReadMp4>(header: BoxHeader, reader: &mut R) -> Result<Self, MP4Error> {
+        let avc_config = AVCDecoderConfigurationRecord::read_sized(reader, header.size_minus_self()).await?;
+        Ok(Self { avc_config })
+    }
+}
+
+impl BoxWrite for AvcC {
+    fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let mut count = 0;
+        count += self.header().write(writer)?;
+        count += self.avc_config.write(writer)?;
+        debug_assert!(count == self.byte_size(), "Byte Size is not equal to written size");
+        Ok(count)
     }
 }
 
@@ -35,7 +162,7 @@ mod test {
     use crate::bytes_read::Mp4Readable;
     use crate::error::MP4Error;
     use crate::header::BoxHeader;
-    use crate::mp4box::avcc::AvcCBox;
+    use crate::mp4box::avcc::{AvcC, AvcCBox, AVCDecoderConfigurationExt, AVCDecoderConfigurationRecord};
     use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
 
     #[test]
@@ -57,4 +184,24 @@ mod test {
         })
     }
 
+    #[test]
+    pub fn test_high_profile_without_ext() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let trailerless = AvcC { avc_config: AVCDecoderConfigurationRecord { profile_indication: 100, ..Default::default() } };
+            let extended = AvcC { avc_config: AVCDecoderConfigurationRecord { profile_indication: 100, ext: Some(AVCDecoderConfigurationExt::default()), ..Default::default() } };
+            for base in [trailerless, extended] {
+                let mut buf = vec![];
+                base.write(&mut std::io::Cursor::new(&mut buf))?;
+                buf.extend_from_slice(&[0, 0, 0, 8, b'f', b'r', b'e', b'e']);
+                let mut cursor = futures::io::Cursor::new(&mut buf);
+                let header = BoxHeader::read(&mut cursor).await?;
+                let new = AvcC::read(header, &mut cursor).await?;
+                assert_eq!(base, new);
+                let next = BoxHeader::read(&mut cursor).await?;
+                assert_eq!(next.id, crate::r#type::BoxType::Id(crate::id::BoxId(*b"free")));
+            }
+            Ok(())
+        })
+    }
+
 }
@@ -9,6 +9,7 @@ use crate::mp4box::box_trait::{PartialBox, PartialBoxRead, PartialBoxWrite};
 use crate::r#type::BoxType;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FullBoxData<F: FlagTrait> {
     pub version: u8,
     pub flags: F
@@ -44,6 +45,7 @@ pub trait FullBoxInfo {
 }
 
 #[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
 pub struct FullBox<P, F>
     where
         P: PartialBox<ParentData=FullBoxData<F>> + FullBoxInfo,
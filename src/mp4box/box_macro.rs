@@ -2,12 +2,13 @@
 
 #[macro_export]
 macro_rules! base_box {
-    (box ($id:expr, $name:ident, $box:ident) $(data { $($data_name:ident: $data:ty),* $(,)* })? $(children { $($child_name:ident: $($child:ident)+),* $(,)*})?) => {
+    (box ($id:expr, $name:ident, $box:ident) $(data { $($(#[$data_attr:meta])* $data_name:ident: $data:ty),* $(,)* })? $(children { $($child_name:ident: $($child:ident)+),* $(,)*})?) => {
         pub type $box = $crate::mp4box::box_root::MP4Box<$name>;
 
         #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct $name {
-            $($(pub $data_name: $data,)*)?
+            $($($(#[$data_attr])* pub $data_name: $data,)*)?
             $($(pub $child_name: base_box!(@type $($child)+)),*)?
         }
 
@@ -99,12 +100,13 @@ macro_rules! base_box {
 
 #[macro_export]
 macro_rules! full_box {
-    (box ($id:expr, $name:ident, $box:ident, $(@save $flag_name:ident :)? $flag:ty) $(data { $($data_name:ident: $data:ty),* $(,)* })? $(children { $($child_name:ident: $($child:ident)+),* $(,)*})?) => {
+    (box ($id:expr, $name:ident, $box:ident, $(@save $flag_name:ident :)? $flag:ty) $(data { $($(#[$data_attr:meta])* $data_name:ident: $data:ty),* $(,)* })? $(children { $($child_name:ident: $($child:ident)+),* $(,)*})?) => {
         pub type $box = $crate::mp4box::box_root::MP4Box<$crate::mp4box::box_full::FullBox<$name, $flag>>;
 
         #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct $name {
-            $($(pub $data_name: $data,)*)?
+            $($($(#[$data_attr])* pub $data_name: $data,)*)?
             $(pub $flag_name: $flag,)?
             $($(pub $child_name: base_box!(@type $($child)+)),*)?
         }
@@ -226,6 +228,7 @@ macro_rules! full_box {
 macro_rules! default_flags {
     ($name:ident, $default:expr) => {
         #[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct $name(pub u32);
 
         impl Default for VmhdFlags {
@@ -525,6 +528,10 @@ macro_rules! flag_option {
             }
 
             impl $crate::bytes_write::Mp4VersionedWritable<$flag> for $name {
+                fn required_version(&self) -> u8 {
+                    self.0.as_ref().map($crate::bytes_write::Mp4VersionedWritable::<$flag>::required_version).unwrap_or_default()
+                }
+
                 fn required_flags(&self) -> $flag {
                     match self.0 { None => <$flag>::default(), Some(_) => <$flag>::[<with_ $value:lower>]() }
                 }
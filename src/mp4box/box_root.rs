@@ -12,6 +12,7 @@ use crate::r#type::BoxType;
 use crate::size::BoxSize::Known;
 
 #[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
 pub struct MP4Box<P>
     where
         P: PartialBox<ParentData=()>
@@ -10,6 +10,7 @@ use crate::r#type::BoxType;
 use crate::size::BoxSize;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnknownBox
 {
     pub id: BoxType,
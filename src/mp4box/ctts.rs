@@ -0,0 +1,126 @@
+use crate::bytes_read::ReadMp4;
+use crate::bytes_reserve::Mp4Reservable;
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::error::MalformedBoxError::UnknownVersion;
+use crate::error::MP4Error;
+use crate::id::BoxId;
+use crate::mp4box::box_full::{FullBox, FullBoxData, FullBoxInfo};
+use crate::mp4box::box_root::MP4Box;
+use crate::mp4box::box_trait::{PartialBox, PartialBoxRead, PartialBoxWrite};
+use crate::r#type::BoxType;
+
+pub type CttsBox = MP4Box<FullBox<Ctts, u32>>;
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CttsEntry {
+    pub sample_count: u32,
+    /// composition time minus decode time
+    pub sample_offset: i32,
+}
+
+/// Composition time to sample box (ISO 14496-12 § 8.6.1.3), version 1 allows negative offsets.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ctts {
+    pub entries: Vec<CttsEntry>,
+}
+
+impl FullBoxInfo for Ctts {
+    type Flag = u32;
+
+    fn version(&self) -> u8 {
+        self.entries.iter().any(|it| it.sample_offset < 0) as u8
+    }
+}
+
+impl PartialBox for Ctts {
+    type ParentData = FullBoxData<u32>;
+    type ThisData = ();
+
+    fn byte_size(&self) -> usize {
+        u32::BYTE_SIZE + self.entries.len() * (u32::BYTE_SIZE + i32::BYTE_SIZE)
+    }
+
+    const ID: BoxType = BoxType::Id(BoxId(*b"ctts"));
+}
+
+#[async_trait::async_trait]
+impl PartialBoxRead for Ctts {
+    async fn read_data<R: ReadMp4>(parent: Self::ParentData, reader: &mut R) -> Result<Self, MP4Error> {
+        let entry_count: u32 = reader.read().await?;
+        let mut entries = vec![];
+        for _ in 0..entry_count {
+            let sample_count = reader.read().await?;
+            let sample_offset = match parent.version {
+                // some encoders write negative offsets in version 0 boxes
+                0 => reader.read::<u32>().await? as i32,
+                1 => reader.read().await?,
+                version => return Err(UnknownVersion(Self::ID, version).into())
+            };
+            entries.push(CttsEntry { sample_count, sample_offset });
+        }
+        Ok(Self { entries })
+    }
+}
+
+impl PartialBoxWrite for Ctts {
+    fn write_data<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let mut count = 0;
+        count += (self.entries.len() as u32).write(writer)?;
+        for entry in &self.entries {
+            count += entry.sample_count.write(writer)?;
+            count += entry.sample_offset.write(writer)?;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bytes_read::Mp4Readable;
+    use crate::error::MP4Error;
+    use crate::header::BoxHeader;
+    use crate::mp4box::box_full::FullBoxInfo;
+    use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox, PartialBox};
+    use crate::mp4box::ctts::{Ctts, CttsBox, CttsEntry};
+
+    #[test]
+    pub fn test_rebuild() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            for sample_offset in [2000, -1000] {
+                let base: CttsBox = Ctts {
+                    entries: vec![
+                        CttsEntry { sample_count: 1, sample_offset: 1000 },
+                        CttsEntry { sample_count: 2, sample_offset },
+                    ]
+                }.into();
+                let mut buf = vec![];
+                let mut cursor = std::io::Cursor::new(&mut buf);
+                let pos = base.write(&mut cursor)?;
+                assert_eq!(pos, base.byte_size());
+                assert_eq!(pos as u64, cursor.position());
+                let mut cursor = futures::io::Cursor::new(&mut buf);
+                let header = BoxHeader::read(&mut cursor).await?;
+                assert_eq!(header.id, Ctts::ID);
+                let new = CttsBox::read(header, &mut cursor).await?;
+                assert_eq!(base, new);
+                assert_eq!(new.version(), (sample_offset < 0) as u8);
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_negative_v0() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let buf = [0, 0, 0, 24, b'c', b't', b't', b's', 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0xff, 0xff, 0xfc, 0x18];
+            let mut cursor = futures::io::Cursor::new(&buf[..]);
+            let header = BoxHeader::read(&mut cursor).await?;
+            let ctts = CttsBox::read(header, &mut cursor).await?;
+            assert_eq!(ctts.entries, vec![CttsEntry { sample_count: 1, sample_offset: -1000 }]);
+            Ok(())
+        })
+    }
+
+}
@@ -8,6 +8,7 @@ use crate::mp4box::box_trait::{PartialBox, PartialBoxRead, PartialBoxWrite};
 use crate::r#type::BoxType;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChannelMapping {
     pub stream_count: u8,
     pub coupled_count: u8,
@@ -37,6 +38,7 @@ impl ChannelMapping {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChannelMappingFamily {
     Family0 {
         stereo: bool
@@ -101,6 +103,7 @@ impl ChannelMappingFamily {
 pub type DOpsBox = MP4Box<DOps>;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DOps {
     pub version: u8,
     pub pre_skip: u16,
@@ -13,6 +13,7 @@ use crate::mp4box::box_unknown::UnknownBox;
 use crate::mp4box::dref::DataEntryBox::Url;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DataEntryBox {
     Url(UrlBox),
     Urn(UrnBox),
@@ -0,0 +1,8 @@
+use crate::base_box;
+use crate::mp4box::elst::ElstBox;
+
+base_box! {
+    box (b"edts", Edts, EdtsBox) children {
+        elst: ElstBox
+    }
+}
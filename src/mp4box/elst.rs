@@ -0,0 +1,194 @@
+use fixed::types::I16F16;
+use fixed_macro::fixed;
+use crate::bytes_read::ReadMp4;
+use crate::bytes_reserve::Mp4Reservable;
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::error::MalformedBoxError::UnknownVersion;
+use crate::error::MP4Error;
+use crate::id::BoxId;
+use crate::mp4box::box_full::{FullBox, FullBoxData, FullBoxInfo};
+use crate::mp4box::box_root::MP4Box;
+use crate::mp4box::box_trait::{PartialBox, PartialBoxRead, PartialBoxWrite};
+use crate::r#type::BoxType;
+
+pub type ElstBox = MP4Box<FullBox<Elst, u32>>;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElstEntry {
+    /// duration of the edit, in the `mvhd` timescale
+    pub segment_duration: u64,
+    /// start of the edit in the media timescale, -1 for an empty edit
+    pub media_time: i64,
+    pub media_rate: I16F16,
+}
+
+impl ElstEntry {
+    pub fn is_empty(&self) -> bool {
+        self.media_time == -1
+    }
+}
+
+impl Default for ElstEntry {
+    fn default() -> Self {
+        Self {
+            segment_duration: 0,
+            media_time: 0,
+            media_rate: fixed!(1: I16F16)
+        }
+    }
+}
+
+/// Edit list box (ISO 14496-12 § 8.6.6), maps the movie timeline to the media timeline of a track.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Elst {
+    pub entries: Vec<ElstEntry>,
+}
+
+impl Elst {
+
+    /// Maps a time of the movie timeline to the media timeline, ignoring the media rate.
+    /// Times in empty edits map to the start of the next edit, times past the last edit are extrapolated from it.
+    /// `None` if the media time does not fit 64 bits.
+    pub fn media_time(&self, movie_time: u64, movie_timescale: u32, media_timescale: u32) -> Option<u64> {
+        if movie_timescale == 0 {
+            return None;
+        }
+        let mut start = 0u64;
+        let mut last = None;
+        for entry in &self.entries {
+            let end = start.saturating_add(entry.segment_duration);
+            if entry.is_empty() {
+                start = end;
+                continue;
+            }
+            let media_start = u64::try_from(entry.media_time).ok()?;
+            let offset = movie_time.saturating_sub(start);
+            let media_offset = u64::try_from(offset as u128 * media_timescale as u128 / movie_timescale as u128).ok()?;
+            let media_time = media_start.checked_add(media_offset)?;
+            if movie_time < end || entry.segment_duration == 0 {
+                return Some(media_time);
+            }
+            last = Some(media_time);
+            start = end;
+        }
+        last
+    }
+}
+
+impl FullBoxInfo for Elst {
+    type Flag = u32;
+
+    fn version(&self) -> u8 {
+        let wide = self.entries.iter().any(|it| {
+            it.segment_duration > u32::MAX as u64 || i32::try_from(it.media_time).is_err()
+        });
+        wide as u8
+    }
+}
+
+impl PartialBox for Elst {
+    type ParentData = FullBoxData<u32>;
+    type ThisData = ();
+
+    fn byte_size(&self) -> usize {
+        let entry_size = match self.version() {
+            0 => u32::BYTE_SIZE + i32::BYTE_SIZE,
+            _ => u64::BYTE_SIZE + i64::BYTE_SIZE
+        } + u32::BYTE_SIZE;
+        u32::BYTE_SIZE + self.entries.len() * entry_size
+    }
+
+    const ID: BoxType = BoxType::Id(BoxId(*b"elst"));
+}
+
+#[async_trait::async_trait]
+impl PartialBoxRead for Elst {
+    async fn read_data<R: ReadMp4>(parent: Self::ParentData, reader: &mut R) -> Result<Self, MP4Error> {
+        let entry_count: u32 = reader.read().await?;
+        let mut entries = vec![];
+        for _ in 0..entry_count {
+            let (segment_duration, media_time) = match parent.version {
+                0 => (reader.read::<u32>().await? as u64, reader.read::<i32>().await? as i64),
+                1 => (reader.read().await?, reader.read().await?),
+                version => return Err(UnknownVersion(Self::ID, version).into())
+            };
+            let media_rate = reader.read().await?;
+            entries.push(ElstEntry { segment_duration, media_time, media_rate });
+        }
+        Ok(Self { entries })
+    }
+}
+
+impl PartialBoxWrite for Elst {
+    fn write_data<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let version = self.version();
+        let mut count = 0;
+        count += (self.entries.len() as u32).write(writer)?;
+        for entry in &self.entries {
+            match version {
+                0 => {
+                    count += (entry.segment_duration as u32).write(writer)?;
+                    count += (entry.media_time as i32).write(writer)?;
+                }
+                _ => {
+                    count += entry.segment_duration.write(writer)?;
+                    count += entry.media_time.write(writer)?;
+                }
+            }
+            count += entry.media_rate.write(writer)?;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bytes_read::Mp4Readable;
+    use crate::error::MP4Error;
+    use crate::header::BoxHeader;
+    use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox, PartialBox};
+    use crate::mp4box::elst::{Elst, ElstBox, ElstEntry};
+
+    #[test]
+    pub fn test_rebuild() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            for segment_duration in [10000, u32::MAX as u64 + 1] {
+                let base: ElstBox = Elst {
+                    entries: vec![
+                        ElstEntry { segment_duration: 500, media_time: -1, ..Default::default() },
+                        ElstEntry { segment_duration, media_time: 1024, ..Default::default() },
+                    ]
+                }.into();
+                let mut buf = vec![];
+                let mut cursor = std::io::Cursor::new(&mut buf);
+                let pos = base.write(&mut cursor)?;
+                assert_eq!(pos, base.byte_size());
+                assert_eq!(pos as u64, cursor.position());
+                let mut cursor = futures::io::Cursor::new(&mut buf);
+                let header = BoxHeader::read(&mut cursor).await?;
+                assert_eq!(header.id, Elst::ID);
+                let new = ElstBox::read(header, &mut cursor).await?;
+                assert_eq!(base, new);
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_media_time() {
+        let elst = Elst {
+            entries: vec![
+                ElstEntry { segment_duration: 500, media_time: -1, ..Default::default() },
+                ElstEntry { segment_duration: 10000, media_time: 2048, ..Default::default() },
+            ]
+        };
+        assert_eq!(elst.media_time(0, 1000, 48000), Some(2048));
+        assert_eq!(elst.media_time(1500, 1000, 48000), Some(2048 + 48000));
+        assert_eq!(elst.media_time(20500, 1000, 48000), Some(2048 + 20000 * 48));
+        assert_eq!(elst.media_time(u64::MAX, 1000, 48000), None);
+        assert_eq!(elst.media_time((u64::MAX - 1000) / 48 + 500, 1000, 48000), None);
+    }
+
+}
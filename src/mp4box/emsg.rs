@@ -0,0 +1,197 @@
+use std::io::SeekFrom;
+use futures::{AsyncReadExt, AsyncSeekExt};
+use crate::bytes_read::ReadMp4;
+use crate::bytes_reserve::Mp4Reservable;
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::error::MalformedBoxError::UnknownVersion;
+use crate::error::MP4Error;
+use crate::header::BoxHeader;
+use crate::id::BoxId;
+use crate::mp4box::box_full::FullBoxData;
+use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+use crate::r#type::BoxType;
+use crate::size::BoxSize;
+
+pub type EmsgBox = Emsg;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EmsgPresentationTime {
+    /// version 0: offset from the earliest presentation time of the segment containing the event
+    Delta(u32),
+    /// version 1: absolute presentation time on the timeline of the track
+    Absolute(u64),
+}
+
+impl Default for EmsgPresentationTime {
+    fn default() -> Self {
+        Self::Delta(0)
+    }
+}
+
+/// Event message (ISO 23009-1 § 5.10.3.3), carries in-band timed metadata such as SCTE-35 cues or ID3 tags.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Emsg {
+    pub scheme_id_uri: String,
+    pub value: String,
+    pub timescale: u32,
+    pub presentation_time: EmsgPresentationTime,
+    pub event_duration: u32,
+    pub id: u32,
+    pub message_data: Vec<u8>,
+}
+
+impl Emsg {
+
+    pub fn version(&self) -> u8 {
+        match self.presentation_time {
+            EmsgPresentationTime::Delta(_) => 0,
+            EmsgPresentationTime::Absolute(_) => 1
+        }
+    }
+
+    fn inner_byte_size(&self) -> usize {
+        let time = match self.presentation_time {
+            EmsgPresentationTime::Delta(_) => u32::BYTE_SIZE,
+            EmsgPresentationTime::Absolute(_) => u64::BYTE_SIZE
+        };
+        FullBoxData { version: self.version(), flags: 0u32 }.byte_size() +
+            self.scheme_id_uri.byte_size() +
+            self.value.byte_size() +
+            self.timescale.byte_size() +
+            time +
+            self.event_duration.byte_size() +
+            self.id.byte_size() +
+            self.message_data.byte_size()
+    }
+
+    fn header(&self) -> BoxHeader {
+        BoxHeader::from_id_and_inner_size(Self::ID, self.inner_byte_size())
+    }
+}
+
+impl IBox for Emsg {
+    fn byte_size(&self) -> usize {
+        self.header().byte_size() + self.inner_byte_size()
+    }
+
+    const ID: BoxType = BoxType::Id(BoxId(*b"emsg"));
+}
+
+#[async_trait::async_trait]
+impl BoxRead for Emsg {
+    async fn read<R: ReadMp4>(header: BoxHeader, reader: &mut R) -> Result<Self, MP4Error> {
+        let start = reader.seek(SeekFrom::Current(0)).await?;
+        let data: FullBoxData<u32> = reader.read().await?;
+        let (scheme_id_uri, value, timescale, presentation_time, event_duration, id) = match data.version {
+            0 => {
+                let scheme_id_uri = reader.read().await?;
+                let value = reader.read().await?;
+                let timescale = reader.read().await?;
+                let delta = reader.read().await?;
+                let event_duration = reader.read().await?;
+                let id = reader.read().await?;
+                (scheme_id_uri, value, timescale, EmsgPresentationTime::Delta(delta), event_duration, id)
+            }
+            1 => {
+                let timescale = reader.read().await?;
+                let time = reader.read().await?;
+                let event_duration = reader.read().await?;
+                let id = reader.read().await?;
+                let scheme_id_uri = reader.read().await?;
+                let value = reader.read().await?;
+                (scheme_id_uri, value, timescale, EmsgPresentationTime::Absolute(time), event_duration, id)
+            }
+            version => return Err(UnknownVersion(Self::ID, version).into())
+        };
+        let message_data = match header.size_minus_self() {
+            BoxSize::Known(size) => {
+                let read = (reader.seek(SeekFrom::Current(0)).await? - start) as usize;
+                let mut vec = vec![0u8; size.saturating_sub(read)];
+                reader.read_exact(vec.as_mut_slice()).await?;
+                vec
+            }
+            BoxSize::Unknown => {
+                let mut vec = vec![];
+                reader.read_to_end(&mut vec).await?;
+                vec
+            }
+        };
+        Ok(Self {
+            scheme_id_uri,
+            value,
+            timescale,
+            presentation_time,
+            event_duration,
+            id,
+            message_data
+        })
+    }
+}
+
+impl BoxWrite for Emsg {
+    fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let mut count = 0;
+        count += self.header().write(writer)?;
+        count += FullBoxData { version: self.version(), flags: 0u32 }.write(writer)?;
+        match self.presentation_time {
+            EmsgPresentationTime::Delta(delta) => {
+                count += self.scheme_id_uri.write(writer)?;
+                count += self.value.write(writer)?;
+                count += self.timescale.write(writer)?;
+                count += delta.write(writer)?;
+                count += self.event_duration.write(writer)?;
+                count += self.id.write(writer)?;
+            }
+            EmsgPresentationTime::Absolute(time) => {
+                count += self.timescale.write(writer)?;
+                count += time.write(writer)?;
+                count += self.event_duration.write(writer)?;
+                count += self.id.write(writer)?;
+                count += self.scheme_id_uri.write(writer)?;
+                count += self.value.write(writer)?;
+            }
+        }
+        count += self.message_data.write(writer)?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bytes_read::Mp4Readable;
+    use crate::error::MP4Error;
+    use crate::header::BoxHeader;
+    use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+    use crate::mp4box::emsg::{Emsg, EmsgBox, EmsgPresentationTime};
+
+    #[test]
+    pub fn test_rebuild() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            for presentation_time in [EmsgPresentationTime::Delta(90), EmsgPresentationTime::Absolute(u32::MAX as u64 + 1)] {
+                let base: EmsgBox = Emsg {
+                    scheme_id_uri: "urn:scte:scte35:2013:bin".to_string(),
+                    value: "1".to_string(),
+                    timescale: 90000,
+                    presentation_time,
+                    event_duration: 180000,
+                    id: 7,
+                    message_data: vec![0xfc, 0x30, 0x11, 0x00]
+                };
+                let mut buf = vec![];
+                let mut cursor = std::io::Cursor::new(&mut buf);
+                let pos = base.write(&mut cursor)?;
+                assert_eq!(pos, base.byte_size());
+                assert_eq!(pos as u64, cursor.position());
+                let mut cursor = futures::io::Cursor::new(&mut buf);
+                let header = BoxHeader::read(&mut cursor).await?;
+                assert_eq!(header.id, EmsgBox::ID);
+                let new = EmsgBox::read(header, &mut cursor).await?;
+                assert_eq!(base, new);
+            }
+            Ok(())
+        })
+    }
+
+}
@@ -11,9 +11,12 @@ use crate::r#type::BoxType;
 pub type FtypBox = Ftyp;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ftyp {
+    #[cfg_attr(feature = "serde", serde(with = "crate::id::fourcc"))]
     pub major_brand: [u8; 4],
     pub minor_version: u32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::id::fourcc::vec"))]
     pub compatible_brands: Vec<[u8; 4]>
 }
 
@@ -4,6 +4,7 @@ full_box! {
     box (b"hdlr", Hdlr, HdlrBox, u32)
     data {
         _res1: u32,
+        #[cfg_attr(feature = "serde", serde(with = "crate::id::fourcc"))]
         handler_type: [u8; 4],
         _res2: [u32; 3],
         name: String,
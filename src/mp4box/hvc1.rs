@@ -0,0 +1,59 @@
+use crate::base_box;
+use crate::codec::Codec;
+use crate::mp4box::hvcc::HvcCBox;
+use crate::types::sample::VisualSampleEntry;
+
+base_box! {
+    box (b"hvc1", Hvc1, Hvc1Box) data {
+        visual_sample_entry: VisualSampleEntry
+    } children {
+        hvcc: HvcCBox
+    }
+}
+
+impl Hvc1 {
+    pub fn codec(&self) -> Option<Codec> {
+        Some(self.hvcc.as_ref()?.hevc_config.codec(*b"hvc1"))
+    }
+
+    pub fn codec_string(&self) -> Option<String> {
+        self.codec().map(|it| it.to_string())
+    }
+}
+
+impl Default for Hvc1 {
+    fn default() -> Self {
+        Self {
+            visual_sample_entry: Default::default(),
+            hvcc: Some(Default::default())
+        }
+    }
+}
+
+// same as `hvc1` but parameter sets may also be sent in band
+base_box! {
+    box (b"hev1", Hev1, Hev1Box) data {
+        visual_sample_entry: VisualSampleEntry
+    } children {
+        hvcc: HvcCBox
+    }
+}
+
+impl Hev1 {
+    pub fn codec(&self) -> Option<Codec> {
+        Some(self.hvcc.as_ref()?.hevc_config.codec(*b"hev1"))
+    }
+
+    pub fn codec_string(&self) -> Option<String> {
+        self.codec().map(|it| it.to_string())
+    }
+}
+
+impl Default for Hev1 {
+    fn default() -> Self {
+        Self {
+            visual_sample_entry: Default::default(),
+            hvcc: Some(Default::default())
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use async_trait::async_trait;
+use crate::base_box;
+use crate::bytes_read::{Mp4Readable, ReadMp4};
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::codec::Codec;
+use crate::error::MP4Error;
+use crate::types::array::Mp4Array;
+
+#[derive(Default, Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HvcCArray {
+    pub array_completeness: bool,
+    pub nal_unit_type: u8,
+    pub nalus: Mp4Array<u16, Mp4Array<u16, u8>>,
+}
+
+#[async_trait]
+impl Mp4Readable for HvcCArray {
+    async fn read<R: ReadMp4>(reader: &mut R) -> Result<Self, MP4Error> {
+        let byte: u8 = reader.read().await?;
+        Ok(Self {
+            array_completeness: byte >> 7 == 1,
+            nal_unit_type: byte & 0b0011_1111,
+            nalus: reader.read().await?
+        })
+    }
+}
+
+impl Mp4Writable for HvcCArray {
+    fn byte_size(&self) -> usize {
+        1 + self.nalus.byte_size()
+    }
+
+    fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let mut count = 0;
+        count += ((self.array_completeness as u8) << 7 | self.nal_unit_type & 0b0011_1111).write(writer)?;
+        count += self.nalus.write(writer)?;
+        Ok(count)
+    }
+}
+
+/// ISO 14496-15 § 8.3.3.1
+#[derive(Default, Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HEVCDecoderConfigurationRecord {
+    pub configuration_version: u8,
+    pub general_profile_space: u8,
+    pub general_tier_flag: bool,
+    pub general_profile_idc: u8,
+    pub general_profile_compatibility_flags: u32,
+    pub general_constraint_indicator_flags: [u8; 6],
+    pub general_level_idc: u8,
+    pub min_spatial_segmentation_idc: u16,
+    pub parallelism_type: u8,
+    pub chroma_format_idc: u8,
+    pub bit_depth_luma_minus8: u8,
+    pub bit_depth_chroma_minus8: u8,
+    pub avg_frame_rate: u16,
+    pub constant_frame_rate: u8,
+    pub num_temporal_layers: u8,
+    pub temporal_id_nested: bool,
+    pub length_size_minus_one: u8,
+    pub arrays: Mp4Array<u8, HvcCArray>,
+}
+
+#[async_trait]
+impl Mp4Readable for HEVCDecoderConfigurationRecord {
+    async fn read<R: ReadMp4>(reader: &mut R) -> Result<Self, MP4Error> {
+        let configuration_version = reader.read().await?;
+        let profile: u8 = reader.read().await?;
+        let general_profile_compatibility_flags = reader.read().await?;
+        let general_constraint_indicator_flags = reader.read().await?;
+        let general_level_idc = reader.read().await?;
+        let min_spatial_segmentation_idc: u16 = reader.read().await?;
+        let parallelism_type: u8 = reader.read().await?;
+        let chroma_format_idc: u8 = reader.read().await?;
+        let bit_depth_luma_minus8: u8 = reader.read().await?;
+        let bit_depth_chroma_minus8: u8 = reader.read().await?;
+        let avg_frame_rate = reader.read().await?;
+        let temporal: u8 = reader.read().await?;
+        let arrays = reader.read().await?;
+        Ok(Self {
+            configuration_version,
+            general_profile_space: profile >> 6,
+            general_tier_flag: (profile >> 5) & 1 == 1,
+            general_profile_idc: profile & 0b1_1111,
+            general_profile_compatibility_flags,
+            general_constraint_indicator_flags,
+            general_level_idc,
+            min_spatial_segmentation_idc: min_spatial_segmentation_idc & 0x0FFF,
+            parallelism_type: parallelism_type & 0b11,
+            chroma_format_idc: chroma_format_idc & 0b11,
+            bit_depth_luma_minus8: bit_depth_luma_minus8 & 0b111,
+            bit_depth_chroma_minus8: bit_depth_chroma_minus8 & 0b111,
+            avg_frame_rate,
+            constant_frame_rate: temporal >> 6,
+            num_temporal_layers: (temporal >> 3) & 0b111,
+            temporal_id_nested: (temporal >> 2) & 1 == 1,
+            length_size_minus_one: temporal & 0b11,
+            arrays
+        })
+    }
+}
+
+impl Mp4Writable for HEVCDecoderConfigurationRecord {
+    fn byte_size(&self) -> usize {
+        22 + self.arrays.byte_size()
+    }
+
+    fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let mut count = 0;
+        count += self.configuration_version.write(writer)?;
+        count += ((self.general_profile_space & 0b11) << 6 | (self.general_tier_flag as u8) << 5 | self.general_profile_idc & 0b1_1111).write(writer)?;
+        count += self.general_profile_compatibility_flags.write(writer)?;
+        count += self.general_constraint_indicator_flags.write(writer)?;
+        count += self.general_level_idc.write(writer)?;
+        count += (0xF000 | self.min_spatial_segmentation_idc & 0x0FFF).write(writer)?;
+        count += (0b1111_1100 | self.parallelism_type & 0b11).write(writer)?;
+        count += (0b1111_1100 | self.chroma_format_idc & 0b11).write(writer)?;
+        count += (0b1111_1000 | self.bit_depth_luma_minus8 & 0b111).write(writer)?;
+        count += (0b1111_1000 | self.bit_depth_chroma_minus8 & 0b111).write(writer)?;
+        count += self.avg_frame_rate.write(writer)?;
+        count += ((self.constant_frame_rate & 0b11) << 6 | (self.num_temporal_layers & 0b111) << 3 | (self.temporal_id_nested as u8) << 2 | self.length_size_minus_one & 0b11).write(writer)?;
+        count += self.arrays.write(writer)?;
+        Ok(count)
+    }
+}
+
+impl HEVCDecoderConfigurationRecord {
+    /// The codec of a sample entry with this configuration, `fourcc` being `hvc1` or `hev1`.
+    pub fn codec(&self, fourcc: [u8; 4]) -> Codec {
+        Codec::Hevc {
+            fourcc,
+            profile_space: self.general_profile_space,
+            profile_idc: self.general_profile_idc,
+            profile_compatibility_flags: self.general_profile_compatibility_flags,
+            tier_flag: self.general_tier_flag,
+            level_idc: self.general_level_idc,
+            constraint_indicator_flags: self.general_constraint_indicator_flags
+        }
+    }
+}
+
+base_box! {
+    box (b"hvcC", HvcC, HvcCBox) data {
+        hevc_config: HEVCDecoderConfigurationRecord
+    }
+}
+
+impl Default for HvcC {
+    fn default() -> Self {
+        Self {
+            hevc_config: HEVCDecoderConfigurationRecord {
+                configuration_version: 1,
+                ..Default::default()
+            }
+        }
+    }
+}
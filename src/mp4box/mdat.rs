@@ -10,6 +10,7 @@ use crate::size::BoxSize;
 use async_trait::async_trait;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MdatBox(pub Vec<u8>);
 
 impl MdatBox {
@@ -0,0 +1,97 @@
+use crate::bytes_read::ReadMp4;
+use crate::bytes_reserve::Mp4Reservable;
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::error::MalformedBoxError::UnknownVersion;
+use crate::error::MP4Error;
+use crate::id::BoxId;
+use crate::mp4box::box_full::{FullBox, FullBoxData, FullBoxInfo};
+use crate::mp4box::box_root::MP4Box;
+use crate::mp4box::box_trait::{PartialBox, PartialBoxRead, PartialBoxWrite};
+use crate::r#type::BoxType;
+use crate::types::versioned_u32_u64::VersionedU32U64;
+
+pub type MehdBox = MP4Box<FullBox<Mehd, u32>>;
+
+/// Movie extends header box (ISO 14496-12 § 8.8.2), the duration of the whole fragmented movie.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mehd {
+    pub fragment_duration: VersionedU32U64,
+    /// writes a version 1 box even when the duration fits 32 bits, so that it can grow without resizing the box
+    pub long_duration: bool,
+}
+
+impl FullBoxInfo for Mehd {
+    type Flag = u32;
+
+    fn version(&self) -> u8 {
+        (self.long_duration || *self.fragment_duration >= u32::MAX as u64) as u8
+    }
+}
+
+impl PartialBox for Mehd {
+    type ParentData = FullBoxData<u32>;
+    type ThisData = ();
+
+    fn byte_size(&self) -> usize {
+        match self.version() {
+            0 => u32::BYTE_SIZE,
+            _ => u64::BYTE_SIZE
+        }
+    }
+
+    const ID: BoxType = BoxType::Id(BoxId(*b"mehd"));
+}
+
+#[async_trait::async_trait]
+impl PartialBoxRead for Mehd {
+    async fn read_data<R: ReadMp4>(parent: Self::ParentData, reader: &mut R) -> Result<Self, MP4Error> {
+        let fragment_duration = match parent.version {
+            0 => reader.read::<u32>().await? as u64,
+            1 => reader.read::<u64>().await?,
+            version => return Err(UnknownVersion(Self::ID, version).into())
+        };
+        Ok(Self { fragment_duration: fragment_duration.into(), long_duration: parent.version == 1 })
+    }
+}
+
+impl PartialBoxWrite for Mehd {
+    fn write_data<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        match self.version() {
+            0 => (*self.fragment_duration as u32).write(writer),
+            _ => self.fragment_duration.0.write(writer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bytes_read::Mp4Readable;
+    use crate::error::MP4Error;
+    use crate::header::BoxHeader;
+    use crate::mp4box::box_full::FullBoxInfo;
+    use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox, PartialBox};
+    use crate::mp4box::mehd::{Mehd, MehdBox};
+
+    #[test]
+    pub fn test_rebuild() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            for (fragment_duration, long_duration) in [(90000u64, false), (90000, true), (u32::MAX as u64 + 1, true)] {
+                let base: MehdBox = Mehd { fragment_duration: fragment_duration.into(), long_duration }.into();
+                let mut buf = vec![];
+                let mut cursor = std::io::Cursor::new(&mut buf);
+                let pos = base.write(&mut cursor)?;
+                assert_eq!(pos as u64, cursor.position());
+                assert_eq!(pos, base.byte_size());
+                let mut cursor = futures::io::Cursor::new(&mut buf);
+                let header = BoxHeader::read(&mut cursor).await?;
+                assert_eq!(header.id, Mehd::ID);
+                let new = MehdBox::read(header, &mut cursor).await?;
+                assert_eq!(base, new);
+                assert_eq!(new.version(), long_duration as u8);
+            }
+            Ok(())
+        })
+    }
+
+}
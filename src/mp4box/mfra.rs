@@ -0,0 +1,16 @@
+use crate::base_box;
+use crate::mp4box::mfro::MfroBox;
+use crate::mp4box::tfra::TfraBox;
+
+base_box! {
+    box (b"mfra", Mfra, MfraBox) children {
+        tfras: vec TfraBox,
+        mfro: MfroBox,
+    }
+}
+
+impl Mfra {
+    pub fn tfra(&self, track_id: u32) -> Option<&TfraBox> {
+        self.tfras.iter().find(|it| it.track_id == track_id)
+    }
+}
@@ -0,0 +1,8 @@
+use crate::full_box;
+
+full_box! {
+    box (b"mfro", Mfro, MfroBox, u32)
+    data {
+        parent_size: u32,
+    }
+}
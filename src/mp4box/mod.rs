@@ -5,6 +5,8 @@ pub mod mvhd;
 pub mod moov;
 pub mod mvex;
 pub mod trex;
+pub mod mehd;
+pub mod trep;
 pub mod trak;
 pub mod tkhd;
 pub mod mdia;
@@ -23,6 +25,8 @@ pub mod stsd;
 pub mod box_unknown;
 pub mod avc1;
 pub mod avcc;
+pub mod hvc1;
+pub mod hvcc;
 pub mod stsc;
 pub mod stco;
 pub mod co64;
@@ -38,3 +42,16 @@ pub mod opus;
 pub mod dops;
 pub mod ftyp;
 pub mod stsz;
+pub mod stz2;
+pub mod stss;
+pub mod ctts;
+pub mod edts;
+pub mod elst;
+pub mod tfra;
+pub mod mfro;
+pub mod mfra;
+pub mod emsg;
+pub mod sidx;
+pub mod sbgp;
+pub mod sgpd;
+pub mod sdtp;
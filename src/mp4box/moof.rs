@@ -1,6 +1,7 @@
 use crate::base_box;
 use crate::mp4box::mfhd::MfhdBox;
-use crate::mp4box::traf::TrafBox;
+use crate::mp4box::moov::Moov;
+use crate::mp4box::traf::{ResolvedSample, TrafBox};
 
 base_box! {
     box (b"moof", Moof, MoofBox) children {
@@ -8,3 +9,23 @@ base_box! {
         trafs: vec TrafBox
     }
 }
+
+impl Moof {
+    pub fn traf(&self, track_id: u32) -> Option<&TrafBox> {
+        self.trafs.iter().find(|traf| traf.track_id() == Some(track_id))
+    }
+
+    /// Resolves the samples of every `traf`, in order, with the `trex` defaults of the `moov`.
+    /// `moof_offset` is the position of the `moof` in the file, or 0 for offsets relative to it.
+    pub fn samples(&self, moof_offset: u64, moov: Option<&Moov>) -> Vec<Vec<ResolvedSample>> {
+        let mut implicit_base = moof_offset;
+        self.trafs.iter().map(|traf| {
+            let trex = traf.track_id().and_then(|id| moov?.trex(id)).map(|it| &it.inner.inner);
+            let samples = traf.samples_from(trex, moof_offset, implicit_base);
+            if let Some(last) = samples.last() {
+                implicit_base = last.offset + last.size as u64;
+            }
+            samples
+        }).collect()
+    }
+}
@@ -2,6 +2,7 @@ use crate::base_box;
 use crate::mp4box::mvhd::MvhdBox;
 use crate::mp4box::mvex::MvexBox;
 use crate::mp4box::trak::TrakBox;
+use crate::mp4box::trex::TrexBox;
 
 base_box! {
     box (b"moov", Moov, MoovBox) children {
@@ -10,3 +11,62 @@ base_box! {
         mvex: MvexBox,
     }
 }
+
+impl Moov {
+    pub fn trak(&self, track_id: u32) -> Option<&TrakBox> {
+        self.traks.iter().find(|trak| trak.track_id() == Some(track_id))
+    }
+
+    pub fn trex(&self, track_id: u32) -> Option<&TrexBox> {
+        self.mvex.as_ref()?.trex.iter().find(|trex| trex.track_id == track_id)
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod test {
+    use std::time::Duration;
+    use crate::demux::Demuxer;
+    use crate::error::MP4Error;
+    use crate::mp4box::box_trait::BoxWrite;
+    use crate::mp4box::ftyp::FtypBox;
+    use crate::mp4box::hdlr::Hdlr;
+    use crate::remux::Remuxer;
+    use crate::remux::test::progressive;
+    use crate::types::date::{base_date, Mp4DateTime};
+
+    fn round_trip<T: BoxWrite + serde::Serialize + serde::de::DeserializeOwned>(value: &T) -> Result<String, MP4Error> {
+        let json = serde_json::to_string(value).unwrap();
+        let parsed: T = serde_json::from_str(&json).unwrap();
+        let (mut expected, mut actual) = (vec![], vec![]);
+        value.write(&mut expected)?;
+        parsed.write(&mut actual)?;
+        assert_eq!(actual, expected);
+        Ok(json)
+    }
+
+    #[test]
+    pub fn test_json() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let buf = progressive()?;
+            let mut moov = Demuxer::new(futures::io::Cursor::new(buf.clone())).read_moov().await?.clone();
+            if let Some(mdia) = moov.traks[0].mdia.as_mut() {
+                mdia.hdlr = Some(Hdlr { handler_type: *b"vide", ..Default::default() }.into());
+            }
+            let json = round_trip(&moov)?;
+            assert!(json.contains(r#""handler_type":"vide""#));
+            assert!(json.contains(r#""language":"und""#));
+            assert!(json.contains(r#""rate":"1""#));
+            assert!(json.contains(r#""sample_offset":-1000"#));
+            let date = Mp4DateTime::from(base_date() + chrono::Duration::seconds(60));
+            assert_eq!(serde_json::to_string(&date).unwrap(), r#""1904-01-01T00:01:00Z""#);
+            assert_eq!(serde_json::from_str::<Mp4DateTime>(r#""1904-01-01T00:01:00Z""#).unwrap(), date);
+
+            let remuxer = Remuxer::new(futures::io::Cursor::new(buf), Duration::from_secs(2)).await?;
+            let ftyp: FtypBox = remuxer.ftyp();
+            assert_eq!(round_trip(&ftyp)?, r#"{"major_brand":"iso6","minor_version":0,"compatible_brands":["iso6","mp41"]}"#);
+            let json = round_trip(remuxer.moov())?;
+            assert!(json.contains(r#""default_sample_flags":{"is_leading":"Unknown","depends_on":"Unknown""#));
+            Ok(())
+        })
+    }
+}
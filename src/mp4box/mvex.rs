@@ -1,8 +1,12 @@
 use crate::base_box;
+use crate::mp4box::mehd::MehdBox;
+use crate::mp4box::trep::TrepBox;
 use crate::mp4box::trex::TrexBox;
 
 base_box! {
     box (b"mvex", Mvex, MvexBox) children {
-        trex: vec TrexBox
+        mehd: MehdBox,
+        trex: vec TrexBox,
+        trep: vec TrepBox,
     }
 }
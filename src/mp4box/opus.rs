@@ -1,4 +1,5 @@
 use crate::base_box;
+use crate::codec::Codec;
 use crate::mp4box::dops::DOpsBox;
 use crate::types::sample::AudioSampleEntry;
 
@@ -9,3 +10,13 @@ base_box! {
         dops: DOpsBox
     }
 }
+
+impl Opus {
+    pub fn codec(&self) -> Codec {
+        Codec::Opus
+    }
+
+    pub fn codec_string(&self) -> String {
+        self.codec().to_string()
+    }
+}
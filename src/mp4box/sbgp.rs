@@ -0,0 +1,172 @@
+use async_trait::async_trait;
+use crate::bytes_read::{Mp4Readable, ReadMp4};
+use crate::bytes_reserve::Mp4Reservable;
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::error::MalformedBoxError::UnknownVersion;
+use crate::error::MP4Error;
+use crate::header::BoxHeader;
+use crate::id::BoxId;
+use crate::mp4box::box_full::FullBoxData;
+use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+use crate::r#type::BoxType;
+
+pub type SbgpBox = Sbgp;
+
+/// A run of consecutive samples belonging to the same group.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SbgpEntry {
+    pub sample_count: u32,
+    /// 1-based index of the `sgpd` entry, 0 when the samples are not part of any group of this type
+    pub group_description_index: u32,
+}
+
+#[async_trait]
+impl Mp4Readable for SbgpEntry {
+    async fn read<R: ReadMp4>(reader: &mut R) -> Result<Self, MP4Error> {
+        Ok(Self {
+            sample_count: reader.read().await?,
+            group_description_index: reader.read().await?
+        })
+    }
+}
+
+impl Mp4Writable for SbgpEntry {
+    fn byte_size(&self) -> usize {
+        self.sample_count.byte_size() + self.group_description_index.byte_size()
+    }
+
+    fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let mut count = 0;
+        count += self.sample_count.write(writer)?;
+        count += self.group_description_index.write(writer)?;
+        Ok(count)
+    }
+}
+
+/// Sample to group box (ISO 14496-12 § 8.9.2), assigns samples to the entries of the `sgpd` of the same grouping type.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sbgp {
+    #[cfg_attr(feature = "serde", serde(with = "crate::id::fourcc"))]
+    pub grouping_type: [u8; 4],
+    /// only present in version 1
+    pub grouping_type_parameter: Option<u32>,
+    pub entries: Vec<SbgpEntry>,
+}
+
+impl Sbgp {
+
+    pub fn version(&self) -> u8 {
+        self.grouping_type_parameter.is_some() as u8
+    }
+
+    /// The `group_description_index` of a 0-based sample, 0 when the sample is not part of any group.
+    pub fn group_description_index(&self, sample: u32) -> u32 {
+        let mut first = 0u32;
+        for entry in &self.entries {
+            first = first.saturating_add(entry.sample_count);
+            if sample < first {
+                return entry.group_description_index;
+            }
+        }
+        0
+    }
+
+    fn inner_byte_size(&self) -> usize {
+        FullBoxData { version: self.version(), flags: 0u32 }.byte_size() +
+            self.grouping_type.byte_size() +
+            self.grouping_type_parameter.map(|_| u32::BYTE_SIZE).unwrap_or_default() +
+            u32::BYTE_SIZE +
+            self.entries.byte_size()
+    }
+
+    fn header(&self) -> BoxHeader {
+        BoxHeader::from_id_and_inner_size(Self::ID, self.inner_byte_size())
+    }
+}
+
+impl IBox for Sbgp {
+    fn byte_size(&self) -> usize {
+        self.header().byte_size() + self.inner_byte_size()
+    }
+
+    const ID: BoxType = BoxType::Id(BoxId(*b"sbgp"));
+}
+
+#[async_trait]
+impl BoxRead for Sbgp {
+    async fn read<R: ReadMp4>(_: BoxHeader, reader: &mut R) -> Result<Self, MP4Error> {
+        let data: FullBoxData<u32> = reader.read().await?;
+        let grouping_type = reader.read().await?;
+        let grouping_type_parameter = match data.version {
+            0 => None,
+            1 => Some(reader.read().await?),
+            version => return Err(UnknownVersion(Self::ID, version).into())
+        };
+        let entry_count: u32 = reader.read().await?;
+        let mut entries = vec![];
+        for _ in 0..entry_count {
+            entries.push(reader.read().await?);
+        }
+        Ok(Self {
+            grouping_type,
+            grouping_type_parameter,
+            entries
+        })
+    }
+}
+
+impl BoxWrite for Sbgp {
+    fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let mut count = 0;
+        count += self.header().write(writer)?;
+        count += FullBoxData { version: self.version(), flags: 0u32 }.write(writer)?;
+        count += self.grouping_type.write(writer)?;
+        if let Some(grouping_type_parameter) = self.grouping_type_parameter {
+            count += grouping_type_parameter.write(writer)?;
+        }
+        count += (self.entries.len() as u32).write(writer)?;
+        count += self.entries.write(writer)?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bytes_read::Mp4Readable;
+    use crate::error::MP4Error;
+    use crate::header::BoxHeader;
+    use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+    use crate::mp4box::sbgp::{Sbgp, SbgpBox, SbgpEntry};
+
+    #[test]
+    pub fn test_rebuild() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            for grouping_type_parameter in [None, Some(4)] {
+                let base: SbgpBox = Sbgp {
+                    grouping_type: *b"roll",
+                    grouping_type_parameter,
+                    entries: vec![
+                        SbgpEntry { sample_count: 1, group_description_index: 1 },
+                        SbgpEntry { sample_count: 10, group_description_index: 0 },
+                    ]
+                };
+                let mut buf = vec![];
+                let mut cursor = std::io::Cursor::new(&mut buf);
+                let pos = base.write(&mut cursor)?;
+                assert_eq!(pos, base.byte_size());
+                assert_eq!(pos as u64, cursor.position());
+                let mut cursor = futures::io::Cursor::new(&mut buf);
+                let header = BoxHeader::read(&mut cursor).await?;
+                assert_eq!(header.id, SbgpBox::ID);
+                let new = SbgpBox::read(header, &mut cursor).await?;
+                assert_eq!(base, new);
+                assert_eq!(new.group_description_index(0), 1);
+                assert_eq!(new.group_description_index(5), 0);
+            }
+            Ok(())
+        })
+    }
+
+}
@@ -0,0 +1,197 @@
+use std::io::SeekFrom;
+use async_trait::async_trait;
+use futures::{AsyncReadExt, AsyncSeekExt};
+use crate::bytes_read::{Mp4Readable, ReadMp4};
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::error::MP4Error;
+use crate::header::BoxHeader;
+use crate::id::BoxId;
+use crate::mp4box::box_full::FullBoxData;
+use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+use crate::mp4box::trex::{IsLeading, SampleDependsOn, SampleFlags, SampleHasRedundancy, SampleIsDependedOn};
+use crate::r#type::BoxType;
+use crate::size::BoxSize;
+
+pub type SdtpBox = Sdtp;
+
+/// The dependency bits of a sample, laid out like the top byte of [`SampleFlags`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SdtpEntry(pub u8);
+
+impl SdtpEntry {
+
+    pub fn new(leading: IsLeading, depends_on: SampleDependsOn, is_depended_on: SampleIsDependedOn, has_redundancy: SampleHasRedundancy) -> Self {
+        Self((leading as u8) << 6 | (depends_on as u8) << 4 | (is_depended_on as u8) << 2 | has_redundancy as u8)
+    }
+
+    pub fn leading(&self) -> IsLeading {
+        (self.0 >> 6).try_into().unwrap_or(IsLeading::Unknown)
+    }
+
+    pub fn depends_on(&self) -> SampleDependsOn {
+        (self.0 >> 4 & 0b11).try_into().unwrap_or(SampleDependsOn::Unknown)
+    }
+
+    pub fn is_depended_on(&self) -> SampleIsDependedOn {
+        (self.0 >> 2 & 0b11).try_into().unwrap_or(SampleIsDependedOn::Unknown)
+    }
+
+    pub fn has_redundancy(&self) -> SampleHasRedundancy {
+        (self.0 & 0b11).try_into().unwrap_or(SampleHasRedundancy::Unknown)
+    }
+
+    /// No other sample depends on this one, it can be dropped without breaking the decoding.
+    pub fn is_disposable(&self) -> bool {
+        self.is_depended_on() == SampleIsDependedOn::NotDependedOn
+    }
+
+    /// Copies the dependency bits into `flags`, leaving the sync, padding and priority bits untouched.
+    pub fn apply(&self, flags: &mut SampleFlags) {
+        flags.set_is_leading(self.0 >> 6);
+        flags.set_sample_depends_on(self.0 >> 4 & 0b11);
+        flags.set_sample_is_depended_on(self.0 >> 2 & 0b11);
+        flags.set_sample_has_redundancy(self.0 & 0b11);
+    }
+}
+
+impl From<SampleFlags> for SdtpEntry {
+    fn from(flags: SampleFlags) -> Self {
+        Self((flags.raw() >> 20) as u8)
+    }
+}
+
+#[async_trait]
+impl Mp4Readable for SdtpEntry {
+    async fn read<R: ReadMp4>(reader: &mut R) -> Result<Self, MP4Error> {
+        Ok(Self(reader.read().await?))
+    }
+}
+
+impl Mp4Writable for SdtpEntry {
+    fn byte_size(&self) -> usize {
+        self.0.byte_size()
+    }
+
+    fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        self.0.write(writer)
+    }
+}
+
+/// Independent and disposable samples box (ISO 14496-12 § 8.6.4), one entry per sample of the `stsz` or the `trun`s.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sdtp {
+    pub entries: Vec<SdtpEntry>,
+}
+
+impl Sdtp {
+
+    pub fn entry(&self, sample: usize) -> Option<SdtpEntry> {
+        self.entries.get(sample).copied()
+    }
+
+    fn inner_byte_size(&self) -> usize {
+        FullBoxData { version: 0, flags: 0u32 }.byte_size() + self.entries.byte_size()
+    }
+
+    fn header(&self) -> BoxHeader {
+        BoxHeader::from_id_and_inner_size(Self::ID, self.inner_byte_size())
+    }
+}
+
+impl From<Vec<SdtpEntry>> for Sdtp {
+    fn from(entries: Vec<SdtpEntry>) -> Self {
+        Self { entries }
+    }
+}
+
+impl IBox for Sdtp {
+    fn byte_size(&self) -> usize {
+        self.header().byte_size() + self.inner_byte_size()
+    }
+
+    const ID: BoxType = BoxType::Id(BoxId(*b"sdtp"));
+}
+
+#[async_trait]
+impl BoxRead for Sdtp {
+    async fn read<R: ReadMp4>(header: BoxHeader, reader: &mut R) -> Result<Self, MP4Error> {
+        let start = reader.seek(SeekFrom::Current(0)).await?;
+        let _: FullBoxData<u32> = reader.read().await?;
+        let data = match header.size_minus_self() {
+            BoxSize::Known(size) => {
+                let read = (reader.seek(SeekFrom::Current(0)).await? - start) as usize;
+                let mut vec = vec![0u8; size.saturating_sub(read)];
+                reader.read_exact(vec.as_mut_slice()).await?;
+                vec
+            }
+            BoxSize::Unknown => {
+                let mut vec = vec![];
+                reader.read_to_end(&mut vec).await?;
+                vec
+            }
+        };
+        Ok(Self {
+            entries: data.into_iter().map(SdtpEntry).collect()
+        })
+    }
+}
+
+impl BoxWrite for Sdtp {
+    fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let mut count = 0;
+        count += self.header().write(writer)?;
+        count += FullBoxData { version: 0, flags: 0u32 }.write(writer)?;
+        count += self.entries.write(writer)?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bytes_read::Mp4Readable;
+    use crate::error::MP4Error;
+    use crate::header::BoxHeader;
+    use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+    use crate::mp4box::sdtp::{Sdtp, SdtpBox, SdtpEntry};
+    use crate::mp4box::trex::{IsLeading, SampleDependsOn, SampleFlags, SampleHasRedundancy, SampleIsDependedOn};
+
+    #[test]
+    pub fn test_rebuild() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let base: SdtpBox = Sdtp {
+                entries: vec![
+                    SdtpEntry::from(SampleFlags::sync()),
+                    SdtpEntry::new(IsLeading::NotLeading, SampleDependsOn::DependsOn, SampleIsDependedOn::NotDependedOn, SampleHasRedundancy::NotRedundant),
+                ]
+            };
+            let mut buf = vec![];
+            let mut cursor = std::io::Cursor::new(&mut buf);
+            let pos = base.write(&mut cursor)?;
+            assert_eq!(pos, base.byte_size());
+            assert_eq!(pos as u64, cursor.position());
+            let mut cursor = futures::io::Cursor::new(&mut buf);
+            let header = BoxHeader::read(&mut cursor).await?;
+            assert_eq!(header.id, SdtpBox::ID);
+            let new = SdtpBox::read(header, &mut cursor).await?;
+            assert_eq!(base, new);
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_sample_flags() {
+        let entry = SdtpEntry::new(IsLeading::LeadingWithoutDependency, SampleDependsOn::DependsOn, SampleIsDependedOn::NotDependedOn, SampleHasRedundancy::Unknown);
+        assert_eq!(entry.leading(), IsLeading::LeadingWithoutDependency);
+        assert!(entry.is_disposable());
+        let mut flags = SampleFlags::non_sync_depends();
+        flags.set_depends_on(SampleDependsOn::Unknown);
+        entry.apply(&mut flags);
+        assert_eq!(flags.depends_on(), SampleDependsOn::DependsOn);
+        assert_eq!(flags.is_depended_on(), SampleIsDependedOn::NotDependedOn);
+        assert!(!flags.is_sync());
+        assert_eq!(SdtpEntry::from(flags), entry);
+    }
+
+}
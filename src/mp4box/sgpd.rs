@@ -0,0 +1,305 @@
+use std::io::SeekFrom;
+use futures::{AsyncReadExt, AsyncSeekExt};
+use crate::bytes_read::ReadMp4;
+use crate::bytes_reserve::Mp4Reservable;
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::error::MalformedBoxError::UnknownVersion;
+use crate::error::MP4Error;
+use crate::header::BoxHeader;
+use crate::id::BoxId;
+use crate::mp4box::box_full::FullBoxData;
+use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+use crate::r#type::BoxType;
+use crate::size::BoxSize;
+
+pub type SgpdBox = Sgpd;
+
+pub const ROLL: [u8; 4] = *b"roll";
+pub const PROL: [u8; 4] = *b"prol";
+pub const RAP: [u8; 4] = *b"rap ";
+pub const SYNC: [u8; 4] = *b"sync";
+pub const TELE: [u8; 4] = *b"tele";
+
+/// A sample group description, its layout is given by the grouping type of the `sgpd`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SampleGroupEntry {
+    /// `roll`: the samples to decode (negative) or skip (positive) around the grouped sample to recover correctly, e.g. the pre-roll of Opus or AAC
+    Roll { roll_distance: i16 },
+    /// `prol`: like `roll`, for a sample that can be decoded without the previous ones but needs a pre-roll
+    Prol { roll_distance: i16 },
+    /// `rap `: open-GOP random access points, with the number of leading samples that cannot be decoded
+    Rap { num_leading_samples_known: bool, num_leading_samples: u8 },
+    /// `sync`: the NAL unit type of a sync sample
+    Sync { nal_unit_type: u8 },
+    /// `tele`: temporal level
+    Tele { level_independently_decodable: bool },
+    /// the raw description of any other grouping type
+    Unknown(Vec<u8>),
+}
+
+impl SampleGroupEntry {
+
+    /// The size of the entries of a known grouping type, as they can be stored without a length.
+    pub fn fixed_size(grouping_type: [u8; 4]) -> Option<usize> {
+        match grouping_type {
+            ROLL | PROL => Some(i16::BYTE_SIZE),
+            RAP | SYNC | TELE => Some(u8::BYTE_SIZE),
+            _ => None
+        }
+    }
+
+    /// Reads an entry of `length` bytes. Known grouping types with an unexpected length are kept raw.
+    pub async fn read<R: ReadMp4>(grouping_type: [u8; 4], length: usize, reader: &mut R) -> Result<Self, MP4Error> {
+        if Self::fixed_size(grouping_type) != Some(length) {
+            let mut data = vec![0u8; length];
+            reader.read_exact(&mut data).await?;
+            return Ok(Self::Unknown(data));
+        }
+        Ok(match grouping_type {
+            ROLL => Self::Roll { roll_distance: reader.read().await? },
+            PROL => Self::Prol { roll_distance: reader.read().await? },
+            RAP => {
+                let byte: u8 = reader.read().await?;
+                Self::Rap { num_leading_samples_known: byte >> 7 == 1, num_leading_samples: byte & 0x7F }
+            }
+            SYNC => Self::Sync { nal_unit_type: reader.read::<u8>().await? & 0x3F },
+            TELE => Self::Tele { level_independently_decodable: reader.read::<u8>().await? >> 7 == 1 },
+            _ => unreachable!()
+        })
+    }
+}
+
+impl Mp4Writable for SampleGroupEntry {
+    fn byte_size(&self) -> usize {
+        match self {
+            Self::Roll { .. } | Self::Prol { .. } => i16::BYTE_SIZE,
+            Self::Rap { .. } | Self::Sync { .. } | Self::Tele { .. } => u8::BYTE_SIZE,
+            Self::Unknown(data) => data.len()
+        }
+    }
+
+    fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        match self {
+            Self::Roll { roll_distance } | Self::Prol { roll_distance } => roll_distance.write(writer),
+            Self::Rap { num_leading_samples_known, num_leading_samples } =>
+                ((*num_leading_samples_known as u8) << 7 | num_leading_samples & 0x7F).write(writer),
+            Self::Sync { nal_unit_type } => (nal_unit_type & 0x3F).write(writer),
+            Self::Tele { level_independently_decodable } => ((*level_independently_decodable as u8) << 7).write(writer),
+            Self::Unknown(data) => data.write(writer)
+        }
+    }
+}
+
+/// Sample group description box (ISO 14496-12 § 8.9.3), the descriptions the `sbgp` of the same grouping type refers to.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sgpd {
+    #[cfg_attr(feature = "serde", serde(with = "crate::id::fourcc"))]
+    pub grouping_type: [u8; 4],
+    /// version 1 and above: the size of every entry, 0 when each entry is preceded by its own size
+    pub default_length: Option<u32>,
+    /// version 2 and above: the entry of the samples that are not mapped by a `sbgp`, 0 for none
+    pub default_sample_description_index: Option<u32>,
+    pub entries: Vec<SampleGroupEntry>,
+}
+
+impl Sgpd {
+
+    /// A version 1 box, with a `default_length` when all the entries have the same size.
+    pub fn new(grouping_type: [u8; 4], entries: Vec<SampleGroupEntry>) -> Self {
+        let mut sizes = entries.iter().map(Mp4Writable::byte_size);
+        let first = sizes.next().unwrap_or_default();
+        let default_length = if sizes.all(|it| it == first) { first as u32 } else { 0 };
+        Self {
+            grouping_type,
+            default_length: Some(default_length),
+            default_sample_description_index: None,
+            entries
+        }
+    }
+
+    pub fn version(&self) -> u8 {
+        if self.default_sample_description_index.is_some() {
+            2
+        } else if self.default_length.is_some() {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// The entry a `sbgp` `group_description_index` refers to.
+    /// Indices above `0x10000` refer to the `sgpd` of the track fragment rather than of the track.
+    pub fn entry(&self, group_description_index: u32) -> Option<&SampleGroupEntry> {
+        let index = group_description_index & 0xFFFF;
+        self.entries.get(index.checked_sub(1)? as usize)
+    }
+
+    fn has_entry_lengths(&self) -> bool {
+        self.version() >= 1 && self.default_length.unwrap_or_default() == 0
+    }
+
+    fn inner_byte_size(&self) -> usize {
+        let lengths = if self.has_entry_lengths() { self.entries.len() * u32::BYTE_SIZE } else { 0 };
+        FullBoxData { version: self.version(), flags: 0u32 }.byte_size() +
+            self.grouping_type.byte_size() +
+            if self.version() >= 1 { u32::BYTE_SIZE } else { 0 } +
+            if self.version() >= 2 { u32::BYTE_SIZE } else { 0 } +
+            u32::BYTE_SIZE +
+            lengths +
+            self.entries.byte_size()
+    }
+
+    fn header(&self) -> BoxHeader {
+        BoxHeader::from_id_and_inner_size(Self::ID, self.inner_byte_size())
+    }
+}
+
+impl IBox for Sgpd {
+    fn byte_size(&self) -> usize {
+        self.header().byte_size() + self.inner_byte_size()
+    }
+
+    const ID: BoxType = BoxType::Id(BoxId(*b"sgpd"));
+}
+
+#[async_trait::async_trait]
+impl BoxRead for Sgpd {
+    async fn read<R: ReadMp4>(header: BoxHeader, reader: &mut R) -> Result<Self, MP4Error> {
+        let start = reader.seek(SeekFrom::Current(0)).await?;
+        let data: FullBoxData<u32> = reader.read().await?;
+        let grouping_type: [u8; 4] = reader.read().await?;
+        if data.version > 2 {
+            return Err(UnknownVersion(Self::ID, data.version).into());
+        }
+        let default_length = if data.version >= 1 { Some(reader.read::<u32>().await?) } else { None };
+        let default_sample_description_index = if data.version >= 2 { Some(reader.read::<u32>().await?) } else { None };
+        let entry_count: u32 = reader.read().await?;
+        let entry_length = match default_length {
+            Some(0) => None,
+            Some(length) => Some(length as usize),
+            None => match (SampleGroupEntry::fixed_size(grouping_type), header.size_minus_self()) {
+                (Some(size), _) => Some(size),
+                (None, _) if entry_count == 0 => Some(0),
+                (None, BoxSize::Known(size)) => {
+                    let read = (reader.seek(SeekFrom::Current(0)).await? - start) as usize;
+                    Some(size.saturating_sub(read) / entry_count as usize)
+                }
+                (None, BoxSize::Unknown) => return Err(MP4Error::Custom(format!(
+                    "Cannot find the size of the {} sample group entries", BoxId(grouping_type)
+                )))
+            }
+        };
+        let mut entries = vec![];
+        for _ in 0..entry_count {
+            let length = match entry_length {
+                Some(length) => length,
+                None => reader.read::<u32>().await? as usize
+            };
+            entries.push(SampleGroupEntry::read(grouping_type, length, reader).await?);
+        }
+        Ok(Self {
+            grouping_type,
+            default_length,
+            default_sample_description_index,
+            entries
+        })
+    }
+}
+
+impl BoxWrite for Sgpd {
+    fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let mut count = 0;
+        count += self.header().write(writer)?;
+        count += FullBoxData { version: self.version(), flags: 0u32 }.write(writer)?;
+        count += self.grouping_type.write(writer)?;
+        if self.version() >= 1 {
+            count += self.default_length.unwrap_or_default().write(writer)?;
+        }
+        if self.version() >= 2 {
+            count += self.default_sample_description_index.unwrap_or_default().write(writer)?;
+        }
+        count += (self.entries.len() as u32).write(writer)?;
+        let has_entry_lengths = self.has_entry_lengths();
+        for entry in &self.entries {
+            if has_entry_lengths {
+                count += (entry.byte_size() as u32).write(writer)?;
+            }
+            count += entry.write(writer)?;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bytes_read::Mp4Readable;
+    use crate::error::MP4Error;
+    use crate::header::BoxHeader;
+    use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+    use crate::mp4box::sgpd::{RAP, ROLL, SampleGroupEntry, Sgpd, SgpdBox};
+
+    #[test]
+    pub fn test_rebuild() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let roll = Sgpd::new(ROLL, vec![SampleGroupEntry::Roll { roll_distance: -2 }]);
+            assert_eq!(roll.default_length, Some(2));
+            let rap = Sgpd {
+                default_length: None,
+                ..Sgpd::new(RAP, vec![SampleGroupEntry::Rap { num_leading_samples_known: true, num_leading_samples: 3 }])
+            };
+            let unknown = Sgpd::new(*b"seig", vec![SampleGroupEntry::Unknown(vec![1, 2]), SampleGroupEntry::Unknown(vec![3])]);
+            assert_eq!(unknown.default_length, Some(0));
+            let unknown_v2 = Sgpd {
+                default_sample_description_index: Some(1),
+                ..Sgpd::new(*b"seig", vec![SampleGroupEntry::Unknown(vec![1, 2]), SampleGroupEntry::Unknown(vec![3, 4])])
+            };
+            let mixed_v2 = Sgpd {
+                default_sample_description_index: Some(2),
+                ..Sgpd::new(*b"seig", vec![SampleGroupEntry::Unknown(vec![1, 2]), SampleGroupEntry::Unknown(vec![3])])
+            };
+            for base in [roll, rap, unknown, unknown_v2, mixed_v2] {
+                let base: SgpdBox = base;
+                let mut buf = vec![];
+                let mut cursor = std::io::Cursor::new(&mut buf);
+                let pos = base.write(&mut cursor)?;
+                assert_eq!(pos, base.byte_size());
+                assert_eq!(pos as u64, cursor.position());
+                let mut cursor = futures::io::Cursor::new(&mut buf);
+                let header = BoxHeader::read(&mut cursor).await?;
+                assert_eq!(header.id, SgpdBox::ID);
+                let new = SgpdBox::read(header, &mut cursor).await?;
+                assert_eq!(base, new);
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_v2_layout() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let bytes = [
+                0, 0, 0, 30, b's', b'g', b'p', b'd', 2, 0, 0, 0, b'r', b'o', b'l', b'l',
+                0, 0, 0, 2, // default_length
+                0, 0, 0, 1, // default_sample_description_index
+                0, 0, 0, 1, // entry_count
+                0xff, 0xfe
+            ];
+            let mut cursor = futures::io::Cursor::new(&bytes[..]);
+            let header = BoxHeader::read(&mut cursor).await?;
+            let sgpd = SgpdBox::read(header, &mut cursor).await?;
+            assert_eq!(sgpd, Sgpd {
+                grouping_type: ROLL,
+                default_length: Some(2),
+                default_sample_description_index: Some(1),
+                entries: vec![SampleGroupEntry::Roll { roll_distance: -2 }]
+            });
+            let mut buf = vec![];
+            sgpd.write(&mut std::io::Cursor::new(&mut buf))?;
+            assert_eq!(buf, bytes);
+            Ok(())
+        })
+    }
+
+}
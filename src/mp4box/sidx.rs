@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use crate::bytes_read::{Mp4Readable, ReadMp4};
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::error::MP4Error;
+use crate::fragment::Fragment;
+use crate::full_box;
+use crate::mp4box::trex::Trex;
+use crate::types::array::Mp4Array;
+use crate::types::versioned_u32_u64::VersionedU32U64;
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SidxReference {
+    /// the reference points to another `sidx` instead of media
+    pub reference_type: bool,
+    pub referenced_size: u32,
+    pub subsegment_duration: u32,
+    pub starts_with_sap: bool,
+    pub sap_type: u8,
+    pub sap_delta_time: u32,
+}
+
+#[async_trait]
+impl Mp4Readable for SidxReference {
+    async fn read<R: ReadMp4>(reader: &mut R) -> Result<Self, MP4Error> {
+        let size: u32 = reader.read().await?;
+        let subsegment_duration = reader.read().await?;
+        let sap: u32 = reader.read().await?;
+        Ok(Self {
+            reference_type: size >> 31 == 1,
+            referenced_size: size & 0x7FFF_FFFF,
+            subsegment_duration,
+            starts_with_sap: sap >> 31 == 1,
+            sap_type: (sap >> 28) as u8 & 0b111,
+            sap_delta_time: sap & 0x0FFF_FFFF
+        })
+    }
+}
+
+impl Mp4Writable for SidxReference {
+    fn byte_size(&self) -> usize {
+        12
+    }
+
+    fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let mut count = 0;
+        count += ((self.reference_type as u32) << 31 | self.referenced_size & 0x7FFF_FFFF).write(writer)?;
+        count += self.subsegment_duration.write(writer)?;
+        count += ((self.starts_with_sap as u32) << 31 | (self.sap_type as u32 & 0b111) << 28 | self.sap_delta_time & 0x0FFF_FFFF).write(writer)?;
+        Ok(count)
+    }
+}
+
+full_box! {
+    box (b"sidx", Sidx, SidxBox, u32)
+    data {
+        reference_id: u32,
+        timescale: u32,
+        earliest_presentation_time: VersionedU32U64,
+        first_offset: VersionedU32U64,
+        _r1: u16,
+        references: Mp4Array<u16, SidxReference>,
+    }
+}
+
+impl Sidx {
+
+    /// Indexes fragments of a track that directly follow the `sidx`.
+    /// The earliest presentation time is the smallest decode time plus composition offset of the samples of the first fragment.
+    pub fn from_fragments<'a>(track_id: u32, timescale: u32, trex: Option<&Trex>, fragments: impl IntoIterator<Item=&'a Fragment>) -> Self {
+        let mut earliest_presentation_time = None;
+        let references = fragments.into_iter().map(|fragment| {
+            let traf = fragment.moof.traf(track_id);
+            if earliest_presentation_time.is_none() {
+                earliest_presentation_time = traf.and_then(|traf| traf.samples(trex, 0).iter()
+                    .filter_map(|it| Some(it.decode_time?.saturating_add_signed(it.composition_offset)))
+                    .min());
+            }
+            let starts_with_sap = traf.and_then(|it| it.first_sample_flags(trex))
+                .map(|flags| flags.is_sync())
+                .unwrap_or(true);
+            SidxReference {
+                reference_type: false,
+                referenced_size: fragment.byte_size() as u32,
+                subsegment_duration: traf.map(|it| it.duration(trex)).unwrap_or_default() as u32,
+                starts_with_sap,
+                sap_type: if starts_with_sap { 1 } else { 0 },
+                sap_delta_time: 0
+            }
+        }).collect::<Vec<_>>();
+        Self {
+            reference_id: track_id,
+            timescale,
+            earliest_presentation_time: earliest_presentation_time.unwrap_or_default().into(),
+            first_offset: 0u32.into(),
+            _r1: 0,
+            references: references.into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bytes_read::Mp4Readable;
+    use crate::error::MP4Error;
+    use crate::header::BoxHeader;
+    use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+    use crate::fragment::Fragment;
+    use crate::mp4box::mdat::MdatBox;
+    use crate::mp4box::moof::Moof;
+    use crate::mp4box::sidx::{Sidx, SidxBox, SidxReference};
+    use crate::mp4box::tfdt::Tfdt;
+    use crate::mp4box::tfhd::Tfhd;
+    use crate::mp4box::traf::Traf;
+    use crate::mp4box::trun::{Trun, TrunEntry};
+    use crate::types::array::Mp4VersionedOffsetArray;
+    use crate::types::versioned_signed_int::VersionedSignedU32;
+
+    #[test]
+    pub fn test_rebuild() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let base: SidxBox = Sidx {
+                reference_id: 1,
+                timescale: 90000,
+                earliest_presentation_time: (u32::MAX as u64 + 10).into(),
+                first_offset: 0u32.into(),
+                _r1: 0,
+                references: vec![SidxReference {
+                    reference_type: false,
+                    referenced_size: 123456,
+                    subsegment_duration: 180000,
+                    starts_with_sap: true,
+                    sap_type: 1,
+                    sap_delta_time: 0
+                }].into()
+            }.into();
+            let mut buf = vec![];
+            let mut cursor = std::io::Cursor::new(&mut buf);
+            let pos = base.write(&mut cursor)?;
+            assert_eq!(pos, base.byte_size());
+            assert_eq!(pos as u64, cursor.position());
+            let mut cursor = futures::io::Cursor::new(&mut buf);
+            let header = BoxHeader::read(&mut cursor).await?;
+            assert_eq!(header.id, SidxBox::ID);
+            let new = SidxBox::read(header, &mut cursor).await?;
+            assert_eq!(base, new);
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_earliest_presentation_time() {
+        // I P B: presented at 2000, 4000 and 3000
+        let fragment = Fragment::new(Moof {
+            mfhd: None,
+            trafs: vec![Traf {
+                tfhd: Some(Tfhd {
+                    track_id: 1,
+                    base_data_offset: Default::default(),
+                    sample_description_index: Default::default(),
+                    default_sample_duration: 1000.into(),
+                    default_sample_size: Default::default(),
+                    default_sample_flags: Default::default(),
+                    flags: Default::default()
+                }.into()),
+                tfdt: Some(Tfdt { base_media_decode_time: 1000u32.into() }.into()),
+                sdtp: None,
+                sbgps: vec![],
+                sgpds: vec![],
+                truns: vec![Trun {
+                    entries: Mp4VersionedOffsetArray::new([1000, 2000, 0].map(|offset| TrunEntry {
+                        sample_composition_time_offset: VersionedSignedU32::from(offset as u32).into(),
+                        ..Default::default()
+                    }).to_vec(), Default::default())
+                }.into()]
+            }.into()]
+        }.into(), MdatBox(vec![]));
+        let sidx = Sidx::from_fragments(1, 1000, None, [&fragment]);
+        assert_eq!(*sidx.earliest_presentation_time, 2000);
+        assert_eq!(sidx.references.0[0].subsegment_duration, 3000);
+    }
+
+}
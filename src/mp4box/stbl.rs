@@ -1,10 +1,16 @@
 use crate::base_box;
 use crate::mp4box::co64::Co64Box;
+use crate::mp4box::ctts::CttsBox;
+use crate::mp4box::sbgp::SbgpBox;
+use crate::mp4box::sdtp::SdtpBox;
+use crate::mp4box::sgpd::{SampleGroupEntry, SgpdBox};
 use crate::mp4box::stco::StcoBox;
 use crate::mp4box::stsc::StscBox;
 use crate::mp4box::stsd::StsdBox;
 use crate::mp4box::stts::SttsBox;
-use crate::mp4box::stsz::StszBox;
+use crate::mp4box::stss::StssBox;
+use crate::mp4box::stsz::{Stsz, StszBox};
+use crate::mp4box::stz2::{Stz2, Stz2Box};
 
 base_box! {
     box (b"stbl", Stbl, StblBox) children {
@@ -12,7 +18,100 @@ base_box! {
         stco: StcoBox,
         stsc: StscBox,
         stsd: StsdBox,
+        stss: StssBox,
         stsz: StszBox,
+        stz2: Stz2Box,
         stts: SttsBox,
+        ctts: CttsBox,
+        sdtp: SdtpBox,
+        sbgps: vec SbgpBox,
+        sgpds: vec SgpdBox,
     }
 }
+
+impl Stbl {
+    /// Number of samples, from the `stsz` or the `stz2`.
+    pub fn sample_count(&self) -> Option<u32> {
+        self.stsz.as_ref().map(|it| it.sample_count())
+            .or(self.stz2.as_ref().map(|it| it.sample_count()))
+    }
+
+    /// Size of a 0-based sample, from the `stsz` or the `stz2`.
+    pub fn sample_size(&self, sample: u32) -> Option<u32> {
+        match (&self.stsz, &self.stz2) {
+            (Some(stsz), _) => stsz.sample_size(sample),
+            (None, Some(stz2)) => stz2.sample_size(sample),
+            (None, None) => None
+        }
+    }
+
+    /// Stores the sample sizes in the smallest box: a `stsz` with a common size, a `stz2` if allowed, or a `stsz` with every size.
+    pub fn set_sample_sizes(&mut self, sample_sizes: &[u32], allow_stz2: bool) {
+        let first = sample_sizes.first().copied().unwrap_or_default();
+        self.stz2 = None;
+        self.stsz = None;
+        if first != 0 && sample_sizes.iter().all(|it| *it == first) {
+            self.stsz = Some(Stsz::Simple { sample_size: first, sample_count: sample_sizes.len() as u32 }.into());
+        } else if let Some(stz2) = Stz2::new(sample_sizes).filter(|_| allow_stz2) {
+            self.stz2 = Some(stz2.into());
+        } else {
+            self.stsz = Some(Stsz::Advanced { sample_sizes: sample_sizes.to_vec().into() }.into());
+        }
+    }
+
+    pub fn sbgp(&self, grouping_type: [u8; 4]) -> Option<&SbgpBox> {
+        self.sbgps.iter().find(|it| it.grouping_type == grouping_type)
+    }
+
+    pub fn sgpd(&self, grouping_type: [u8; 4]) -> Option<&SgpdBox> {
+        self.sgpds.iter().find(|it| it.grouping_type == grouping_type)
+    }
+
+    /// The description of the group of a 0-based sample, `None` when the sample is not part of any group of that type.
+    pub fn sample_group_entry(&self, grouping_type: [u8; 4], sample: u32) -> Option<&SampleGroupEntry> {
+        let index = self.sbgp(grouping_type)?.group_description_index(sample);
+        self.sgpd(grouping_type)?.entry(index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mp4box::box_trait::IBox;
+    use crate::mp4box::stbl::Stbl;
+
+    #[test]
+    pub fn test_sample_sizes() {
+        let mut stbl = Stbl {
+            co64: None,
+            stco: None,
+            stsc: None,
+            stsd: None,
+            stss: None,
+            stsz: None,
+            stz2: None,
+            stts: None,
+            ctts: None,
+            sdtp: None,
+            sbgps: vec![],
+            sgpds: vec![]
+        };
+        stbl.set_sample_sizes(&[100, 100, 100], true);
+        assert!(stbl.stz2.is_none());
+        assert_eq!(stbl.sample_count(), Some(3));
+        assert_eq!(stbl.sample_size(2), Some(100));
+        assert_eq!(stbl.sample_size(3), None);
+
+        let sizes = [100, 2000, 300, 40];
+        stbl.set_sample_sizes(&sizes, false);
+        let stsz_size = stbl.stsz.byte_size();
+        stbl.set_sample_sizes(&sizes, true);
+        assert!(stbl.stsz.is_none());
+        assert!(stbl.stz2.byte_size() < stsz_size);
+        assert_eq!((0..4).filter_map(|it| stbl.sample_size(it)).collect::<Vec<_>>(), sizes);
+
+        stbl.set_sample_sizes(&[70000, 3], true);
+        assert!(stbl.stz2.is_none());
+        assert_eq!(stbl.sample_size(0), Some(70000));
+    }
+
+}
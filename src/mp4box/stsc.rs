@@ -3,6 +3,7 @@ use crate::types::array::Mp4Array;
 
 mp4_data! {
     #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct StscEntry {
         pub first_chunk: u32,
         pub samples_per_chunk: u32,
@@ -6,13 +6,19 @@ use crate::bytes_read::{Mp4Readable, ReadMp4};
 use crate::bytes_write::{Mp4Writable, WriteMp4};
 use crate::error::MP4Error;
 use crate::header::BoxHeader;
+use crate::codec::Codec;
 use crate::mp4box::avc1::{Avc1Box};
 use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+use crate::mp4box::hvc1::{Hev1Box, Hvc1Box};
 use crate::mp4box::opus::OpusBox;
+use crate::types::sample::{AudioSampleEntry, VisualSampleEntry};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StsdSampleEntry {
     Avc1(Avc1Box),
+    Hvc1(Hvc1Box),
+    Hev1(Hev1Box),
     Opus(OpusBox),
     Unknown(UnknownBox),
 }
@@ -23,6 +29,8 @@ impl Mp4Readable for StsdSampleEntry {
         let header: BoxHeader = reader.read().await?;
         Ok(match header.id {
             Avc1Box::ID => Self::Avc1(<Avc1Box as BoxRead>::read(header, reader).await?),
+            Hvc1Box::ID => Self::Hvc1(<Hvc1Box as BoxRead>::read(header, reader).await?),
+            Hev1Box::ID => Self::Hev1(<Hev1Box as BoxRead>::read(header, reader).await?),
             OpusBox::ID => Self::Opus(<OpusBox as BoxRead>::read(header, reader).await?),
             _ => Self::Unknown(<UnknownBox as BoxRead>::read(header, reader).await?)
         })
@@ -33,6 +41,8 @@ impl Mp4Writable for StsdSampleEntry {
     fn byte_size(&self) -> usize {
         match self {
             StsdSampleEntry::Avc1(it) => it.byte_size(),
+            StsdSampleEntry::Hvc1(it) => it.byte_size(),
+            StsdSampleEntry::Hev1(it) => it.byte_size(),
             StsdSampleEntry::Opus(it) => it.byte_size(),
             StsdSampleEntry::Unknown(it) => it.byte_size()
         }
@@ -41,12 +51,48 @@ impl Mp4Writable for StsdSampleEntry {
     fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
         match self {
             StsdSampleEntry::Avc1(it) => it.write(writer),
+            StsdSampleEntry::Hvc1(it) => it.write(writer),
+            StsdSampleEntry::Hev1(it) => it.write(writer),
             StsdSampleEntry::Opus(it) => it.write(writer),
             StsdSampleEntry::Unknown(it) => it.write(writer)
         }
     }
 }
 
+impl StsdSampleEntry {
+    pub fn visual_sample_entry(&self) -> Option<&VisualSampleEntry> {
+        match self {
+            StsdSampleEntry::Avc1(it) => Some(&it.visual_sample_entry),
+            StsdSampleEntry::Hvc1(it) => Some(&it.visual_sample_entry),
+            StsdSampleEntry::Hev1(it) => Some(&it.visual_sample_entry),
+            StsdSampleEntry::Opus(_) | StsdSampleEntry::Unknown(_) => None
+        }
+    }
+
+    pub fn audio_sample_entry(&self) -> Option<&AudioSampleEntry> {
+        match self {
+            StsdSampleEntry::Opus(it) => Some(&it.audio),
+            _ => None
+        }
+    }
+
+    /// `None` for unknown sample entries or if the decoder configuration is missing.
+    pub fn codec(&self) -> Option<Codec> {
+        match self {
+            StsdSampleEntry::Avc1(it) => it.codec(),
+            StsdSampleEntry::Hvc1(it) => it.codec(),
+            StsdSampleEntry::Hev1(it) => it.codec(),
+            StsdSampleEntry::Opus(it) => Some(it.codec()),
+            StsdSampleEntry::Unknown(_) => None
+        }
+    }
+
+    /// The RFC 6381 codec string, as used in HLS and DASH manifests.
+    pub fn codec_string(&self) -> Option<String> {
+        self.codec().map(|it| it.to_string())
+    }
+}
+
 full_box! {
     box (b"stsd", Stsd, StsdBox, u32) data {
         entries: Mp4Array<u32, StsdSampleEntry>
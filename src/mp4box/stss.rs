@@ -0,0 +1,27 @@
+use crate::full_box;
+use crate::types::array::Mp4Array;
+
+full_box! {
+    box (b"stss", Stss, StssBox, u32) data {
+        sample_numbers: Mp4Array<u32, u32>
+    }
+}
+
+/// The sample numbers of `stss` are 1-based and increasing, the samples given to these methods are 0-based.
+impl Stss {
+    pub fn is_sync(&self, sample: u32) -> bool {
+        self.sample_numbers.0.binary_search(&sample.saturating_add(1)).is_ok()
+    }
+
+    /// The last sync sample at or before `sample`.
+    pub fn sync_sample_before(&self, sample: u32) -> Option<u32> {
+        let index = self.sample_numbers.0.partition_point(|it| *it <= sample.saturating_add(1));
+        Some(self.sample_numbers.0[index.checked_sub(1)?].saturating_sub(1))
+    }
+
+    /// The first sync sample at or after `sample`.
+    pub fn sync_sample_after(&self, sample: u32) -> Option<u32> {
+        let index = self.sample_numbers.0.partition_point(|it| *it < sample.saturating_add(1));
+        self.sample_numbers.0.get(index).map(|it| it.saturating_sub(1))
+    }
+}
@@ -13,6 +13,7 @@ use crate::types::array::Mp4Array;
 pub type StszBox = MP4Box<FullBox<Stsz, u32>>;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Stsz {
     Simple {
         sample_size: u32,
@@ -83,6 +84,22 @@ impl PartialBoxWrite for Stsz {
     }
 }
 
+impl Stsz {
+    pub fn sample_count(&self) -> u32 {
+        match self {
+            Stsz::Simple { sample_count, .. } => *sample_count,
+            Stsz::Advanced { sample_sizes } => sample_sizes.0.len() as u32
+        }
+    }
+
+    pub fn sample_size(&self, sample: u32) -> Option<u32> {
+        match self {
+            Stsz::Simple { sample_size, sample_count } => (sample < *sample_count).then_some(*sample_size),
+            Stsz::Advanced { sample_sizes } => sample_sizes.0.get(sample as usize).copied()
+        }
+    }
+}
+
 impl Default for Stsz {
     fn default() -> Self {
         Self::Simple {
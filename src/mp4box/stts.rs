@@ -3,6 +3,7 @@ use crate::types::array::Mp4Array;
 
 mp4_data! {
     #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct SttsEntry {
         pub sample_count: u32,
         pub sample_delta: u32
@@ -0,0 +1,154 @@
+use crate::bytes_read::ReadMp4;
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::error::MP4Error;
+use crate::id::BoxId;
+use crate::mp4box::box_full::{FullBox, FullBoxData, FullBoxInfo};
+use crate::mp4box::box_root::MP4Box;
+use crate::mp4box::box_trait::{PartialBox, PartialBoxRead, PartialBoxWrite};
+use crate::r#type::BoxType;
+
+pub type Stz2Box = MP4Box<FullBox<Stz2, u32>>;
+
+/// Compact sample size box (ISO 14496-12 § 8.7.3.3), the sample sizes packed on 4, 8 or 16 bits.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stz2 {
+    /// 4, 8 or 16
+    pub field_size: u8,
+    pub sample_sizes: Vec<u16>,
+}
+
+impl Stz2 {
+
+    /// Packs the sample sizes on the smallest field size that fits them, `None` if one does not fit 16 bits.
+    pub fn new(sample_sizes: &[u32]) -> Option<Self> {
+        let max = sample_sizes.iter().copied().max().unwrap_or_default();
+        let field_size = match max {
+            0..=0xF => 4,
+            0x10..=0xFF => 8,
+            0x100..=0xFFFF => 16,
+            _ => return None
+        };
+        Some(Self {
+            field_size,
+            sample_sizes: sample_sizes.iter().map(|it| *it as u16).collect()
+        })
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_sizes.len() as u32
+    }
+
+    pub fn sample_size(&self, sample: u32) -> Option<u32> {
+        self.sample_sizes.get(sample as usize).map(|it| *it as u32)
+    }
+
+    fn entries_byte_size(&self) -> usize {
+        (self.sample_sizes.len() * self.field_size as usize).div_ceil(8)
+    }
+}
+
+impl FullBoxInfo for Stz2 {
+    type Flag = u32;
+}
+
+impl PartialBox for Stz2 {
+    type ParentData = FullBoxData<u32>;
+    type ThisData = ();
+
+    fn byte_size(&self) -> usize {
+        3 + self.field_size.byte_size() + self.sample_count().byte_size() + self.entries_byte_size()
+    }
+
+    const ID: BoxType = BoxType::Id(BoxId(*b"stz2"));
+}
+
+#[async_trait::async_trait]
+impl PartialBoxRead for Stz2 {
+    async fn read_data<R: ReadMp4>(_: Self::ParentData, reader: &mut R) -> Result<Self, MP4Error> {
+        let _reserved = reader.read_u24().await?;
+        let field_size: u8 = reader.read().await?;
+        let sample_count: u32 = reader.read().await?;
+        let mut sample_sizes = Vec::with_capacity(sample_count as usize);
+        match field_size {
+            4 => {
+                for i in 0..sample_count.div_ceil(2) {
+                    let byte: u8 = reader.read().await?;
+                    sample_sizes.push((byte >> 4) as u16);
+                    if i * 2 + 1 < sample_count {
+                        sample_sizes.push((byte & 0xF) as u16);
+                    }
+                }
+            }
+            8 => for _ in 0..sample_count {
+                sample_sizes.push(reader.read::<u8>().await? as u16);
+            }
+            16 => for _ in 0..sample_count {
+                sample_sizes.push(reader.read().await?);
+            }
+            _ => return Err(MP4Error::Custom(format!("Invalid stz2 field size: {}", field_size)))
+        }
+        Ok(Self { field_size, sample_sizes })
+    }
+}
+
+impl PartialBoxWrite for Stz2 {
+    fn write_data<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let max = 1u32 << self.field_size.min(16);
+        if self.sample_sizes.iter().any(|it| *it as u32 >= max) {
+            return Err(MP4Error::Custom(format!("A sample size does not fit the stz2 field size of {} bits", self.field_size)));
+        }
+        let mut count = 0;
+        count += writer.write_u24(0)?;
+        count += self.field_size.write(writer)?;
+        count += self.sample_count().write(writer)?;
+        match self.field_size {
+            4 => for pair in self.sample_sizes.chunks(2) {
+                let low = pair.get(1).copied().unwrap_or_default();
+                count += ((pair[0] << 4 | low) as u8).write(writer)?;
+            }
+            8 => for size in &self.sample_sizes {
+                count += (*size as u8).write(writer)?;
+            }
+            16 => for size in &self.sample_sizes {
+                count += size.write(writer)?;
+            }
+            _ => return Err(MP4Error::Custom(format!("Invalid stz2 field size: {}", self.field_size)))
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bytes_read::Mp4Readable;
+    use crate::error::MP4Error;
+    use crate::header::BoxHeader;
+    use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox, PartialBox};
+    use crate::mp4box::stz2::{Stz2, Stz2Box};
+
+    #[test]
+    pub fn test_rebuild() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            for (sizes, field_size) in [(vec![1, 15, 7], 4), (vec![3, 200], 8), (vec![1000, 3, 65535], 16)] {
+                let stz2 = Stz2::new(&sizes).unwrap();
+                assert_eq!(stz2.field_size, field_size);
+                let base: Stz2Box = stz2.into();
+                let mut buf = vec![];
+                let mut cursor = std::io::Cursor::new(&mut buf);
+                let pos = base.write(&mut cursor)?;
+                assert_eq!(pos, base.byte_size());
+                assert_eq!(pos as u64, cursor.position());
+                let mut cursor = futures::io::Cursor::new(&mut buf);
+                let header = BoxHeader::read(&mut cursor).await?;
+                assert_eq!(header.id, Stz2::ID);
+                let new = Stz2Box::read(header, &mut cursor).await?;
+                assert_eq!(base, new);
+                assert_eq!((0..new.sample_count()).filter_map(|it| new.sample_size(it)).collect::<Vec<_>>(), sizes);
+            }
+            assert_eq!(Stz2::new(&[65536]), None);
+            Ok(())
+        })
+    }
+
+}
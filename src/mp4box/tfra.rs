@@ -0,0 +1,203 @@
+use async_trait::async_trait;
+use crate::bytes_read::ReadMp4;
+use crate::bytes_reserve::Mp4Reservable;
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::error::MalformedBoxError::UnknownVersion;
+use crate::error::MP4Error;
+use crate::header::BoxHeader;
+use crate::id::BoxId;
+use crate::mp4box::box_full::FullBoxData;
+use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+use crate::r#type::BoxType;
+
+pub type TfraBox = Tfra;
+
+/// A random access point of a track, the traf, trun and sample numbers are 1-based.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TfraEntry {
+    /// presentation time of the sample, in the track timescale
+    pub time: u64,
+    /// position of the `moof` in the file
+    pub moof_offset: u64,
+    pub traf_number: u32,
+    pub trun_number: u32,
+    pub sample_number: u32,
+}
+
+/// Track fragment random access box (ISO 14496-12 § 8.8.10), lists the sync samples of a track in a fragmented file.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tfra {
+    pub track_id: u32,
+    pub entries: Vec<TfraEntry>,
+}
+
+/// Number of bytes needed to store `value`, from 1 to 4.
+fn length_size(value: u32) -> usize {
+    match value {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x1_0000..=0xFF_FFFF => 3,
+        _ => 4
+    }
+}
+
+async fn read_number<R: ReadMp4>(size: usize, reader: &mut R) -> Result<u32, MP4Error> {
+    let mut value = 0u32;
+    for _ in 0..size {
+        value = value << 8 | reader.read::<u8>().await? as u32;
+    }
+    Ok(value)
+}
+
+fn write_number<W: WriteMp4>(value: u32, size: usize, writer: &mut W) -> Result<usize, MP4Error> {
+    let mut count = 0;
+    for byte in &value.to_be_bytes()[4 - size..] {
+        count += byte.write(writer)?;
+    }
+    Ok(count)
+}
+
+impl Tfra {
+
+    pub fn version(&self) -> u8 {
+        self.entries.iter().any(|it| it.time > u32::MAX as u64 || it.moof_offset > u32::MAX as u64) as u8
+    }
+
+    /// Byte sizes of the traf, trun and sample numbers.
+    fn length_sizes(&self) -> (usize, usize, usize) {
+        self.entries.iter().fold((1, 1, 1), |(traf, trun, sample), it| (
+            traf.max(length_size(it.traf_number)),
+            trun.max(length_size(it.trun_number)),
+            sample.max(length_size(it.sample_number))
+        ))
+    }
+
+    /// The last entry at or before `time`.
+    pub fn entry_before(&self, time: u64) -> Option<&TfraEntry> {
+        self.entries.iter().rev().find(|it| it.time <= time)
+    }
+
+    fn inner_byte_size(&self) -> usize {
+        let (traf, trun, sample) = self.length_sizes();
+        let time_size = match self.version() {
+            0 => u32::BYTE_SIZE * 2,
+            _ => u64::BYTE_SIZE * 2
+        };
+        FullBoxData { version: self.version(), flags: 0u32 }.byte_size() +
+            self.track_id.byte_size() +
+            u32::BYTE_SIZE +
+            u32::BYTE_SIZE +
+            self.entries.len() * (time_size + traf + trun + sample)
+    }
+
+    fn header(&self) -> BoxHeader {
+        BoxHeader::from_id_and_inner_size(Self::ID, self.inner_byte_size())
+    }
+}
+
+impl IBox for Tfra {
+    fn byte_size(&self) -> usize {
+        self.header().byte_size() + self.inner_byte_size()
+    }
+
+    const ID: BoxType = BoxType::Id(BoxId(*b"tfra"));
+}
+
+#[async_trait]
+impl BoxRead for Tfra {
+    async fn read<R: ReadMp4>(_: BoxHeader, reader: &mut R) -> Result<Self, MP4Error> {
+        let data: FullBoxData<u32> = reader.read().await?;
+        if data.version > 1 {
+            return Err(UnknownVersion(Self::ID, data.version).into());
+        }
+        let track_id = reader.read().await?;
+        let sizes: u32 = reader.read().await?;
+        let traf_size = (sizes >> 4 & 0b11) as usize + 1;
+        let trun_size = (sizes >> 2 & 0b11) as usize + 1;
+        let sample_size = (sizes & 0b11) as usize + 1;
+        let entry_count: u32 = reader.read().await?;
+        let mut entries = vec![];
+        for _ in 0..entry_count {
+            let (time, moof_offset) = match data.version {
+                0 => (reader.read::<u32>().await? as u64, reader.read::<u32>().await? as u64),
+                _ => (reader.read().await?, reader.read().await?)
+            };
+            entries.push(TfraEntry {
+                time,
+                moof_offset,
+                traf_number: read_number(traf_size, reader).await?,
+                trun_number: read_number(trun_size, reader).await?,
+                sample_number: read_number(sample_size, reader).await?
+            });
+        }
+        Ok(Self { track_id, entries })
+    }
+}
+
+impl BoxWrite for Tfra {
+    fn write<W: WriteMp4>(&self, writer: &mut W) -> Result<usize, MP4Error> {
+        let version = self.version();
+        let (traf, trun, sample) = self.length_sizes();
+        let mut count = 0;
+        count += self.header().write(writer)?;
+        count += FullBoxData { version, flags: 0u32 }.write(writer)?;
+        count += self.track_id.write(writer)?;
+        count += ((traf as u32 - 1) << 4 | (trun as u32 - 1) << 2 | (sample as u32 - 1)).write(writer)?;
+        count += (self.entries.len() as u32).write(writer)?;
+        for entry in &self.entries {
+            match version {
+                0 => {
+                    count += (entry.time as u32).write(writer)?;
+                    count += (entry.moof_offset as u32).write(writer)?;
+                }
+                _ => {
+                    count += entry.time.write(writer)?;
+                    count += entry.moof_offset.write(writer)?;
+                }
+            }
+            count += write_number(entry.traf_number, traf, writer)?;
+            count += write_number(entry.trun_number, trun, writer)?;
+            count += write_number(entry.sample_number, sample, writer)?;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bytes_read::Mp4Readable;
+    use crate::error::MP4Error;
+    use crate::header::BoxHeader;
+    use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+    use crate::mp4box::tfra::{Tfra, TfraBox, TfraEntry};
+
+    #[test]
+    pub fn test_rebuild() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            for moof_offset in [4000, u32::MAX as u64 + 1] {
+                let base: TfraBox = Tfra {
+                    track_id: 1,
+                    entries: vec![
+                        TfraEntry { time: 0, moof_offset: 1000, traf_number: 1, trun_number: 1, sample_number: 1 },
+                        TfraEntry { time: 90000, moof_offset, traf_number: 1, trun_number: 2, sample_number: 300 },
+                    ]
+                };
+                let mut buf = vec![];
+                let mut cursor = std::io::Cursor::new(&mut buf);
+                let pos = base.write(&mut cursor)?;
+                assert_eq!(pos, base.byte_size());
+                assert_eq!(pos as u64, cursor.position());
+                let mut cursor = futures::io::Cursor::new(&mut buf);
+                let header = BoxHeader::read(&mut cursor).await?;
+                assert_eq!(header.id, TfraBox::ID);
+                let new = TfraBox::read(header, &mut cursor).await?;
+                assert_eq!(base, new);
+                assert_eq!(new.entry_before(89999).map(|it| it.moof_offset), Some(1000));
+            }
+            Ok(())
+        })
+    }
+
+}
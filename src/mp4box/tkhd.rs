@@ -24,6 +24,20 @@ impl Hash for TrakFlags {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for TrakFlags {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for TrakFlags {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self(u32::deserialize(deserializer)?))
+    }
+}
+
 
 full_box! {
     box (b"tkhd", Tkhd, TkhdBox, @save flags: TrakFlags)
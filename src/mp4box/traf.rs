@@ -1,12 +1,349 @@
 use crate::base_box;
+use crate::mp4box::sbgp::SbgpBox;
+use crate::mp4box::sdtp::{SdtpBox, SdtpEntry};
+use crate::mp4box::sgpd::SgpdBox;
 use crate::mp4box::tfdt::TfdtBox;
 use crate::mp4box::tfhd::TfhdBox;
+use crate::mp4box::trex::{SampleFlags, Trex};
 use crate::mp4box::trun::TrunBox;
+use crate::types::versioned_signed_int::VersionedSignedU32;
+
+/// A sample of a track fragment with the `trun`, `tfhd` and `trex` defaulting applied.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResolvedSample {
+    pub duration: u32,
+    pub size: u32,
+    pub flags: SampleFlags,
+    pub composition_offset: i64,
+    /// position of the sample data, relative to what the `moof` offset is
+    pub offset: u64,
+    /// `None` when the fragment has no `tfdt`
+    pub decode_time: Option<u64>,
+}
+
+impl ResolvedSample {
+    pub fn presentation_time(&self) -> Option<u64> {
+        self.decode_time?.checked_add_signed(self.composition_offset)
+    }
+}
 
 base_box! {
     box (b"traf", Traf, TrafBox) children {
         tfhd: TfhdBox,
         tfdt: TfdtBox,
-        truns: vec TrunBox
+        truns: vec TrunBox,
+        sdtp: SdtpBox,
+        sbgps: vec SbgpBox,
+        sgpds: vec SgpdBox,
+    }
+}
+
+impl Traf {
+    pub fn sbgp(&self, grouping_type: [u8; 4]) -> Option<&SbgpBox> {
+        self.sbgps.iter().find(|it| it.grouping_type == grouping_type)
+    }
+
+    pub fn sgpd(&self, grouping_type: [u8; 4]) -> Option<&SgpdBox> {
+        self.sgpds.iter().find(|it| it.grouping_type == grouping_type)
+    }
+
+    pub fn track_id(&self) -> Option<u32> {
+        self.tfhd.as_ref().map(|it| it.track_id)
     }
+
+    pub fn sample_count(&self) -> usize {
+        self.truns.iter().map(|trun| trun.entries.data.len()).sum()
+    }
+
+    /// Sum of the sample durations, falling back on the `tfhd` then `trex` defaults.
+    pub fn duration(&self, trex: Option<&Trex>) -> u64 {
+        self.samples(trex, 0).iter().map(|it| it.duration as u64).sum()
+    }
+
+    /// Flags of the first sample, falling back on the `tfhd` then `trex` defaults.
+    pub fn first_sample_flags(&self, trex: Option<&Trex>) -> Option<SampleFlags> {
+        self.samples(trex, 0).first().map(|it| it.flags)
+    }
+
+    /// Rewrites the `sdtp` from the resolved sample flags so that both agree, if the fragment has one.
+    pub fn update_sdtp(&mut self, trex: Option<&Trex>) {
+        if self.sdtp.is_some() {
+            let entries = self.samples(trex, 0).into_iter().map(|it| SdtpEntry::from(it.flags)).collect();
+            self.sdtp = Some(SdtpBox { entries });
+        }
+    }
+
+    /// Resolves every sample of the fragment from the `trun` entries, the `tfhd` defaults then the `trex` defaults.
+    /// `moof_offset` is the position of the enclosing `moof`, it is the base of the data offsets unless the `tfhd` has a `base_data_offset`.
+    /// This is only right for the first `traf` of a `moof` or with `DEFAULT_BASE_IS_MOOF`, use [`Moof::samples`](crate::mp4box::moof::Moof::samples) otherwise.
+    pub fn samples(&self, trex: Option<&Trex>, moof_offset: u64) -> Vec<ResolvedSample> {
+        self.samples_from(trex, moof_offset, moof_offset)
+    }
+
+    /// `implicit_base` is where the data of the previous `traf` ended.
+    pub(crate) fn samples_from(&self, trex: Option<&Trex>, moof_offset: u64, implicit_base: u64) -> Vec<ResolvedSample> {
+        let tfhd = self.tfhd.as_ref();
+        let base = tfhd.and_then(|it| *it.base_data_offset).unwrap_or_else(|| {
+            if tfhd.is_some_and(|it| it.flags.default_base_is_moof()) { moof_offset } else { implicit_base }
+        });
+        let default_duration = tfhd.and_then(|it| *it.default_sample_duration)
+            .or(trex.map(|it| it.default_sample_duration))
+            .unwrap_or_default();
+        let default_size = tfhd.and_then(|it| *it.default_sample_size)
+            .or(trex.map(|it| it.default_sample_size))
+            .unwrap_or_default();
+        let default_flags = tfhd.and_then(|it| *it.default_sample_flags)
+            .or(trex.map(|it| it.default_sample_flags))
+            .unwrap_or_default();
+        let mut decode_time = self.tfdt.as_ref().map(|it| *it.base_media_decode_time);
+        let mut offset = base;
+        let mut samples = Vec::with_capacity(self.sample_count());
+        for trun in &self.truns {
+            if let Some(data_offset) = *trun.entries.offset.data_offset {
+                offset = base.saturating_add_signed(data_offset as i64);
+            }
+            for (i, entry) in trun.entries.data.iter().enumerate() {
+                let sample = ResolvedSample {
+                    duration: entry.sample_duration.unwrap_or(default_duration),
+                    size: entry.sample_size.unwrap_or(default_size),
+                    flags: trun.entries.offset.first_sample_flags.filter(|_| i == 0)
+                        .or(entry.sample_flags.0)
+                        .unwrap_or(default_flags),
+                    composition_offset: match entry.sample_composition_time_offset.unwrap_or_default() {
+                        VersionedSignedU32::Unsigned(offset) => offset as i64,
+                        VersionedSignedU32::Signed(offset) => offset as i64
+                    },
+                    offset,
+                    decode_time
+                };
+                offset += sample.size as u64;
+                decode_time = decode_time.map(|it| it + sample.duration as u64);
+                samples.push(sample);
+            }
+        }
+        samples
+    }
+
+    /// Hoists the values shared by the samples into the `tfhd` defaults, or relies on the `trex` defaults when they match,
+    /// uses `first_sample_flags` when only the first sample of a run differs and strips the redundant per-sample fields.
+    /// The resolved value of every sample is preserved, fields that can not be resolved are left untouched.
+    pub fn compact(&mut self, trex: Option<&Trex>) {
+        let tfhd = match &mut self.tfhd {
+            Some(tfhd) => tfhd,
+            None => return
+        };
+
+        let durations = self.truns.iter().map(|trun| trun.entries.data.iter()
+            .map(|entry| entry.sample_duration.or(*tfhd.default_sample_duration).or(trex.map(|it| it.default_sample_duration)))
+            .collect::<Option<Vec<_>>>()
+        ).collect::<Option<Vec<_>>>();
+        if let Some(durations) = durations {
+            let inherited = trex.map(|it| it.default_sample_duration);
+            let default = best_default(&durations, inherited, false);
+            tfhd.default_sample_duration = default.filter(|_| default != inherited).into();
+            tfhd.flags.unset_has_default_sample_duration();
+            for (trun, durations) in self.truns.iter_mut().zip(durations) {
+                let uniform = default.is_some() && durations.iter().all(|it| Some(*it) == default);
+                for (entry, duration) in trun.entries.data.iter_mut().zip(durations) {
+                    entry.sample_duration = Some(duration).filter(|_| !uniform).into();
+                }
+            }
+        }
+
+        let sizes = self.truns.iter().map(|trun| trun.entries.data.iter()
+            .map(|entry| entry.sample_size.or(*tfhd.default_sample_size).or(trex.map(|it| it.default_sample_size)))
+            .collect::<Option<Vec<_>>>()
+        ).collect::<Option<Vec<_>>>();
+        if let Some(sizes) = sizes {
+            let inherited = trex.map(|it| it.default_sample_size);
+            let default = best_default(&sizes, inherited, false);
+            tfhd.default_sample_size = default.filter(|_| default != inherited).into();
+            tfhd.flags.unset_has_default_sample_size();
+            for (trun, sizes) in self.truns.iter_mut().zip(sizes) {
+                let uniform = default.is_some() && sizes.iter().all(|it| Some(*it) == default);
+                for (entry, size) in trun.entries.data.iter_mut().zip(sizes) {
+                    entry.sample_size = Some(size).filter(|_| !uniform).into();
+                }
+            }
+        }
+
+        let flags = self.truns.iter().map(|trun| trun.entries.data.iter().enumerate()
+            .map(|(i, entry)| trun.entries.offset.first_sample_flags.filter(|_| i == 0)
+                .or(entry.sample_flags.0)
+                .or(*tfhd.default_sample_flags)
+                .or(trex.map(|it| it.default_sample_flags)))
+            .collect::<Option<Vec<_>>>()
+        ).collect::<Option<Vec<_>>>();
+        if let Some(flags) = flags {
+            let inherited = trex.map(|it| it.default_sample_flags);
+            let default = best_default(&flags, inherited, true);
+            tfhd.default_sample_flags = default.filter(|_| default != inherited).into();
+            tfhd.flags.unset_has_default_sample_flags();
+            for (trun, flags) in self.truns.iter_mut().zip(flags) {
+                let uniform = default.is_some() && flags.iter().skip(1).all(|it| Some(*it) == default);
+                trun.entries.offset.first_sample_flags = flags.first().copied().filter(|it| uniform && Some(*it) != default).into();
+                for (entry, flags) in trun.entries.data.iter_mut().zip(flags) {
+                    entry.sample_flags = Some(flags).filter(|_| !uniform).into();
+                }
+            }
+        }
+
+        for trun in &mut self.truns {
+            let offsets = &mut trun.entries.data;
+            let zero = offsets.iter().all(|it| matches!(it.sample_composition_time_offset.0, None | Some(VersionedSignedU32::Unsigned(0) | VersionedSignedU32::Signed(0))));
+            for entry in offsets {
+                entry.sample_composition_time_offset = if zero {
+                    None
+                } else {
+                    Some(entry.sample_composition_time_offset.unwrap_or_default())
+                }.into();
+            }
+        }
+    }
+}
+
+/// Picks the default value making the per-sample fields of the runs the smallest, `None` if every sample should be explicit.
+/// `inherited` is the `trex` default that applies without a `tfhd` default,
+/// `first_sample` allows the first sample of a run to differ through `first_sample_flags`.
+fn best_default<T: Copy + Eq>(runs: &[Vec<T>], inherited: Option<T>, first_sample: bool) -> Option<T> {
+    let skip = if first_sample { 1 } else { 0 };
+    let uniform = |run: &Vec<T>, default: T| run.iter().skip(skip).all(|it| *it == default);
+    let cost = |default: Option<T>| {
+        let header = if default.is_some() && default != inherited { 4 } else { 0 };
+        header + runs.iter().map(|run| match default {
+            Some(default) if uniform(run, default) => {
+                if first_sample && run.first().is_some_and(|it| *it != default) { 4 } else { 0 }
+            }
+            _ => 4 * run.len()
+        }).sum::<usize>()
+    };
+    // only a value some run is uniform with can save anything
+    let mut candidates = vec![inherited];
+    for run in runs {
+        if let Some(value) = run.get(skip).or_else(|| run.first()) {
+            if uniform(run, *value) && !candidates.contains(&Some(*value)) {
+                candidates.push(Some(*value));
+            }
+        }
+    }
+    let mut best = (cost(None), None);
+    for candidate in candidates {
+        let cost = cost(candidate);
+        if cost < best.0 || (cost == best.0 && candidate == inherited) {
+            best = (cost, candidate);
+        }
+    }
+    best.1
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mp4box::box_trait::IBox;
+    use crate::mp4box::sdtp::SdtpEntry;
+    use crate::mp4box::tfdt::Tfdt;
+    use crate::mp4box::tfhd::{Tfhd, TfhdFlags};
+    use crate::mp4box::traf::{Traf, TrafBox};
+    use crate::mp4box::trex::{SampleFlags, Trex};
+    use crate::mp4box::trun::{Trun, TrunEntry};
+    use crate::types::array::Mp4VersionedOffsetArray;
+    use crate::types::versioned_signed_int::VersionedSignedU32;
+
+    fn trun(entries: &[(u32, u32, SampleFlags)]) -> Trun {
+        Trun {
+            entries: Mp4VersionedOffsetArray::new(entries.iter().map(|(duration, size, flags)| TrunEntry {
+                sample_duration: (*duration).into(),
+                sample_size: (*size).into(),
+                sample_flags: (*flags).into(),
+                sample_composition_time_offset: VersionedSignedU32::Unsigned(0).into()
+            }).collect(), Default::default())
+        }
+    }
+
+    #[test]
+    pub fn test_compact() {
+        let trex = Trex {
+            track_id: 1,
+            default_sample_description_index: 1,
+            default_sample_duration: 1000,
+            default_sample_size: 0,
+            default_sample_flags: SampleFlags::sync()
+        };
+        let sync = SampleFlags::sync();
+        let delta = SampleFlags::non_sync_depends();
+        let mut traf: TrafBox = Traf {
+            tfhd: Some(Tfhd {
+                track_id: 1,
+                base_data_offset: Default::default(),
+                sample_description_index: Default::default(),
+                default_sample_duration: Default::default(),
+                default_sample_size: Default::default(),
+                default_sample_flags: Default::default(),
+                flags: Default::default()
+            }.into()),
+            tfdt: None,
+            sdtp: None,
+            sbgps: vec![],
+            sgpds: vec![],
+            truns: vec![
+                trun(&[(1000, 10, sync), (1000, 20, delta), (1000, 30, delta)]).into(),
+                trun(&[(1000, 40, delta), (1000, 50, delta)]).into(),
+            ]
+        }.into();
+        let size = traf.byte_size();
+        traf.compact(Some(&trex));
+        assert!(traf.byte_size() < size);
+
+        let tfhd = traf.tfhd.as_ref().unwrap();
+        assert_eq!(*tfhd.default_sample_duration, None);
+        assert_eq!(*tfhd.default_sample_flags, Some(delta));
+        assert_eq!(*traf.truns[0].entries.offset.first_sample_flags, Some(sync));
+        assert_eq!(*traf.truns[1].entries.offset.first_sample_flags, None);
+        for entry in traf.truns.iter().flat_map(|it| it.entries.data.iter()) {
+            assert_eq!(*entry.sample_duration, None);
+            assert_eq!(*entry.sample_flags, None);
+            assert_eq!(*entry.sample_composition_time_offset, None);
+            assert!(entry.sample_size.is_some());
+        }
+        assert_eq!(traf.duration(Some(&trex)), 5000);
+        assert_eq!(traf.first_sample_flags(Some(&trex)), Some(sync));
+
+        traf.sdtp = Some(Default::default());
+        traf.update_sdtp(Some(&trex));
+        let expected = [sync, delta, delta, delta, delta].map(SdtpEntry::from).to_vec();
+        assert_eq!(traf.sdtp.as_ref().map(|it| &it.entries), Some(&expected));
+    }
+
+    #[test]
+    pub fn test_samples() {
+        let mut flags = TfhdFlags::default();
+        flags.set_default_base_is_moof();
+        let mut first = trun(&[(1000, 10, SampleFlags::sync()), (2000, 20, SampleFlags::non_sync_depends())]);
+        first.entries.offset.data_offset = 100.into();
+        first.entries.data[1].sample_composition_time_offset = VersionedSignedU32::Signed(-500).into();
+        let mut second = trun(&[(1000, 30, SampleFlags::non_sync_depends())]);
+        second.entries.offset.data_offset = 200.into();
+        let traf = Traf {
+            tfhd: Some(Tfhd {
+                track_id: 1,
+                base_data_offset: Default::default(),
+                sample_description_index: Default::default(),
+                default_sample_duration: Default::default(),
+                default_sample_size: Default::default(),
+                default_sample_flags: Default::default(),
+                flags
+            }.into()),
+            tfdt: Some(Tfdt { base_media_decode_time: 9000u64.into() }.into()),
+            sdtp: None,
+            sbgps: vec![],
+            sgpds: vec![],
+            truns: vec![first.into(), second.into()]
+        };
+        let samples = traf.samples(None, 5000);
+        assert_eq!(samples.iter().map(|it| it.offset).collect::<Vec<_>>(), vec![5100, 5110, 5200]);
+        assert_eq!(samples.iter().map(|it| it.decode_time).collect::<Vec<_>>(), vec![Some(9000), Some(10000), Some(12000)]);
+        assert_eq!(samples[1].presentation_time(), Some(9500));
+        assert_eq!(traf.duration(None), 4000);
+    }
+
 }
@@ -1,10 +1,43 @@
 use crate::base_box;
+use crate::mp4box::edts::EdtsBox;
+use crate::mp4box::elst::ElstBox;
 use crate::mp4box::mdia::MdiaBox;
+use crate::mp4box::stbl::StblBox;
+use crate::mp4box::stsd::StsdSampleEntry;
 use crate::mp4box::tkhd::TkhdBox;
 
 base_box! {
     box (b"trak", Trak, TrakBox) children {
         tkhd: TkhdBox,
+        edts: EdtsBox,
         mdia: MdiaBox,
     }
-}
\ No newline at end of file
+}
+
+impl Trak {
+    pub fn track_id(&self) -> Option<u32> {
+        self.tkhd.as_ref().map(|it| it.track_id)
+    }
+
+    /// The media timescale from `mdhd`.
+    pub fn timescale(&self) -> Option<u32> {
+        self.mdia.as_ref()?.mdhd.as_ref().map(|it| it.timescale)
+    }
+
+    pub fn handler_type(&self) -> Option<[u8; 4]> {
+        self.mdia.as_ref()?.hdlr.as_ref().map(|it| it.handler_type)
+    }
+
+    pub fn elst(&self) -> Option<&ElstBox> {
+        self.edts.as_ref()?.elst.as_ref()
+    }
+
+    pub fn stbl(&self) -> Option<&StblBox> {
+        self.mdia.as_ref()?.minf.as_ref()?.stbl.as_ref()
+    }
+
+    /// The first sample description of the track.
+    pub fn sample_entry(&self) -> Option<&StsdSampleEntry> {
+        self.stbl()?.stsd.as_ref()?.entries.0.first()
+    }
+}
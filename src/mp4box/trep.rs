@@ -0,0 +1,8 @@
+use crate::full_box;
+
+full_box! {
+    box (b"trep", Trep, TrepBox, u32)
+    data {
+        track_id: u32,
+    }
+}
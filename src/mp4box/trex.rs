@@ -6,11 +6,28 @@ use crate::bytes_read::{Mp4Readable, ReadMp4};
 use crate::error::MP4Error;
 use crate::full_box;
 
+macro_rules! sample_flag_enum {
+    ($name:ident { $($variant:ident = $value:literal),* $(,)* }) => {
+        impl TryFrom<u8> for $name {
+            type Error = MP4Error;
+
+            fn try_from(value: u8) -> Result<Self, Self::Error> {
+                match value {
+                    $($value => Ok(Self::$variant),)*
+                    _ => Err(MP4Error::Custom(format!("Invalid {} value: {}", stringify!($name), value)))
+                }
+            }
+        }
+    };
+}
+
 #[repr(u8)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IsLeading {
-    /// the	leading	nature of this sample is unknown
+    /// the leading nature of this sample is unknown
     Unknown = 0,
-    /// this sample is a leading sample that has a dependency before the referenced I‐picture (and is therefore	not	decodable)
+    /// this sample is a leading sample that has a dependency before the referenced I‐picture (and is therefore not decodable)
     LeadingWithDependency = 1,
     /// this sample is not a leading sample
     NotLeading = 2,
@@ -18,48 +35,124 @@ pub enum IsLeading {
     LeadingWithoutDependency = 3,
 }
 
+sample_flag_enum!(IsLeading { Unknown = 0, LeadingWithDependency = 1, NotLeading = 2, LeadingWithoutDependency = 3 });
+
 #[repr(u8)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SampleDependsOn {
-    /// the	dependency	of	this	sample	is	unknown
+    /// the dependency of this sample is unknown
     Unknown = 0,
-    /// 	 this	sample	does	depend	on	others	(not	an	I	picture)
+    /// this sample does depend on others (not an I picture)
     DependsOn = 1,
-    ///	 this	sample	does	not	depend	on	others	(I	picture)
+    /// this sample does not depend on others (I picture)
     DoesntDependOn = 2,
     Reserved = 3,
 }
 
+sample_flag_enum!(SampleDependsOn { Unknown = 0, DependsOn = 1, DoesntDependOn = 2, Reserved = 3 });
+
 #[repr(u8)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SampleIsDependedOn {
-    /// the	dependency	of	other	samples	on	this	sample	is	unknown
+    /// the dependency of other samples on this sample is unknown
     Unknown = 0,
-    ///other	samples	may	depend	on	this	one	(not	disposable)
+    /// other samples may depend on this one (not disposable)
     DependedOn = 1,
-    ///no	other	sample	depends	on	this	one	(disposable)
+    /// no other sample depends on this one (disposable)
     NotDependedOn = 2,
     Reserved = 3,
 }
 
+sample_flag_enum!(SampleIsDependedOn { Unknown = 0, DependedOn = 1, NotDependedOn = 2, Reserved = 3 });
+
 #[repr(u8)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SampleHasRedundancy {
-    ///	 it	is	unknown	whether	there	is	redundant	coding	in	this	sample
+    /// it is unknown whether there is redundant coding in this sample
     Unknown = 0,
-    ///	 there	is	redundant	coding	in	this	sample
+    /// there is redundant coding in this sample
     Redundant = 1,
-    ///	 there	is	no	redundant	coding	in	this	sample
+    /// there is no redundant coding in this sample
     NotRedundant = 2,
     Reserved = 3,
 }
 
+sample_flag_enum!(SampleHasRedundancy { Unknown = 0, Redundant = 1, NotRedundant = 2, Reserved = 3 });
+
 bitregions! {
     pub SampleFlags u32 {
-        IS_LEADING:                     0b0000000000000000__0_000_00_00_00_11_0000,
-        SAMPLE_DEPENDS_ON:              0b0000000000000000__0_000_00_00_11_00_0000,
-        SAMPLE_IS_DEPENDED_ON:          0b0000000000000000__0_000_00_11_00_00_0000,
-        SAMPLE_HAS_REDUNDANCY:          0b0000000000000000__0_000_11_00_00_00_0000,
-        SAMPLE_PADDING_VALUE:           0b0000000000000000__0_111_00_00_00_00_0000,
-        SAMPLE_IS_NON_SYNC_SAMPLE:      0b0000000000000000__1_000_00_00_00_00_0000,
-        SAMPLE_DEGRADATION_PRIORITY:    0b1111111111111111__0_000_00_00_00_00_0000,
+        IS_LEADING:                     0b0000_11_00_00_00_000_0__0000000000000000,
+        SAMPLE_DEPENDS_ON:              0b0000_00_11_00_00_000_0__0000000000000000,
+        SAMPLE_IS_DEPENDED_ON:          0b0000_00_00_11_00_000_0__0000000000000000,
+        SAMPLE_HAS_REDUNDANCY:          0b0000_00_00_00_11_000_0__0000000000000000,
+        SAMPLE_PADDING_VALUE:           0b0000_00_00_00_00_111_0__0000000000000000,
+        SAMPLE_IS_NON_SYNC_SAMPLE:      0b0000_00_00_00_00_000_1__0000000000000000,
+        SAMPLE_DEGRADATION_PRIORITY:    0b0000_00_00_00_00_000_0__1111111111111111,
+    }
+}
+
+impl SampleFlags {
+
+    /// A sync sample that does not depend on others, such as an IDR picture.
+    pub fn sync() -> Self {
+        let mut flags = Self::default();
+        flags.set_depends_on(SampleDependsOn::DoesntDependOn);
+        flags
+    }
+
+    /// A non sync sample that depends on others, such as a P or B picture.
+    pub fn non_sync_depends() -> Self {
+        let mut flags = Self::default();
+        flags.set_depends_on(SampleDependsOn::DependsOn);
+        flags.set_sample_is_non_sync_sample();
+        flags
+    }
+
+    pub fn is_sync(&self) -> bool {
+        !self.sample_is_non_sync_sample()
+    }
+
+    pub fn set_sync(&mut self, sync: bool) {
+        if sync {
+            self.unset_sample_is_non_sync_sample()
+        } else {
+            self.set_sample_is_non_sync_sample()
+        }
+    }
+
+    pub fn leading(&self) -> IsLeading {
+        self.is_leading().try_into().unwrap_or(IsLeading::Unknown)
+    }
+
+    pub fn set_leading(&mut self, value: IsLeading) {
+        self.set_is_leading(value as u8)
+    }
+
+    pub fn depends_on(&self) -> SampleDependsOn {
+        self.sample_depends_on().try_into().unwrap_or(SampleDependsOn::Unknown)
+    }
+
+    pub fn set_depends_on(&mut self, value: SampleDependsOn) {
+        self.set_sample_depends_on(value as u8)
+    }
+
+    pub fn is_depended_on(&self) -> SampleIsDependedOn {
+        self.sample_is_depended_on().try_into().unwrap_or(SampleIsDependedOn::Unknown)
+    }
+
+    pub fn set_is_depended_on(&mut self, value: SampleIsDependedOn) {
+        self.set_sample_is_depended_on(value as u8)
+    }
+
+    pub fn has_redundancy(&self) -> SampleHasRedundancy {
+        self.sample_has_redundancy().try_into().unwrap_or(SampleHasRedundancy::Unknown)
+    }
+
+    pub fn set_has_redundancy(&mut self, value: SampleHasRedundancy) {
+        self.set_sample_has_redundancy(value as u8)
     }
 }
 
@@ -87,6 +180,53 @@ impl Hash for SampleFlags {
     }
 }
 
+/// The serialized form of [`SampleFlags`], one field per region.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SampleFlagsFields {
+    is_leading: IsLeading,
+    depends_on: SampleDependsOn,
+    is_depended_on: SampleIsDependedOn,
+    has_redundancy: SampleHasRedundancy,
+    padding_value: u8,
+    is_non_sync_sample: bool,
+    degradation_priority: u16,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SampleFlags {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SampleFlagsFields {
+            is_leading: self.leading(),
+            depends_on: self.depends_on(),
+            is_depended_on: self.is_depended_on(),
+            has_redundancy: self.has_redundancy(),
+            padding_value: self.sample_padding_value(),
+            is_non_sync_sample: self.sample_is_non_sync_sample(),
+            degradation_priority: self.sample_degradation_priority(),
+        }.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SampleFlags {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = SampleFlagsFields::deserialize(deserializer)?;
+        if fields.padding_value > 0b111 {
+            return Err(serde::de::Error::custom(format!("Invalid sample padding value: {}", fields.padding_value)));
+        }
+        let mut flags = Self::default();
+        flags.set_leading(fields.is_leading);
+        flags.set_depends_on(fields.depends_on);
+        flags.set_is_depended_on(fields.is_depended_on);
+        flags.set_has_redundancy(fields.has_redundancy);
+        flags.set_sample_padding_value(fields.padding_value);
+        flags.set_sync(!fields.is_non_sync_sample);
+        flags.set_sample_degradation_priority(fields.degradation_priority);
+        Ok(flags)
+    }
+}
+
 full_box! {
     box (b"trex", Trex, TrexBox, u32)
     data {
@@ -97,3 +237,25 @@ full_box! {
         default_sample_flags: SampleFlags,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::mp4box::trex::{IsLeading, SampleDependsOn, SampleFlags, SampleIsDependedOn};
+
+    #[test]
+    fn test_sample_flags() {
+        assert_eq!(SampleFlags::sync().raw(), 0x0200_0000);
+        assert_eq!(SampleFlags::non_sync_depends().raw(), 0x0101_0000);
+        let mut flags = SampleFlags::non_sync_depends();
+        flags.set_is_depended_on(SampleIsDependedOn::NotDependedOn);
+        flags.set_leading(IsLeading::NotLeading);
+        assert!(!flags.is_sync());
+        assert_eq!(flags.depends_on(), SampleDependsOn::DependsOn);
+        assert_eq!(flags.is_depended_on(), SampleIsDependedOn::NotDependedOn);
+        assert_eq!(flags.leading(), IsLeading::NotLeading);
+        flags.set_sync(true);
+        assert!(flags.is_sync());
+        assert!(SampleDependsOn::try_from(4).is_err());
+    }
+
+}
@@ -25,36 +25,43 @@ impl Hash for TrunFlags {
 
 flag_option! {
     #[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct TrunDataOffset(pub i32, TrunFlags, HAS_DATA_OFFSET);
 }
 
 flag_option! {
     #[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct TrunFirstSampleFlags(pub SampleFlags, TrunFlags, HAS_FIRST_SAMPLE_FLAGS);
 }
 
 flag_option! {
     #[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct TrunSampleDuration(pub u32, TrunFlags, HAS_SAMPLE_DURATION);
 }
 
 flag_option! {
     #[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct TrunSampleSize(pub u32, TrunFlags, HAS_SAMPLE_SIZE);
 }
 
 flag_option! {
     #[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct TrunSampleFlags(pub SampleFlags, TrunFlags, HAS_SAMPLE_FLAGS);
 }
 
 flag_option! {
     #[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct TrunSampleCompositionOffset(pub VersionedSignedU32, TrunFlags, HAS_SAMPLE_COMPOSITION);
 }
 
 mp4_versioned_data! {
      #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+     #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct TrunOffset {
         pub data_offset: TrunDataOffset,
         pub first_sample_flags: TrunFirstSampleFlags,
@@ -63,6 +70,7 @@ mp4_versioned_data! {
 
 mp4_versioned_data! {
      #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+     #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct TrunEntry {
         pub sample_duration: TrunSampleDuration,
         pub sample_size: TrunSampleSize,
@@ -104,7 +112,7 @@ mod test {
                     data: vec![TrunEntry {
                         sample_duration: 32u32.into(),
                         sample_size: TrunSampleSize::from(100000u32),
-                        sample_flags: SampleFlags::from(37814272).into(),
+                        sample_flags: SampleFlags::non_sync_depends().into(),
                         sample_composition_time_offset: Default::default()
                     }],
                     offset: TrunOffset {
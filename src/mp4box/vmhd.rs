@@ -6,6 +6,7 @@ use crate::bytes_write::{Mp4Writable, WriteMp4};
 use crate::error::MP4Error;
 
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VideoGraphicsMode(pub u16);
 
 impl VideoGraphicsMode {
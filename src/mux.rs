@@ -0,0 +1,285 @@
+use std::io::SeekFrom;
+use std::time::Duration;
+use futures::{AsyncReadExt, AsyncSeekExt};
+use crate::bytes_read::ReadMp4;
+use crate::bytes_write::WriteMp4;
+use crate::demux::Demuxer;
+use crate::error::MP4Error;
+use crate::fragment::{Fragment, FragmentWriter};
+use crate::mp4box::mdat::MdatBox;
+use crate::mp4box::mfhd::Mfhd;
+use crate::mp4box::moof::{Moof, MoofBox};
+use crate::mp4box::moov::{Moov, MoovBox};
+use crate::mp4box::mvex::Mvex;
+use crate::mp4box::traf::ResolvedSample;
+use crate::mp4box::trex::Trex;
+use crate::remux::{fragmented_ftyp, interleaved_chunks, read_boxes, write_progressive, ProgressiveTrack};
+use crate::sample_table::SampleTable;
+use crate::types::duration::{duration_from_ticks, Mp4Duration};
+
+/// A track of one of the inputs of [`mux_tracks`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TrackSelection {
+    /// index of the input
+    pub input: usize,
+    pub track_id: u32,
+}
+
+/// Writes the given tracks of a file into a new one, see [`mux_tracks`].
+pub async fn extract_tracks<R: ReadMp4, W: WriteMp4>(reader: R, track_ids: &[u32], allow_stz2: bool, writer: &mut W) -> Result<usize, MP4Error> {
+    let selection = track_ids.iter()
+        .map(|track_id| TrackSelection { input: 0, track_id: *track_id })
+        .collect::<Vec<_>>();
+    mux_tracks(vec![reader], &selection, allow_stz2, writer).await
+}
+
+/// Writes the selected tracks of the inputs into a single file, with `track_id`s numbered from 1 in the order of `selection`.
+/// The `mvhd` is taken from the input of the first selected track. Returns the number of bytes written.
+///
+/// Progressive inputs give a progressive file whose chunks hold at most a second of a track, interleaved by decode time,
+/// its sample sizes are written in a `stz2` when `allow_stz2` is set and it is smaller than a `stsz`.
+/// Fragmented inputs give a fragmented file made of the input fragments restricted to the selected tracks, ordered by decode time.
+/// Progressive and fragmented inputs can not be mixed, turn them into the same layout first with
+/// [`Remuxer`](crate::remux::Remuxer) or [`defragment`](crate::remux::defragment).
+pub async fn mux_tracks<R: ReadMp4, W: WriteMp4>(inputs: Vec<R>, selection: &[TrackSelection], allow_stz2: bool, writer: &mut W) -> Result<usize, MP4Error> {
+    let first = selection.first().ok_or_else(|| MP4Error::Custom("No track is selected".to_string()))?;
+    let mut sources = vec![];
+    let mut readers = vec![];
+    for reader in inputs {
+        let mut demuxer = Demuxer::new(reader);
+        sources.push(read_boxes(&mut demuxer).await?);
+        readers.push(demuxer.into_inner());
+    }
+    let source = |selected: &TrackSelection| sources.get(selected.input)
+        .map(|(moov, _)| moov)
+        .ok_or_else(|| MP4Error::Custom(format!("No input {}", selected.input)));
+
+    let fragmented = source(first)?.mvex.is_some();
+    let mvhd = source(first)?.mvhd.clone();
+    let movie_timescale = mvhd.as_ref().map(|it| it.timescale).unwrap_or(1000);
+    let mut moov = Moov { mvhd, traks: vec![], mvex: None };
+    let mut trexs = vec![];
+    for (index, selected) in selection.iter().enumerate() {
+        let source = source(selected)?;
+        if source.mvex.is_some() != fragmented {
+            return Err(MP4Error::Custom("Can not mux progressive and fragmented inputs".to_string()));
+        }
+        let mut trak = source.trak(selected.track_id)
+            .ok_or_else(|| MP4Error::Custom(format!("Input {} has no track {}", selected.input, selected.track_id)))?
+            .clone();
+        let track_id = index as u32 + 1;
+        let timescale = source.mvhd.as_ref().map(|it| it.timescale).unwrap_or(1000);
+        let rescale = |it: u64| (it as u128 * movie_timescale as u128 / timescale.max(1) as u128) as u64;
+        if let Some(tkhd) = &mut trak.tkhd {
+            tkhd.track_id = track_id;
+            tkhd.duration = Mp4Duration(tkhd.duration.0.map(rescale));
+        }
+        if let Some(elst) = trak.edts.as_mut().and_then(|it| it.elst.as_mut()) {
+            for entry in &mut elst.entries {
+                entry.segment_duration = rescale(entry.segment_duration);
+            }
+        }
+        moov.traks.push(trak);
+        let mut trex = source.trex(selected.track_id).map(|it| it.inner.inner.clone()).unwrap_or(Trex {
+            track_id: 0,
+            default_sample_description_index: 1,
+            default_sample_duration: 0,
+            default_sample_size: 0,
+            default_sample_flags: Default::default()
+        });
+        trex.track_id = track_id;
+        trexs.push(trex.into());
+    }
+    if let Some(mvhd) = &mut moov.mvhd {
+        mvhd.next_track_id = selection.len() as u32 + 1;
+    }
+
+    if !fragmented {
+        return mux_progressive(&mut readers, &moov, selection, allow_stz2, writer).await;
+    }
+    moov.mvex = Some(Mvex { mehd: None, trex: trexs, trep: vec![] }.into());
+    let mut fragment_writer = FragmentWriter::new(writer);
+    fragment_writer.write_init(&fragmented_ftyp(), &MoovBox::from(moov))?;
+
+    let mut fragments = vec![];
+    for (input, (source, moofs)) in sources.iter().enumerate() {
+        for (index, (_, moof)) in moofs.iter().enumerate() {
+            let trafs = moof.trafs.iter()
+                .filter(|traf| selection.iter().any(|it| it.input == input && Some(it.track_id) == traf.track_id()))
+                .collect::<Vec<_>>();
+            if trafs.is_empty() {
+                continue;
+            }
+            let start = trafs.iter().filter_map(|traf| {
+                let timescale = source.trak(traf.track_id()?)?.timescale()?;
+                duration_from_ticks(*traf.tfdt.as_ref()?.base_media_decode_time, timescale)
+            }).min();
+            fragments.push((start.unwrap_or(Duration::ZERO), input, index));
+        }
+    }
+    fragments.sort_by_key(|(start, _, _)| *start);
+    for (sequence_number, (_, input, index)) in fragments.into_iter().enumerate() {
+        let (source, moofs) = &sources[input];
+        let (position, moof) = &moofs[index];
+        let mut fragment = select_fragment(&mut readers[input], source, *position, moof, input, selection).await?;
+        if let Some(mfhd) = &mut fragment.moof.mfhd {
+            mfhd.sequence_number = sequence_number as u32 + 1;
+        }
+        fragment_writer.write_fragment(&mut fragment)?;
+    }
+    Ok(fragment_writer.position() as usize)
+}
+
+/// Copies the `traf`s of the selected tracks of an input fragment, with their samples.
+async fn select_fragment<R: ReadMp4>(
+    reader: &mut R,
+    source: &Moov,
+    position: u64,
+    moof: &MoofBox,
+    input: usize,
+    selection: &[TrackSelection]
+) -> Result<Fragment, MP4Error> {
+    let mut trafs = vec![];
+    let mut data = vec![];
+    for (traf, samples) in moof.trafs.iter().zip(moof.samples(position, Some(source))) {
+        for (index, _) in selection.iter().enumerate().filter(|(_, it)| it.input == input && Some(it.track_id) == traf.track_id()) {
+            let mut traf = traf.clone();
+            if let Some(tfhd) = &mut traf.tfhd {
+                tfhd.track_id = index as u32 + 1;
+            }
+            for sample in &samples {
+                reader.seek(SeekFrom::Start(sample.offset)).await?;
+                let start = data.len();
+                data.resize(start + sample.size as usize, 0);
+                reader.read_exact(&mut data[start..]).await?;
+            }
+            trafs.push(traf);
+        }
+    }
+    let moof = Moof {
+        mfhd: Some(Mfhd { sequence_number: 0 }.into()),
+        trafs
+    };
+    Ok(Fragment::new(moof.into(), MdatBox(data)))
+}
+
+/// Writes the samples of progressive inputs in chunks of at most a second, interleaved by decode time.
+async fn mux_progressive<R: ReadMp4, W: WriteMp4>(
+    readers: &mut [R],
+    moov: &Moov,
+    selection: &[TrackSelection],
+    allow_stz2: bool,
+    writer: &mut W
+) -> Result<usize, MP4Error> {
+    let mut tracks = vec![];
+    let mut chunks = vec![];
+    for (index, (trak, selected)) in moov.traks.iter().zip(selection).enumerate() {
+        let table = match trak.stbl() {
+            Some(stbl) => SampleTable::new(stbl)?,
+            None => return Err(MP4Error::Custom(format!("Track {} has no sample table", selected.track_id)))
+        };
+        let timescale = trak.timescale().filter(|it| *it > 0).unwrap_or(1000);
+        let samples = table.samples().collect::<Vec<_>>();
+        chunks.extend(interleaved_chunks(selected.input, index, timescale, &samples));
+        let samples = samples.into_iter().map(ResolvedSample::from).collect();
+        tracks.push(ProgressiveTrack { track_id: index as u32 + 1, samples });
+    }
+    chunks.sort_by_key(|(start, _)| *start);
+    let chunks = chunks.into_iter().map(|(_, chunk)| chunk).collect::<Vec<_>>();
+    write_progressive(readers, moov, &tracks, &chunks, allow_stz2, writer).await
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::demux::{Demuxer, Mp4Event};
+    use crate::error::MP4Error;
+    use crate::fragment::FragmentWriter;
+    use crate::mp4box::box_trait::IBox;
+    use crate::mux::{extract_tracks, mux_tracks, TrackSelection};
+    use crate::remux::Remuxer;
+    use crate::remux::test::progressive;
+
+    #[test]
+    pub fn test_progressive() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let source = progressive()?;
+            let mut buf = vec![];
+            extract_tracks(futures::io::Cursor::new(source.clone()), &[2], false, &mut buf).await?;
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let moov = demuxer.read_moov().await?;
+            assert_eq!(moov.traks.len(), 1);
+            assert_eq!(moov.traks[0].track_id(), Some(1));
+            assert_eq!(moov.mvhd.as_ref().map(|it| it.next_track_id), Some(2));
+            let mut data = vec![];
+            while let Some(sample) = demuxer.next_sample().await? {
+                assert_eq!(sample.track_id, 1);
+                data.extend(sample.data);
+            }
+            assert_eq!(data, (100..112).flat_map(|it| [it; 2]).collect::<Vec<_>>());
+
+            let inputs = vec![futures::io::Cursor::new(source.clone()), futures::io::Cursor::new(source)];
+            let selection = [TrackSelection { input: 1, track_id: 2 }, TrackSelection { input: 0, track_id: 1 }];
+            let mut buf = vec![];
+            mux_tracks(inputs, &selection, false, &mut buf).await?;
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let moov = demuxer.read_moov().await?;
+            assert_eq!(moov.trak(2).and_then(|it| it.elst()).map(|it| it.entries.len()), Some(1));
+            assert_eq!(moov.mvhd.as_ref().map(|it| it.next_track_id), Some(3));
+            let mut samples = vec![];
+            while let Some(sample) = demuxer.next_sample().await? {
+                samples.push((sample.track_id, sample.decode_time, sample.data[0]));
+            }
+            // one second chunks, interleaved by decode time
+            assert_eq!(samples[..5], [(1, 0, 100), (1, 1000, 101), (2, 0, 0), (1, 2000, 102), (1, 3000, 103)]);
+            assert_eq!(samples.len(), 18);
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_fragmented() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let mut remuxer = Remuxer::new(futures::io::Cursor::new(progressive()?), Duration::from_secs(2)).await?;
+            let mut writer = FragmentWriter::new(vec![]);
+            remuxer.remux(&mut writer).await?;
+            let source = writer.into_inner();
+
+            let inputs = vec![futures::io::Cursor::new(source.clone()), futures::io::Cursor::new(source)];
+            let selection = [TrackSelection { input: 0, track_id: 2 }, TrackSelection { input: 1, track_id: 1 }];
+            let mut buf = vec![];
+            mux_tracks(inputs, &selection, false, &mut buf).await?;
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(buf));
+            let mut fragments = vec![];
+            let mut moof = None;
+            while let Some(event) = demuxer.next().await? {
+                match event {
+                    Mp4Event::Moov(moov) => {
+                        assert_eq!(moov.trex(1).map(|it| it.track_id), Some(1));
+                        assert_eq!(moov.trex(2).map(|it| it.track_id), Some(2));
+                        assert_eq!(moov.mvhd.as_ref().map(|it| it.next_track_id), Some(3));
+                    }
+                    Mp4Event::Moof(it) => moof = Some(it),
+                    Mp4Event::Mdat(mdat) => {
+                        let moof = moof.take().unwrap();
+                        let start = moof.byte_size() as u64 + 8;
+                        let samples = moof.samples(0, demuxer.moov().map(|it| &**it));
+                        fragments.push(moof.trafs.iter().zip(samples).map(|(traf, samples)| (
+                            traf.track_id().unwrap(),
+                            moof.mfhd.as_ref().unwrap().sequence_number,
+                            samples.iter().map(|it| mdat.0[(it.offset - start) as usize]).collect::<Vec<_>>()
+                        )).collect::<Vec<_>>());
+                    }
+                    _ => {}
+                }
+            }
+            // the fragments of both inputs start at 0s and 3s
+            assert_eq!(fragments.len(), 4);
+            assert_eq!(fragments[0], [(1, 1, (100..106).collect::<Vec<_>>())]);
+            assert_eq!(fragments[1], [(2, 2, vec![0, 1, 2])]);
+            assert_eq!(fragments[3], [(2, 4, vec![3, 4, 5])]);
+            Ok(())
+        })
+    }
+
+}
@@ -0,0 +1,326 @@
+use crate::error::MP4Error;
+use crate::mp4box::avcc::AVCDecoderConfigurationRecord;
+use crate::mp4box::hvcc::{HEVCDecoderConfigurationRecord, HvcCArray};
+use crate::mp4box::stsd::StsdSampleEntry;
+use crate::mp4box::trex::SampleFlags;
+use crate::nal::{avc, hevc};
+use crate::types::array::Mp4Array;
+
+const START_CODE: [u8; 4] = [0, 0, 0, 1];
+
+/// Splits an Annex B byte stream on its 3 or 4 byte start codes, anything before the first start code is ignored.
+pub fn split_annex_b(data: &[u8]) -> Vec<&[u8]> {
+    let mut nalus = vec![];
+    let mut start = None;
+    let mut i = 0;
+    while i + 2 < data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            if let Some(start) = start {
+                nalus.push(trim_trailing_zeros(&data[start..i]));
+            }
+            i += 3;
+            start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+    if let Some(start) = start {
+        nalus.push(trim_trailing_zeros(&data[start..]));
+    }
+    nalus.retain(|it| !it.is_empty());
+    nalus
+}
+
+fn trim_trailing_zeros(nal: &[u8]) -> &[u8] {
+    let len = nal.iter().rposition(|it| *it != 0).map(|it| it + 1).unwrap_or_default();
+    &nal[..len]
+}
+
+fn check_length_size(length_size: usize) -> Result<(), MP4Error> {
+    if !(1..=8).contains(&length_size) {
+        return Err(MP4Error::Custom(format!("Invalid NAL unit length size {}", length_size)));
+    }
+    Ok(())
+}
+
+/// Splits a mp4 sample into its NAL units, each prefixed by its size on `length_size` bytes.
+pub fn split_length_prefixed(data: &[u8], length_size: usize) -> Result<Vec<&[u8]>, MP4Error> {
+    check_length_size(length_size)?;
+    let mut nalus = vec![];
+    let mut rest = data;
+    while !rest.is_empty() {
+        if rest.len() < length_size {
+            return Err(MP4Error::Custom("Truncated NAL unit length".to_string()));
+        }
+        let (length, tail) = rest.split_at(length_size);
+        let length = length.iter().fold(0usize, |acc, it| acc << 8 | *it as usize);
+        if tail.len() < length {
+            return Err(MP4Error::Custom("Truncated NAL unit".to_string()));
+        }
+        let (nal, tail) = tail.split_at(length);
+        nalus.push(nal);
+        rest = tail;
+    }
+    Ok(nalus)
+}
+
+/// Joins NAL units into a mp4 sample, each prefixed by its size on `length_size` bytes.
+pub fn join_length_prefixed<'a>(nalus: impl IntoIterator<Item=&'a [u8]>, length_size: usize) -> Result<Vec<u8>, MP4Error> {
+    check_length_size(length_size)?;
+    let mut data = vec![];
+    for nal in nalus {
+        if length_size < 8 && nal.len() >> (length_size * 8) != 0 {
+            return Err(MP4Error::Custom(format!("NAL unit of {} bytes does not fit a {} byte length", nal.len(), length_size)));
+        }
+        data.extend_from_slice(&(nal.len() as u64).to_be_bytes()[8 - length_size..]);
+        data.extend_from_slice(nal);
+    }
+    Ok(data)
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum NalFormat {
+    Avc,
+    Hevc,
+}
+
+impl NalFormat {
+
+    pub fn nal_unit_type(&self, nal: &[u8]) -> Option<u8> {
+        match self {
+            NalFormat::Avc => avc::nal_unit_type(nal),
+            NalFormat::Hevc => hevc::nal_unit_type(nal)
+        }
+    }
+
+    pub fn is_parameter_set(&self, nal: &[u8]) -> bool {
+        matches!(
+            (self, self.nal_unit_type(nal)),
+            (NalFormat::Avc, Some(avc::NAL_SPS | avc::NAL_PPS | avc::NAL_SPS_EXT))
+                | (NalFormat::Hevc, Some(hevc::NAL_VPS | hevc::NAL_SPS | hevc::NAL_PPS))
+        )
+    }
+
+    /// IDR pictures for AVC, IRAP pictures for HEVC.
+    pub fn is_random_access(&self, nal: &[u8]) -> bool {
+        match (self, self.nal_unit_type(nal)) {
+            (NalFormat::Avc, Some(avc::NAL_IDR)) => true,
+            (NalFormat::Hevc, Some(nal_unit_type)) => hevc::is_irap(nal_unit_type),
+            _ => false
+        }
+    }
+}
+
+/// Parameter sets in the order they must be sent to the decoder.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct ParameterSets {
+    /// only used by HEVC
+    pub vps: Vec<Vec<u8>>,
+    pub sps: Vec<Vec<u8>>,
+    pub pps: Vec<Vec<u8>>,
+    /// only used by AVC
+    pub sps_ext: Vec<Vec<u8>>,
+}
+
+impl ParameterSets {
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=&[u8]> {
+        self.vps.iter().chain(&self.sps).chain(&self.pps).chain(&self.sps_ext).map(Vec::as_slice)
+    }
+
+    /// Adds a parameter set if it is not known yet, returns true if it was added.
+    fn insert(&mut self, format: NalFormat, nal: &[u8]) -> bool {
+        let sets = match (format, format.nal_unit_type(nal)) {
+            (NalFormat::Avc, Some(avc::NAL_SPS)) | (NalFormat::Hevc, Some(hevc::NAL_SPS)) => &mut self.sps,
+            (NalFormat::Avc, Some(avc::NAL_PPS)) | (NalFormat::Hevc, Some(hevc::NAL_PPS)) => &mut self.pps,
+            (NalFormat::Avc, Some(avc::NAL_SPS_EXT)) => &mut self.sps_ext,
+            (NalFormat::Hevc, Some(hevc::NAL_VPS)) => &mut self.vps,
+            _ => return false
+        };
+        if sets.iter().any(|it| it == nal) {
+            return false;
+        }
+        sets.push(nal.to_vec());
+        true
+    }
+}
+
+/// A sample converted from Annex B, with its flags derived from the picture type.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct NalSample {
+    pub data: Vec<u8>,
+    pub flags: SampleFlags,
+}
+
+/// Converts samples between Annex B byte streams and the length prefixed format of `avcC` / `hvcC`,
+/// keeping the parameter sets out of band.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct NalConverter {
+    pub format: NalFormat,
+    /// size of the NAL unit length prefix: `length_size_minus_one + 1`
+    pub length_size: u8,
+    pub parameter_sets: ParameterSets,
+}
+
+impl NalConverter {
+
+    pub fn new(format: NalFormat) -> Self {
+        Self {
+            format,
+            length_size: 4,
+            parameter_sets: Default::default()
+        }
+    }
+
+    pub fn from_avcc(config: &AVCDecoderConfigurationRecord) -> Self {
+        let to_vec = |it: &Mp4Array<u16, u8>| it.0.clone();
+        Self {
+            format: NalFormat::Avc,
+            length_size: u8::from(config.length_size_minus_one) + 1,
+            parameter_sets: ParameterSets {
+                vps: vec![],
+                sps: config.sps.0.iter().map(to_vec).collect(),
+                pps: config.pps.0.iter().map(to_vec).collect(),
+                sps_ext: config.ext.iter().flat_map(|it| it.sps_ext.0.iter()).map(to_vec).collect()
+            }
+        }
+    }
+
+    pub fn from_hvcc(config: &HEVCDecoderConfigurationRecord) -> Self {
+        let mut converter = Self::new(NalFormat::Hevc);
+        converter.length_size = config.length_size_minus_one + 1;
+        for nal in config.arrays.0.iter().flat_map(|it| it.nalus.0.iter()) {
+            converter.parameter_sets.insert(NalFormat::Hevc, &nal.0);
+        }
+        converter
+    }
+
+    /// `None` if the sample entry is not AVC or HEVC, or lacks its decoder configuration.
+    pub fn from_sample_entry(entry: &StsdSampleEntry) -> Option<Self> {
+        match entry {
+            StsdSampleEntry::Avc1(it) => Some(Self::from_avcc(&it.avcc.as_ref()?.avc_config)),
+            StsdSampleEntry::Hvc1(it) => Some(Self::from_hvcc(&it.hvcc.as_ref()?.hevc_config)),
+            StsdSampleEntry::Hev1(it) => Some(Self::from_hvcc(&it.hvcc.as_ref()?.hevc_config)),
+            _ => None
+        }
+    }
+
+    /// Converts an Annex B access unit to a mp4 sample.
+    /// In band parameter sets are removed and collected into [`NalConverter::parameter_sets`].
+    pub fn annex_b_to_sample(&mut self, access_unit: &[u8]) -> Result<NalSample, MP4Error> {
+        let mut nalus = vec![];
+        let mut sync = false;
+        for nal in split_annex_b(access_unit) {
+            if self.format.is_parameter_set(nal) {
+                self.parameter_sets.insert(self.format, nal);
+                continue;
+            }
+            sync |= self.format.is_random_access(nal);
+            nalus.push(nal);
+        }
+        Ok(NalSample {
+            data: join_length_prefixed(nalus, self.length_size as usize)?,
+            flags: if sync { SampleFlags::sync() } else { SampleFlags::non_sync_depends() }
+        })
+    }
+
+    /// Converts a mp4 sample to an Annex B access unit.
+    /// The parameter sets are inserted before random access pictures unless the sample already carries some.
+    pub fn sample_to_annex_b(&self, sample: &[u8]) -> Result<Vec<u8>, MP4Error> {
+        let nalus = split_length_prefixed(sample, self.length_size as usize)?;
+        let mut insert = !nalus.iter().any(|it| self.format.is_parameter_set(it));
+        let mut data = vec![];
+        for nal in nalus {
+            if insert && self.format.is_random_access(nal) {
+                for parameter_set in self.parameter_sets.iter() {
+                    data.extend_from_slice(&START_CODE);
+                    data.extend_from_slice(parameter_set);
+                }
+                insert = false;
+            }
+            data.extend_from_slice(&START_CODE);
+            data.extend_from_slice(nal);
+        }
+        Ok(data)
+    }
+
+    /// Stores the collected parameter sets and the length size into an `avcC` configuration.
+    pub fn update_avcc(&self, config: &mut AVCDecoderConfigurationRecord) {
+        config.length_size_minus_one = (self.length_size - 1).into();
+        config.sps = self.parameter_sets.sps.iter().map(|it| it.clone().into()).collect::<Vec<_>>().into();
+        config.pps = self.parameter_sets.pps.iter().map(|it| it.clone().into()).collect::<Vec<_>>().into();
+        if let Some(ext) = &mut config.ext {
+            ext.sps_ext = self.parameter_sets.sps_ext.iter().map(|it| it.clone().into()).collect::<Vec<_>>().into();
+        }
+    }
+
+    /// Stores the collected parameter sets and the length size into a `hvcC` configuration.
+    pub fn update_hvcc(&self, config: &mut HEVCDecoderConfigurationRecord) {
+        config.length_size_minus_one = self.length_size - 1;
+        let sets = [
+            (hevc::NAL_VPS, &self.parameter_sets.vps),
+            (hevc::NAL_SPS, &self.parameter_sets.sps),
+            (hevc::NAL_PPS, &self.parameter_sets.pps),
+        ];
+        config.arrays = sets.into_iter()
+            .filter(|(_, nalus)| !nalus.is_empty())
+            .map(|(nal_unit_type, nalus)| HvcCArray {
+                array_completeness: true,
+                nal_unit_type,
+                nalus: nalus.iter().map(|it| it.clone().into()).collect::<Vec<_>>().into()
+            })
+            .collect::<Vec<_>>().into();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::MP4Error;
+    use crate::mp4box::avcc::AVCDecoderConfigurationRecord;
+    use crate::mp4box::trex::SampleFlags;
+    use crate::nal::annex_b::{NalConverter, NalFormat, join_length_prefixed, split_annex_b, split_length_prefixed};
+
+    #[test]
+    fn test_split_annex_b() {
+        let data = [0, 0, 0, 1, 0x09, 0xf0, 0, 0, 1, 0x65, 1, 2, 0, 0, 0, 0, 1, 0x41, 3];
+        assert_eq!(split_annex_b(&data), vec![&[0x09, 0xf0][..], &[0x65, 1, 2], &[0x41, 3]]);
+    }
+
+    #[test]
+    fn test_invalid_length_size() {
+        assert!(split_length_prefixed(&[0, 1, 0x65], 0).is_err());
+        assert!(split_length_prefixed(&[0, 1, 0x65], 9).is_err());
+        assert!(join_length_prefixed([&[0x65u8][..]], 0).is_err());
+        assert_eq!(split_length_prefixed(&[0, 1, 0x65], 2).unwrap(), vec![&[0x65u8][..]]);
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), MP4Error> {
+        let sps = [0x67, 0x42, 0xc0, 0x1e];
+        let pps = [0x68, 0xce, 0x3c, 0x80];
+        let idr = [0x65, 0x88, 0x84];
+        let slice = [0x41, 0x9a, 0x02];
+        let annex_b = |nalus: &[&[u8]]| nalus.iter().flat_map(|it| [&[0u8, 0, 0, 1][..], it].concat()).collect::<Vec<u8>>();
+
+        let mut converter = NalConverter::new(NalFormat::Avc);
+        converter.length_size = 2;
+        let key = converter.annex_b_to_sample(&annex_b(&[&sps, &pps, &idr]))?;
+        assert_eq!(key.data, vec![0, 3, 0x65, 0x88, 0x84]);
+        assert_eq!(key.flags, SampleFlags::sync());
+        let delta = converter.annex_b_to_sample(&annex_b(&[&slice]))?;
+        assert_eq!(delta.flags, SampleFlags::non_sync_depends());
+
+        let mut config = AVCDecoderConfigurationRecord::default();
+        converter.update_avcc(&mut config);
+        let demuxer = NalConverter::from_avcc(&config);
+        assert_eq!(demuxer, converter);
+        assert_eq!(demuxer.sample_to_annex_b(&key.data)?, annex_b(&[&sps, &pps, &idr]));
+        assert_eq!(demuxer.sample_to_annex_b(&delta.data)?, annex_b(&[&slice]));
+        Ok(())
+    }
+
+}
@@ -0,0 +1,261 @@
+use crate::error::MP4Error;
+use crate::nal::bit_reader::BitReader;
+use crate::nal::unescape_rbsp;
+
+pub const NAL_SLICE: u8 = 1;
+pub const NAL_IDR: u8 = 5;
+pub const NAL_SEI: u8 = 6;
+pub const NAL_SPS: u8 = 7;
+pub const NAL_PPS: u8 = 8;
+pub const NAL_AUD: u8 = 9;
+pub const NAL_SPS_EXT: u8 = 13;
+
+/// The `nal_unit_type` of an AVC NAL unit from its header byte.
+pub fn nal_unit_type(nal: &[u8]) -> Option<u8> {
+    nal.first().map(|it| it & 0b1_1111)
+}
+
+/// Sample aspect ratios of `aspect_ratio_idc` 1 to 16 (ITU-T H.264 Table E-1).
+const ASPECT_RATIOS: [(u16, u16); 16] = [
+    (1, 1), (12, 11), (10, 11), (16, 11), (40, 33), (24, 11), (20, 11), (32, 11),
+    (80, 33), (18, 11), (15, 11), (64, 33), (160, 99), (4, 3), (3, 2), (2, 1)
+];
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct FrameCropping {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct AvcTiming {
+    pub num_units_in_tick: u32,
+    pub time_scale: u32,
+    pub fixed_frame_rate: bool,
+}
+
+/// The parts of the video usability information we care about.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct AvcVui {
+    /// width and height of a sample
+    pub sample_aspect_ratio: Option<(u16, u16)>,
+    pub video_full_range: bool,
+    pub timing: Option<AvcTiming>,
+}
+
+/// A sequence parameter set (ITU-T H.264 § 7.3.2.1.1).
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct AvcSps {
+    pub profile_idc: u8,
+    /// `constraint_set0_flag` to `constraint_set5_flag` and the reserved bits, as in `avcC`
+    pub constraint_flags: u8,
+    pub level_idc: u8,
+    pub seq_parameter_set_id: u32,
+    pub chroma_format_idc: u32,
+    pub separate_colour_plane: bool,
+    pub bit_depth_luma_minus8: u32,
+    pub bit_depth_chroma_minus8: u32,
+    pub log2_max_frame_num_minus4: u32,
+    pub pic_order_cnt_type: u32,
+    pub max_num_ref_frames: u32,
+    pub pic_width_in_mbs_minus1: u32,
+    pub pic_height_in_map_units_minus1: u32,
+    pub frame_mbs_only: bool,
+    pub frame_cropping: Option<FrameCropping>,
+    pub vui: Option<AvcVui>,
+}
+
+impl AvcSps {
+
+    /// Parses a SPS NAL unit, header byte included.
+    pub fn parse(nal: &[u8]) -> Result<Self, MP4Error> {
+        if nal_unit_type(nal) != Some(NAL_SPS) {
+            return Err(MP4Error::Custom("NAL unit is not a sequence parameter set".to_string()));
+        }
+        let rbsp = unescape_rbsp(&nal[1..]);
+        let mut reader = BitReader::new(&rbsp);
+        let mut sps = Self {
+            profile_idc: reader.read_u8()?,
+            constraint_flags: reader.read_u8()?,
+            level_idc: reader.read_u8()?,
+            seq_parameter_set_id: reader.read_ue()?,
+            chroma_format_idc: 1,
+            ..Default::default()
+        };
+        if matches!(sps.profile_idc, 100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135) {
+            sps.chroma_format_idc = reader.read_ue()?;
+            if sps.chroma_format_idc == 3 {
+                sps.separate_colour_plane = reader.read_bit()?;
+            }
+            sps.bit_depth_luma_minus8 = reader.read_ue()?;
+            sps.bit_depth_chroma_minus8 = reader.read_ue()?;
+            let _qpprime_y_zero_transform_bypass = reader.read_bit()?;
+            if reader.read_bit()? {
+                let count = if sps.chroma_format_idc != 3 { 8 } else { 12 };
+                for i in 0..count {
+                    if reader.read_bit()? {
+                        skip_scaling_list(&mut reader, if i < 6 { 16 } else { 64 })?;
+                    }
+                }
+            }
+        }
+        sps.log2_max_frame_num_minus4 = reader.read_ue()?;
+        sps.pic_order_cnt_type = reader.read_ue()?;
+        match sps.pic_order_cnt_type {
+            0 => {
+                let _log2_max_pic_order_cnt_lsb_minus4 = reader.read_ue()?;
+            }
+            1 => {
+                let _delta_pic_order_always_zero = reader.read_bit()?;
+                let _offset_for_non_ref_pic = reader.read_se()?;
+                let _offset_for_top_to_bottom_field = reader.read_se()?;
+                for _ in 0..reader.read_ue()? {
+                    let _offset_for_ref_frame = reader.read_se()?;
+                }
+            }
+            _ => {}
+        }
+        sps.max_num_ref_frames = reader.read_ue()?;
+        let _gaps_in_frame_num_value_allowed = reader.read_bit()?;
+        sps.pic_width_in_mbs_minus1 = reader.read_ue()?;
+        sps.pic_height_in_map_units_minus1 = reader.read_ue()?;
+        sps.frame_mbs_only = reader.read_bit()?;
+        if !sps.frame_mbs_only {
+            let _mb_adaptive_frame_field = reader.read_bit()?;
+        }
+        let _direct_8x8_inference = reader.read_bit()?;
+        if reader.read_bit()? {
+            sps.frame_cropping = Some(FrameCropping {
+                left: reader.read_ue()?,
+                right: reader.read_ue()?,
+                top: reader.read_ue()?,
+                bottom: reader.read_ue()?
+            });
+        }
+        if reader.read_bit()? {
+            sps.vui = Some(read_vui(&mut reader)?);
+        }
+        Ok(sps)
+    }
+
+    /// `ChromaArrayType`, 0 for monochrome or separately coded colour planes.
+    pub fn chroma_array_type(&self) -> u32 {
+        if self.separate_colour_plane { 0 } else { self.chroma_format_idc }
+    }
+
+    /// Displayed width in pixels, after cropping, saturated for out of range values.
+    pub fn width(&self) -> u32 {
+        let crop_unit_x = match self.chroma_array_type() {
+            1 | 2 => 2,
+            _ => 1
+        };
+        let crop = self.frame_cropping.map(|it| it.left.saturating_add(it.right)).unwrap_or_default();
+        self.pic_width_in_mbs_minus1.saturating_add(1).saturating_mul(16).saturating_sub(crop.saturating_mul(crop_unit_x))
+    }
+
+    /// Displayed height in pixels, after cropping, saturated for out of range values.
+    pub fn height(&self) -> u32 {
+        let field_factor = if self.frame_mbs_only { 1 } else { 2 };
+        let crop_unit_y = match self.chroma_array_type() {
+            1 => 2,
+            _ => 1
+        } * field_factor;
+        let crop = self.frame_cropping.map(|it| it.top.saturating_add(it.bottom)).unwrap_or_default();
+        self.pic_height_in_map_units_minus1.saturating_add(1).saturating_mul(16 * field_factor).saturating_sub(crop.saturating_mul(crop_unit_y))
+    }
+
+    /// Frames per second as signaled by the VUI timing info.
+    pub fn frame_rate(&self) -> Option<f64> {
+        let timing = self.vui?.timing?;
+        if timing.num_units_in_tick == 0 {
+            return None;
+        }
+        Some(timing.time_scale as f64 / (2.0 * timing.num_units_in_tick as f64))
+    }
+}
+
+fn skip_scaling_list(reader: &mut BitReader, size: usize) -> Result<(), MP4Error> {
+    let mut last_scale = 8;
+    let mut next_scale = 8;
+    for _ in 0..size {
+        if next_scale != 0 {
+            next_scale = (last_scale + reader.read_se()?.rem_euclid(256)) % 256;
+        }
+        if next_scale != 0 {
+            last_scale = next_scale;
+        }
+    }
+    Ok(())
+}
+
+fn read_vui(reader: &mut BitReader) -> Result<AvcVui, MP4Error> {
+    let mut vui = AvcVui::default();
+    if reader.read_bit()? {
+        vui.sample_aspect_ratio = match reader.read_u8()? {
+            255 => Some((reader.read_u16()?, reader.read_u16()?)),
+            idc @ 1..=16 => Some(ASPECT_RATIOS[idc as usize - 1]),
+            _ => None
+        };
+    }
+    if reader.read_bit()? {
+        let _overscan_appropriate = reader.read_bit()?;
+    }
+    if reader.read_bit()? {
+        let _video_format = reader.read_bits(3)?;
+        vui.video_full_range = reader.read_bit()?;
+        if reader.read_bit()? {
+            let _colour_primaries_transfer_characteristics_matrix_coefficients = reader.read_bits(24)?;
+        }
+    }
+    if reader.read_bit()? {
+        let _chroma_sample_loc_type_top_field = reader.read_ue()?;
+        let _chroma_sample_loc_type_bottom_field = reader.read_ue()?;
+    }
+    if reader.read_bit()? {
+        vui.timing = Some(AvcTiming {
+            num_units_in_tick: reader.read_bits(32)?,
+            time_scale: reader.read_bits(32)?,
+            fixed_frame_rate: reader.read_bit()?
+        });
+    }
+    Ok(vui)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::MP4Error;
+    use crate::nal::avc::{AvcSps, FrameCropping};
+
+    #[test]
+    fn test_parse_sps() -> Result<(), MP4Error> {
+        // High profile level 3.1, 1280x720 at 25 fps
+        let sps = AvcSps::parse(&[
+            0x67, 0x64, 0x00, 0x1f, 0xac, 0xd9, 0x40, 0x50, 0x05, 0xbb, 0x01, 0x10, 0x00, 0x00,
+            0x03, 0x00, 0x10, 0x00, 0x00, 0x03, 0x03, 0x28, 0x40
+        ])?;
+        assert_eq!(sps.profile_idc, 100);
+        assert_eq!(sps.level_idc, 31);
+        assert_eq!(sps.chroma_format_idc, 1);
+        assert_eq!(sps.bit_depth_luma_minus8, 0);
+        assert_eq!((sps.width(), sps.height()), (1280, 720));
+        assert_eq!(sps.vui.and_then(|it| it.sample_aspect_ratio), Some((1, 1)));
+        assert_eq!(sps.frame_rate(), Some(25.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_out_of_range_dimensions() {
+        let sps = AvcSps {
+            chroma_format_idc: 1,
+            pic_width_in_mbs_minus1: u32::MAX - 1,
+            pic_height_in_map_units_minus1: u32::MAX - 1,
+            frame_cropping: Some(FrameCropping { left: u32::MAX - 1, right: u32::MAX - 1, top: 0, bottom: 8 }),
+            ..Default::default()
+        };
+        assert_eq!(sps.width(), 0);
+        assert_eq!(sps.height(), u32::MAX - 32);
+    }
+
+}
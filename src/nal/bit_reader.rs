@@ -0,0 +1,91 @@
+use crate::error::MP4Error;
+
+/// Reads a NAL unit payload bit by bit, most significant bit first.
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub fn bits_left(&self) -> usize {
+        (self.data.len() * 8).saturating_sub(self.position)
+    }
+
+    pub fn read_bit(&mut self) -> Result<bool, MP4Error> {
+        let byte = self.data.get(self.position / 8)
+            .ok_or_else(|| MP4Error::Custom("Unexpected end of bitstream".to_string()))?;
+        let bit = byte >> (7 - self.position % 8) & 1 == 1;
+        self.position += 1;
+        Ok(bit)
+    }
+
+    /// Reads up to 32 bits as an unsigned integer.
+    pub fn read_bits(&mut self, count: u8) -> Result<u32, MP4Error> {
+        debug_assert!(count <= 32);
+        let mut value = 0u64;
+        for _ in 0..count {
+            value = value << 1 | self.read_bit()? as u64;
+        }
+        Ok(value as u32)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, MP4Error> {
+        Ok(self.read_bits(8)? as u8)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, MP4Error> {
+        Ok(self.read_bits(16)? as u16)
+    }
+
+    pub fn skip_bits(&mut self, count: usize) -> Result<(), MP4Error> {
+        if self.bits_left() < count {
+            return Err(MP4Error::Custom("Unexpected end of bitstream".to_string()));
+        }
+        self.position += count;
+        Ok(())
+    }
+
+    /// Unsigned Exp-Golomb code, `ue(v)`.
+    pub fn read_ue(&mut self) -> Result<u32, MP4Error> {
+        let mut leading_zeros = 0;
+        while !self.read_bit()? {
+            leading_zeros += 1;
+            if leading_zeros > 31 {
+                return Err(MP4Error::Custom("Exp-Golomb code is too long".to_string()));
+            }
+        }
+        Ok(((1u64 << leading_zeros) - 1 + self.read_bits(leading_zeros)? as u64) as u32)
+    }
+
+    /// Signed Exp-Golomb code, `se(v)`.
+    pub fn read_se(&mut self) -> Result<i32, MP4Error> {
+        let code = self.read_ue()? as i64;
+        Ok(if code & 1 == 1 { (code + 1) / 2 } else { -code / 2 } as i32)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::MP4Error;
+    use crate::nal::bit_reader::BitReader;
+
+    #[test]
+    fn test_exp_golomb() -> Result<(), MP4Error> {
+        // 1 | 010 | 011 | 00100 | 00101
+        let mut reader = BitReader::new(&[0b1010_0110, 0b0100_0010, 0b1000_0000]);
+        assert_eq!(reader.read_ue()?, 0);
+        assert_eq!(reader.read_ue()?, 1);
+        assert_eq!(reader.read_ue()?, 2);
+        assert_eq!(reader.read_se()?, 2);
+        assert_eq!(reader.read_se()?, -2);
+        assert_eq!(reader.bits_left(), 7);
+        assert!(reader.read_bits(8).is_err());
+        Ok(())
+    }
+
+}
@@ -0,0 +1,14 @@
+pub const NAL_VPS: u8 = 32;
+pub const NAL_SPS: u8 = 33;
+pub const NAL_PPS: u8 = 34;
+pub const NAL_AUD: u8 = 35;
+
+/// The `nal_unit_type` of a HEVC NAL unit from its two byte header.
+pub fn nal_unit_type(nal: &[u8]) -> Option<u8> {
+    nal.first().map(|it| it >> 1 & 0b11_1111)
+}
+
+/// Intra random access point pictures: BLA, IDR and CRA.
+pub fn is_irap(nal_unit_type: u8) -> bool {
+    (16..=23).contains(&nal_unit_type)
+}
@@ -0,0 +1,19 @@
+pub mod bit_reader;
+pub mod avc;
+pub mod hevc;
+pub mod annex_b;
+
+/// Removes the emulation prevention bytes (`00 00 03`) from a NAL unit to get its raw payload.
+pub fn unescape_rbsp(data: &[u8]) -> Vec<u8> {
+    let mut rbsp = Vec::with_capacity(data.len());
+    let mut zeros = 0;
+    for &byte in data {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        rbsp.push(byte);
+    }
+    rbsp
+}
@@ -0,0 +1,838 @@
+use std::io::SeekFrom;
+use std::time::Duration;
+use futures::{AsyncReadExt, AsyncSeekExt};
+use crate::bytes_read::ReadMp4;
+use crate::bytes_write::{Mp4Writable, WriteMp4};
+use crate::demux::Demuxer;
+use crate::error::MP4Error;
+use crate::header::BoxHeader;
+use crate::fragment::{Fragment, FragmentWriter};
+use crate::mp4box::box_trait::{BoxRead, BoxWrite, IBox};
+use crate::mp4box::co64::{Co64, StcoEntry as Co64Entry};
+use crate::mp4box::ctts::{Ctts, CttsEntry};
+use crate::mp4box::edts::Edts;
+use crate::mp4box::elst::{Elst, ElstEntry};
+use crate::mp4box::ftyp::FtypBox;
+use crate::mp4box::mdat::MdatBox;
+use crate::mp4box::mfhd::Mfhd;
+use crate::mp4box::moof::{Moof, MoofBox};
+use crate::mp4box::moov::{Moov, MoovBox};
+use crate::mp4box::mvex::Mvex;
+use crate::mp4box::stbl::Stbl;
+use crate::mp4box::stco::{Stco, StcoEntry};
+use crate::mp4box::stsc::{Stsc, StscEntry};
+use crate::mp4box::stss::Stss;
+use crate::mp4box::stsz::Stsz;
+use crate::mp4box::stts::{Stts, SttsEntry};
+use crate::mp4box::tfdt::Tfdt;
+use crate::mp4box::tfhd::Tfhd;
+use crate::mp4box::traf::{ResolvedSample, Traf, TrafBox};
+use crate::mp4box::trex::Trex;
+use crate::mp4box::trun::{Trun, TrunEntry};
+use crate::sample_table::{SampleTable, TableSample};
+use crate::types::array::Mp4VersionedOffsetArray;
+use crate::types::duration::{duration_from_ticks, ticks_from_duration, Mp4Duration};
+use crate::types::versioned_signed_int::VersionedSignedU32;
+
+struct RemuxTrack {
+    track_id: u32,
+    timescale: u32,
+    table: SampleTable,
+    trex: Trex,
+    /// next sample to put in a fragment
+    next: u32,
+}
+
+/// Turns a progressive `moov`/`mdat` file into a fragmented one.
+///
+/// The init segment is the source `moov` with empty sample tables and a `mvex`, the sample descriptions and edit lists are kept as is.
+/// Fragments are cut at the sync samples of the reference track, the first video track or else the first track,
+/// once they hold at least the target duration. The other tracks follow on the same boundaries.
+pub struct Remuxer<R: ReadMp4> {
+    reader: R,
+    moov: MoovBox,
+    tracks: Vec<RemuxTrack>,
+    reference: usize,
+    fragment_duration: Duration,
+    sequence_number: u32,
+}
+
+impl<R: ReadMp4> Remuxer<R> {
+
+    pub async fn new(reader: R, fragment_duration: Duration) -> Result<Self, MP4Error> {
+        let mut demuxer = Demuxer::new(reader);
+        let source = demuxer.read_moov().await?.clone();
+        let mut tracks = vec![];
+        for trak in &source.traks {
+            if let (Some(track_id), Some(stbl)) = (trak.track_id(), trak.stbl()) {
+                tracks.push(RemuxTrack {
+                    track_id,
+                    timescale: trak.timescale().filter(|it| *it > 0).unwrap_or(1000),
+                    table: SampleTable::new(stbl)?,
+                    trex: Trex {
+                        track_id,
+                        default_sample_description_index: 1,
+                        default_sample_duration: 0,
+                        default_sample_size: 0,
+                        default_sample_flags: Default::default()
+                    },
+                    next: 0
+                });
+            }
+        }
+        if tracks.is_empty() {
+            return Err(MP4Error::Custom("The file has no track with a sample table".to_string()));
+        }
+        let reference = tracks.iter()
+            .position(|track| source.trak(track.track_id).and_then(|it| it.handler_type()) == Some(*b"vide"))
+            .unwrap_or_default();
+        let moov = init_moov(&source, &tracks);
+        Ok(Self {
+            reader: demuxer.into_inner(),
+            moov,
+            tracks,
+            reference,
+            fragment_duration,
+            sequence_number: 0
+        })
+    }
+
+    pub fn ftyp(&self) -> FtypBox {
+        fragmented_ftyp()
+    }
+
+    /// The `moov` of the init segment.
+    pub fn moov(&self) -> &MoovBox {
+        &self.moov
+    }
+
+    /// Reads the samples of the next fragment, `None` once every sample was remuxed.
+    /// The `trun` data offsets are left for [`FragmentWriter::write_fragment`] to compute.
+    pub async fn next_fragment(&mut self) -> Result<Option<Fragment>, MP4Error> {
+        let reference = &self.tracks[self.reference];
+        let mut end = reference.next;
+        let end_time = match reference.table.sample(end) {
+            Some(first) => {
+                let target = first.decode_time + ticks_from_duration(self.fragment_duration, reference.timescale);
+                end += 1;
+                loop {
+                    match reference.table.sample(end) {
+                        Some(sample) if sample.sync && sample.decode_time >= target => break Some(sample.decode_time),
+                        Some(_) => end += 1,
+                        None => break None
+                    }
+                }
+            }
+            None => None
+        };
+        let reference_timescale = reference.timescale;
+
+        let mut trafs = vec![];
+        let mut data = vec![];
+        for (i, track) in self.tracks.iter_mut().enumerate() {
+            let mut samples = vec![];
+            while let Some(sample) = track.table.sample(track.next) {
+                let included = match end_time {
+                    _ if i == self.reference => sample.number < end,
+                    Some(end_time) => (sample.decode_time as u128) * (reference_timescale as u128) < (end_time as u128) * (track.timescale as u128),
+                    None => true
+                };
+                if !included {
+                    break;
+                }
+                samples.push(sample);
+                track.next += 1;
+            }
+            for run in samples.chunk_by(|a, b| a.sample_description_index == b.sample_description_index) {
+                for sample in run {
+                    if self.reader.seek(SeekFrom::Current(0)).await? != sample.offset {
+                        self.reader.seek(SeekFrom::Start(sample.offset)).await?;
+                    }
+                    let start = data.len();
+                    data.resize(start + sample.size as usize, 0);
+                    self.reader.read_exact(&mut data[start..]).await?;
+                }
+                let samples = run.iter().copied().map(ResolvedSample::from).collect::<Vec<_>>();
+                trafs.push(fragment_traf(&track.trex, run[0].sample_description_index, &samples));
+            }
+        }
+        if trafs.is_empty() {
+            return Ok(None);
+        }
+        self.sequence_number += 1;
+        let moof = Moof {
+            mfhd: Some(Mfhd { sequence_number: self.sequence_number }.into()),
+            trafs
+        };
+        Ok(Some(Fragment::new(moof.into(), MdatBox(data))))
+    }
+
+    /// Writes the init segment and every fragment.
+    pub async fn remux<W: WriteMp4>(&mut self, writer: &mut FragmentWriter<W>) -> Result<(), MP4Error> {
+        writer.write_init(&self.ftyp(), &self.moov)?;
+        while let Some(mut fragment) = self.next_fragment().await? {
+            writer.write_fragment(&mut fragment)?;
+        }
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// The `ftyp` of the fragmented files we write.
+pub(crate) fn fragmented_ftyp() -> FtypBox {
+    FtypBox {
+        major_brand: *b"iso6",
+        minor_version: 0,
+        compatible_brands: vec![*b"iso6", *b"mp41"]
+    }
+}
+
+/// The source `moov` with its sample tables emptied, except for the sample descriptions and groups, and a `trex` per track.
+fn init_moov(source: &Moov, tracks: &[RemuxTrack]) -> MoovBox {
+    let mut moov = source.clone();
+    for trak in &mut moov.traks {
+        let stbl = match trak.mdia.as_mut().and_then(|it| it.minf.as_mut()).and_then(|it| it.stbl.as_mut()) {
+            Some(stbl) => stbl,
+            None => continue
+        };
+        **stbl = Stbl {
+            co64: None,
+            stco: Some(Stco { entries: vec![].into() }.into()),
+            stsc: Some(Stsc { entries: vec![].into() }.into()),
+            stsd: stbl.stsd.take(),
+            stss: None,
+            stsz: Some(Stsz::Simple { sample_size: 0, sample_count: 0 }.into()),
+            stz2: None,
+            stts: Some(Stts { samples: vec![].into() }.into()),
+            ctts: None,
+            sdtp: None,
+            sbgps: vec![],
+            sgpds: std::mem::take(&mut stbl.sgpds)
+        };
+    }
+    moov.mvex = Some(Mvex {
+        mehd: None,
+        trex: tracks.iter().map(|it| it.trex.clone().into()).collect(),
+        trep: vec![]
+    }.into());
+    moov.into()
+}
+
+/// A `traf` holding samples that share a sample description, starting at the decode time of the first one.
+/// The per-sample fields are compacted against `trex`.
+pub(crate) fn fragment_traf(trex: &Trex, sample_description_index: u32, samples: &[ResolvedSample]) -> TrafBox {
+    let has_composition = samples.iter().any(|it| it.composition_offset != 0);
+    let entries = samples.iter().map(|sample| TrunEntry {
+        sample_duration: sample.duration.into(),
+        sample_size: sample.size.into(),
+        sample_flags: sample.flags.into(),
+        sample_composition_time_offset: match sample.composition_offset.clamp(i32::MIN as i64, i32::MAX as i64) {
+            _ if !has_composition => None,
+            offset if offset < 0 => Some(VersionedSignedU32::Signed(offset as i32)),
+            offset => Some(VersionedSignedU32::Unsigned(offset as u32))
+        }.into()
+    }).collect();
+    let mut traf = Traf {
+        tfhd: Some(Tfhd {
+            track_id: trex.track_id,
+            base_data_offset: Default::default(),
+            sample_description_index: Some(sample_description_index)
+                .filter(|it| *it != trex.default_sample_description_index)
+                .into(),
+            default_sample_duration: Default::default(),
+            default_sample_size: Default::default(),
+            default_sample_flags: Default::default(),
+            flags: Default::default()
+        }.into()),
+        tfdt: Some(Tfdt { base_media_decode_time: samples.first().and_then(|it| it.decode_time).unwrap_or_default().into() }.into()),
+        sdtp: None,
+        sbgps: vec![],
+        sgpds: vec![],
+        truns: vec![Trun { entries: Mp4VersionedOffsetArray::new(entries, Default::default()) }.into()]
+    };
+    traf.compact(Some(trex));
+    traf.into()
+}
+
+/// A run of samples of a track that is stored contiguously in a progressive output, the data of a `traf` once defragmented.
+pub(crate) struct ProgressiveChunk {
+    /// the input the data is read from
+    pub(crate) input: usize,
+    pub(crate) track: usize,
+    pub(crate) first_sample: usize,
+    pub(crate) sample_count: usize,
+    pub(crate) sample_description_index: u32,
+}
+
+/// The samples of a track of a progressive output, their offsets are positions in the input of their chunk.
+pub(crate) struct ProgressiveTrack {
+    pub(crate) track_id: u32,
+    pub(crate) samples: Vec<ResolvedSample>,
+}
+
+/// Splits the samples of a track into chunks of at most a second that share a sample description,
+/// with the decode time they start at to interleave them with the chunks of the other tracks.
+pub(crate) fn interleaved_chunks(input: usize, track: usize, timescale: u32, samples: &[TableSample]) -> Vec<(Option<Duration>, ProgressiveChunk)> {
+    let mut chunks = vec![];
+    let mut chunk: Option<(u64, ProgressiveChunk)> = None;
+    for (i, sample) in samples.iter().enumerate() {
+        match &mut chunk {
+            Some((start, chunk)) if chunk.sample_description_index == sample.sample_description_index
+                && sample.decode_time < *start + timescale as u64 => chunk.sample_count += 1,
+            _ => {
+                let next = ProgressiveChunk {
+                    input,
+                    track,
+                    first_sample: i,
+                    sample_count: 1,
+                    sample_description_index: sample.sample_description_index
+                };
+                if let Some((start, chunk)) = chunk.replace((sample.decode_time, next)) {
+                    chunks.push((duration_from_ticks(start, timescale), chunk));
+                }
+            }
+        }
+    }
+    if let Some((start, chunk)) = chunk {
+        chunks.push((duration_from_ticks(start, timescale), chunk));
+    }
+    chunks
+}
+
+/// Reads the `moov` and the `moof` boxes with their positions from the top level boxes of a file.
+pub(crate) async fn read_boxes<R: ReadMp4>(demuxer: &mut Demuxer<R>) -> Result<(MoovBox, Vec<(u64, MoofBox)>), MP4Error> {
+    let mut moov = None;
+    let mut moofs = vec![];
+    let mut next = Some(0);
+    while let Some((header, following)) = match next {
+        Some(position) => demuxer.top_level_header(position).await?,
+        None => None
+    } {
+        match header.id {
+            MoovBox::ID => moov = Some(MoovBox::read(header, demuxer.get_mut()).await?),
+            MoofBox::ID => moofs.push((next.unwrap_or_default(), MoofBox::read(header, demuxer.get_mut()).await?)),
+            _ => {}
+        }
+        next = following;
+    }
+    let moov = moov.ok_or_else(|| MP4Error::Custom("The file has no moov".to_string()))?;
+    Ok((moov, moofs))
+}
+
+/// Turns a fragmented file into a progressive one: `ftyp`, a single `moov` and a single `mdat` holding the samples of every `moof`.
+///
+/// Samples are resolved through the `tfhd`/`trex` defaults, every `traf` becomes a chunk and the `mvex` is dropped.
+/// A gap between the `tfdt` of a fragment and the end of the previous one lengthens the last sample before it.
+/// The tracks are rebased on the earliest first `tfdt`, a track starting later is delayed by an empty edit, see [`rebase_edits`]. With `allow_stz2`, the sample sizes are written in a `stz2` when it is smaller than a `stsz`.
+/// Returns the number of bytes written.
+pub async fn defragment<R: ReadMp4, W: WriteMp4>(reader: R, allow_stz2: bool, writer: &mut W) -> Result<usize, MP4Error> {
+    let mut demuxer = Demuxer::new(reader);
+    let (mut source, moofs) = read_boxes(&mut demuxer).await?;
+
+    let mut tracks = source.traks.iter()
+        .map(|it| ProgressiveTrack { track_id: it.track_id().unwrap_or_default(), samples: vec![] })
+        .collect::<Vec<_>>();
+    // decode time the next fragment of each track should start at
+    let mut ends = vec![0u64; tracks.len()];
+    let mut firsts = vec![None; tracks.len()];
+    let mut chunks = vec![];
+    for (position, moof) in &moofs {
+        for (traf, samples) in moof.trafs.iter().zip(moof.samples(*position, Some(&source))) {
+            let track_id = traf.track_id();
+            let index = match tracks.iter().position(|it| Some(it.track_id) == track_id) {
+                Some(index) => index,
+                None => continue
+            };
+            if samples.is_empty() {
+                continue;
+            }
+            let track = &mut tracks[index];
+            let end = &mut ends[index];
+            let start = samples[0].decode_time.unwrap_or(*end);
+            firsts[index].get_or_insert(start);
+            if let Some(last) = track.samples.last_mut().filter(|_| start > *end) {
+                last.duration = last.duration.saturating_add((start - *end).min(u32::MAX as u64) as u32);
+            }
+            *end = start + samples.iter().map(|it| it.duration as u64).sum::<u64>();
+            let sample_description_index = traf.tfhd.as_ref().and_then(|it| *it.sample_description_index)
+                .or(track_id.and_then(|id| source.trex(id)).map(|it| it.default_sample_description_index))
+                .unwrap_or(1);
+            chunks.push(ProgressiveChunk {
+                input: 0,
+                track: index,
+                first_sample: track.samples.len(),
+                sample_count: samples.len(),
+                sample_description_index
+            });
+            track.samples.extend(samples);
+        }
+    }
+    rebase_edits(&mut source, &firsts);
+    write_progressive(std::slice::from_mut(demuxer.get_mut()), &source, &tracks, &chunks, allow_stz2, writer).await
+}
+
+/// Keeps the offsets between the first decode times of the tracks once their samples start at 0:
+/// the earliest track starts the movie and the others are delayed by an empty edit of the difference.
+/// Edits past the first decode time of a track are moved back by it, earlier ones are kept as written against a media starting at 0.
+fn rebase_edits(moov: &mut Moov, firsts: &[Option<u64>]) {
+    let movie_timescale = moov.mvhd.as_ref().map(|it| it.timescale).unwrap_or(1000);
+    let starts = moov.traks.iter().zip(firsts).map(|(trak, first)| {
+        let timescale = trak.timescale().filter(|it| *it > 0)?;
+        Some((*first)? as u128 * movie_timescale as u128 / timescale as u128)
+    }).collect::<Vec<_>>();
+    let earliest = starts.iter().flatten().copied().min().unwrap_or_default();
+    for ((trak, first), start) in moov.traks.iter_mut().zip(firsts).zip(starts) {
+        let (first, delay) = match (first, start) {
+            (Some(first), Some(start)) => (*first, u64::try_from(start - earliest).unwrap_or(u64::MAX)),
+            _ => continue
+        };
+        if trak.elst().is_none() && delay == 0 {
+            continue;
+        }
+        let mut entries = trak.elst().map(|it| it.entries.clone()).unwrap_or_else(|| vec![ElstEntry::default()]);
+        for entry in entries.iter_mut().filter(|it| !it.is_empty()) {
+            if let Some(media_time) = u64::try_from(entry.media_time).ok().and_then(|it| it.checked_sub(first)) {
+                entry.media_time = media_time as i64;
+            }
+        }
+        if delay > 0 {
+            entries.insert(0, ElstEntry { segment_duration: delay, media_time: -1, ..Default::default() });
+        }
+        trak.edts = Some(Edts { elst: Some(Elst { entries }.into()) }.into());
+    }
+}
+
+/// Writes a progressive file with the `moov` of `source` and the samples of `tracks`, which match its `trak`s,
+/// laid out in a single `mdat` in the order of `chunks`, see [`Stbl::set_sample_sizes`] for `allow_stz2`.
+/// Returns the number of bytes written.
+pub(crate) async fn write_progressive<R: ReadMp4, W: WriteMp4>(
+    inputs: &mut [R],
+    source: &Moov,
+    tracks: &[ProgressiveTrack],
+    chunks: &[ProgressiveChunk],
+    allow_stz2: bool,
+    writer: &mut W
+) -> Result<usize, MP4Error> {
+    let ftyp = FtypBox {
+        major_brand: *b"isom",
+        minor_version: 512,
+        compatible_brands: vec![*b"isom", *b"iso2", *b"mp41"]
+    };
+    let data_size = tracks.iter().flat_map(|it| it.samples.iter()).map(|it| it.size as u64).sum::<u64>();
+    let mdat_header = BoxHeader::from_id_and_inner_size(MdatBox::ID, data_size as usize);
+    let mut offsets = vec![0u64; chunks.len()];
+    let moov = loop {
+        let moov = progressive_moov(source, tracks, chunks, &offsets, allow_stz2);
+        let mut offset = (ftyp.byte_size() + moov.byte_size() + mdat_header.byte_size()) as u64;
+        let mut changed = false;
+        for (chunk, chunk_offset) in chunks.iter().zip(offsets.iter_mut()) {
+            changed |= *chunk_offset != offset;
+            *chunk_offset = offset;
+            offset += tracks[chunk.track].samples[chunk.first_sample..][..chunk.sample_count].iter()
+                .map(|it| it.size as u64)
+                .sum::<u64>();
+        }
+        // the offsets only change the size of the moov when they no longer fit a stco
+        if !changed {
+            break moov;
+        }
+    };
+
+    let mut count = 0;
+    count += ftyp.write(writer)?;
+    count += moov.write(writer)?;
+    count += mdat_header.write(writer)?;
+    let mut data = vec![];
+    for chunk in chunks {
+        let reader = inputs.get_mut(chunk.input)
+            .ok_or_else(|| MP4Error::Custom(format!("No input {}", chunk.input)))?;
+        for sample in &tracks[chunk.track].samples[chunk.first_sample..][..chunk.sample_count] {
+            reader.seek(SeekFrom::Start(sample.offset)).await?;
+            data.resize(sample.size as usize, 0);
+            reader.read_exact(&mut data).await?;
+            count += writer.write(&data)?;
+        }
+    }
+    Ok(count)
+}
+
+/// The source `moov` without `mvex`, with sample tables and durations built from the samples of `tracks`.
+fn progressive_moov(source: &Moov, tracks: &[ProgressiveTrack], chunks: &[ProgressiveChunk], offsets: &[u64], allow_stz2: bool) -> MoovBox {
+    let mut moov = source.clone();
+    moov.mvex = None;
+    let movie_timescale = moov.mvhd.as_ref().map(|it| it.timescale).unwrap_or(1000);
+    let mut movie_duration = 0;
+    for (index, (trak, track)) in moov.traks.iter_mut().zip(tracks).enumerate() {
+        let media_duration = track.samples.iter().map(|it| it.duration as u64).sum::<u64>();
+        let timescale = trak.timescale().filter(|it| *it > 0).unwrap_or(1000);
+        let to_movie = |it: u64| (it as u128 * movie_timescale as u128 / timescale as u128) as u64;
+        let track_chunks = chunks.iter().zip(offsets).filter(|(chunk, _)| chunk.track == index).collect::<Vec<_>>();
+        let mdia = match trak.mdia.as_mut() {
+            Some(mdia) => mdia,
+            None => continue
+        };
+        if let Some(mdhd) = &mut mdia.mdhd {
+            mdhd.duration = Mp4Duration(Some(media_duration));
+        }
+        if let Some(stbl) = mdia.minf.as_mut().and_then(|it| it.stbl.as_mut()) {
+            **stbl = progressive_stbl(stbl, &track.samples, &track_chunks, allow_stz2);
+        }
+        let mut duration = to_movie(media_duration);
+        if let Some(elst) = trak.edts.as_mut().and_then(|it| it.elst.as_mut()) {
+            for entry in elst.entries.iter_mut().filter(|it| it.segment_duration == 0 && !it.is_empty()) {
+                entry.segment_duration = to_movie(media_duration.saturating_sub(entry.media_time.max(0) as u64));
+            }
+            duration = elst.entries.iter().map(|it| it.segment_duration).sum();
+        }
+        if let Some(tkhd) = &mut trak.tkhd {
+            tkhd.duration = Mp4Duration(Some(duration));
+        }
+        movie_duration = movie_duration.max(duration);
+    }
+    if let Some(mvhd) = &mut moov.mvhd {
+        mvhd.duration = Mp4Duration(Some(movie_duration));
+    }
+    moov.into()
+}
+
+/// Sample tables of a track, keeping the sample descriptions and group descriptions of `source`.
+fn progressive_stbl(source: &mut Stbl, samples: &[ResolvedSample], chunks: &[(&ProgressiveChunk, &u64)], allow_stz2: bool) -> Stbl {
+    let mut stts: Vec<SttsEntry> = vec![];
+    for sample in samples {
+        match stts.last_mut() {
+            Some(last) if last.sample_delta == sample.duration => last.sample_count += 1,
+            _ => stts.push(SttsEntry { sample_count: 1, sample_delta: sample.duration })
+        }
+    }
+    let mut ctts: Vec<CttsEntry> = vec![];
+    for sample in samples {
+        let sample_offset = sample.composition_offset.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        match ctts.last_mut() {
+            Some(last) if last.sample_offset == sample_offset => last.sample_count += 1,
+            _ => ctts.push(CttsEntry { sample_count: 1, sample_offset })
+        }
+    }
+    let mut stsc: Vec<StscEntry> = vec![];
+    for (i, (chunk, _)) in chunks.iter().enumerate() {
+        match stsc.last() {
+            Some(last) if last.samples_per_chunk == chunk.sample_count as u32 && last.sample_description_index == chunk.sample_description_index => {}
+            _ => stsc.push(StscEntry {
+                first_chunk: i as u32 + 1,
+                samples_per_chunk: chunk.sample_count as u32,
+                sample_description_index: chunk.sample_description_index
+            })
+        }
+    }
+    let large = chunks.iter().any(|(_, offset)| **offset > u32::MAX as u64);
+    let mut stbl = Stbl {
+        co64: Some(Co64 {
+            entries: chunks.iter().map(|(_, offset)| Co64Entry { chunk_offset: **offset }).collect::<Vec<_>>().into()
+        }.into()).filter(|_| large),
+        stco: Some(Stco {
+            entries: chunks.iter().map(|(_, offset)| StcoEntry { chunk_offset: **offset as u32 }).collect::<Vec<_>>().into()
+        }.into()).filter(|_| !large),
+        stsc: Some(Stsc { entries: stsc.into() }.into()),
+        stsd: source.stsd.take(),
+        stss: Some(Stss {
+            sample_numbers: samples.iter().enumerate()
+                .filter(|(_, it)| it.flags.is_sync())
+                .map(|(i, _)| i as u32 + 1)
+                .collect::<Vec<_>>()
+                .into()
+        }.into()).filter(|_| samples.iter().any(|it| !it.flags.is_sync())),
+        stsz: None,
+        stz2: None,
+        stts: Some(Stts { samples: stts.into() }.into()),
+        ctts: Some(Ctts { entries: ctts }.into()).filter(|_| samples.iter().any(|it| it.composition_offset != 0)),
+        sdtp: None,
+        sbgps: vec![],
+        sgpds: std::mem::take(&mut source.sgpds)
+    };
+    stbl.set_sample_sizes(&samples.iter().map(|it| it.size).collect::<Vec<_>>(), allow_stz2);
+    stbl
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::time::Duration;
+    use crate::demux::{Demuxer, Mp4Event};
+    use crate::error::MP4Error;
+    use crate::fragment::{Fragment, FragmentWriter};
+    use crate::mp4box::box_trait::{BoxWrite, IBox};
+    use crate::mp4box::ctts::{Ctts, CttsEntry};
+    use crate::mp4box::edts::Edts;
+    use crate::mp4box::elst::{Elst, ElstEntry};
+    use crate::mp4box::ftyp::Ftyp;
+    use crate::mp4box::hdlr::Hdlr;
+    use crate::mp4box::mdat::MdatBox;
+    use crate::mp4box::mdhd::Mdhd;
+    use crate::mp4box::mdia::Mdia;
+    use crate::mp4box::minf::Minf;
+    use crate::mp4box::moov::{Moov, MoovBox};
+    use crate::mp4box::stbl::Stbl;
+    use crate::mp4box::stco::{Stco, StcoEntry};
+    use crate::mp4box::stsc::{Stsc, StscEntry};
+    use crate::mp4box::stss::Stss;
+    use crate::mp4box::stts::{Stts, SttsEntry};
+    use crate::mp4box::tkhd::Tkhd;
+    use crate::mp4box::trak::Trak;
+    use crate::remux::{defragment, Remuxer};
+    use crate::sample_table::SampleTable;
+
+    fn trak(track_id: u32, timescale: u32, mut stbl: Stbl, sizes: &[u32]) -> Trak {
+        stbl.set_sample_sizes(sizes, false);
+        Trak {
+            tkhd: Some(Tkhd { track_id, ..Default::default() }.into()),
+            edts: None,
+            mdia: Some(Mdia {
+                mdhd: Some(Mdhd { timescale, ..Default::default() }.into()),
+                hdlr: None,
+                minf: Some(Minf {
+                    vmhd: None,
+                    smhd: None,
+                    dinf: None,
+                    stbl: Some(stbl.into())
+                }.into())
+            }.into())
+        }
+    }
+
+    fn stbl(chunk_offset: u32, sample_count: u32, sample_delta: u32) -> Stbl {
+        Stbl {
+            co64: None,
+            stco: Some(Stco { entries: vec![StcoEntry { chunk_offset }].into() }.into()),
+            stsc: Some(Stsc { entries: vec![StscEntry { first_chunk: 1, samples_per_chunk: sample_count, sample_description_index: 1 }].into() }.into()),
+            stsd: None,
+            stss: None,
+            stsz: None,
+            stz2: None,
+            stts: Some(Stts { samples: vec![SttsEntry { sample_count, sample_delta }].into() }.into()),
+            ctts: None,
+            sdtp: None,
+            sbgps: vec![],
+            sgpds: vec![]
+        }
+    }
+
+    /// 6 video samples of 1s with sync samples at 0s and 3s, then 12 audio samples of 0.5s
+    pub(crate) fn progressive() -> Result<Vec<u8>, MP4Error> {
+        let ftyp = Ftyp { major_brand: *b"isom", minor_version: 0, compatible_brands: vec![*b"isom"] };
+        let mdat = MdatBox((0..6u8).flat_map(|it| [it; 4]).chain((0..12u8).flat_map(|it| [100 + it; 2])).collect());
+        let base = ftyp.byte_size() as u32 + 8;
+        let mut video = stbl(base, 6, 1000);
+        video.stss = Some(Stss { sample_numbers: vec![1, 4].into() }.into());
+        video.ctts = Some(Ctts {
+            entries: vec![
+                CttsEntry { sample_count: 1, sample_offset: 1000 },
+                CttsEntry { sample_count: 1, sample_offset: -1000 },
+                CttsEntry { sample_count: 4, sample_offset: 0 },
+            ]
+        }.into());
+        let mut video = trak(1, 1000, video, &[4; 6]);
+        video.edts = Some(Edts {
+            elst: Some(Elst { entries: vec![ElstEntry { segment_duration: 6000, media_time: 1000, ..Default::default() }] }.into())
+        }.into());
+        let moov = Moov {
+            mvhd: Some(Default::default()),
+            traks: vec![video.into(), trak(2, 2000, stbl(base + 24, 12, 1000), &[2; 12]).into()],
+            mvex: None
+        };
+        let mut buf = vec![];
+        ftyp.write(&mut buf)?;
+        mdat.write(&mut buf)?;
+        MoovBox::from(moov).write(&mut buf)?;
+        Ok(buf)
+    }
+
+    #[test]
+    pub fn test_remux() -> Result<(), MP4Error> {
+        let buf = progressive()?;
+        futures::executor::block_on(async {
+            let mut remuxer = Remuxer::new(futures::io::Cursor::new(buf), Duration::from_secs(2)).await?;
+            let init = remuxer.moov().clone();
+            assert_eq!(init.trex(2).map(|it| it.track_id), Some(2));
+            assert!(init.trak(1).and_then(|it| it.elst()).is_some());
+            assert_eq!(init.trak(1).and_then(|it| it.stbl()).and_then(|it| it.sample_count()), Some(0));
+            let mut writer = FragmentWriter::new(vec![]);
+            remuxer.remux(&mut writer).await?;
+
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(writer.into_inner()));
+            let mut moofs = vec![];
+            while let Some(event) = demuxer.next().await? {
+                match event {
+                    Mp4Event::Moof(moof) => moofs.push(moof),
+                    Mp4Event::Mdat(mdat) => {
+                        let moof = moofs.last().unwrap();
+                        let start = moof.byte_size() as u64 + 8;
+                        let samples = moof.samples(0, demuxer.moov().map(|it| &**it));
+                        let tracks = samples.iter().map(|samples| samples.iter().map(|it| (
+                            it.decode_time.unwrap(),
+                            it.composition_offset,
+                            it.flags.is_sync(),
+                            mdat.0[(it.offset - start) as usize]
+                        )).collect::<Vec<_>>()).collect::<Vec<_>>();
+                        match moofs.len() {
+                            1 => {
+                                assert_eq!(tracks[0], [(0, 1000, true, 0), (1000, -1000, false, 1), (2000, 0, false, 2)]);
+                                assert_eq!(tracks[1].iter().map(|it| it.3).collect::<Vec<_>>(), (100..106).collect::<Vec<_>>());
+                            }
+                            2 => {
+                                assert_eq!(tracks[0].first(), Some(&(3000, 0, true, 3)));
+                                assert_eq!(tracks[0].len(), 3);
+                                assert_eq!(tracks[1].first().map(|it| (it.0, it.3)), Some((6000, 106)));
+                                assert_eq!(tracks[1].len(), 6);
+                            }
+                            _ => unreachable!()
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            assert_eq!(moofs.len(), 2);
+            assert_eq!(moofs[1].mfhd.as_ref().map(|it| it.sequence_number), Some(2));
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_remux_reference() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let mut buf = progressive()?;
+            let mut moov = Demuxer::new(futures::io::Cursor::new(buf.clone())).read_moov().await?.clone();
+            buf.truncate(buf.len() - moov.byte_size());
+            let data = buf.clone();
+            if let Some(mdia) = moov.traks[0].mdia.as_mut() {
+                mdia.hdlr = Some(Hdlr { handler_type: *b"vide", ..Default::default() }.into());
+            }
+            // the audio, an unusable copy of it without tkhd, then the video
+            moov.traks.reverse();
+            let mut unusable = moov.traks[0].clone();
+            unusable.tkhd = None;
+            moov.traks.insert(0, unusable);
+            moov.write(&mut buf)?;
+            let mut remuxer = Remuxer::new(futures::io::Cursor::new(buf), Duration::from_secs(2)).await?;
+            let fragment = remuxer.next_fragment().await?.unwrap();
+            // cut on the video sync sample at 3s
+            let samples = fragment.moof.samples(0, Some(remuxer.moov()));
+            let counts = fragment.moof.trafs.iter().zip(samples).map(|(traf, samples)| (traf.track_id().unwrap(), samples.len())).collect::<Vec<_>>();
+            assert_eq!(counts, [(2, 6), (1, 3)]);
+
+            let mut buf = data;
+            moov.traks.clear();
+            moov.write(&mut buf)?;
+            assert!(Remuxer::new(futures::io::Cursor::new(buf), Duration::from_secs(2)).await.is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_defragment() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let source = progressive()?;
+            let mut remuxer = Remuxer::new(futures::io::Cursor::new(source.clone()), Duration::from_secs(2)).await?;
+            let mut writer = FragmentWriter::new(vec![]);
+            remuxer.remux(&mut writer).await?;
+            let mut buf = vec![];
+            defragment(futures::io::Cursor::new(writer.into_inner()), false, &mut buf).await?;
+
+            let mut original = Demuxer::new(futures::io::Cursor::new(source));
+            let mut defragmented = Demuxer::new(futures::io::Cursor::new(buf));
+            let moov = defragmented.read_moov().await?.clone();
+            assert!(moov.mvex.is_none());
+            assert_eq!(moov.mvhd.as_ref().map(|it| it.duration.0), Some(Some(6000)));
+            assert_eq!(moov.trak(1).and_then(|it| it.tkhd.as_ref()).map(|it| it.duration.0), Some(Some(6000)));
+            assert_eq!(moov.trak(2).and_then(|it| it.mdia.as_ref()?.mdhd.as_ref()).map(|it| it.duration.0), Some(Some(12000)));
+            let original_table = SampleTable::new(original.read_moov().await?.trak(1).and_then(|it| it.stbl()).unwrap())?;
+            let table = SampleTable::new(moov.trak(1).and_then(|it| it.stbl()).unwrap())?;
+            for (a, b) in original_table.samples().zip(table.samples()) {
+                assert_eq!((a.decode_time, a.duration, a.size, a.sync, a.composition_offset), (b.decode_time, b.duration, b.size, b.sync, b.composition_offset));
+            }
+
+            let mut samples = vec![];
+            while let Some(sample) = defragmented.next_sample().await? {
+                samples.push(sample);
+            }
+            samples.sort_by_key(|it| (it.track_id, it.number));
+            let mut expected = vec![];
+            while let Some(sample) = original.next_sample().await? {
+                expected.push(sample);
+            }
+            assert_eq!(samples, expected);
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_defragment_stz2() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let mut source = progressive()?;
+            let mut moov = Demuxer::new(futures::io::Cursor::new(source.clone())).read_moov().await?.clone();
+            source.truncate(source.len() - moov.byte_size());
+            let sizes = [2, 6, 4, 4, 3, 5];
+            moov.traks[0].mdia.as_mut().and_then(|it| it.minf.as_mut()).and_then(|it| it.stbl.as_mut()).unwrap().set_sample_sizes(&sizes, false);
+            moov.write(&mut source)?;
+            let mut remuxer = Remuxer::new(futures::io::Cursor::new(source), Duration::from_secs(2)).await?;
+            let mut writer = FragmentWriter::new(vec![]);
+            remuxer.remux(&mut writer).await?;
+            let fragmented = writer.into_inner();
+            for allow_stz2 in [false, true] {
+                let mut buf = vec![];
+                defragment(futures::io::Cursor::new(fragmented.clone()), allow_stz2, &mut buf).await?;
+                let moov = Demuxer::new(futures::io::Cursor::new(buf)).read_moov().await?.clone();
+                let stbl = moov.trak(1).and_then(|it| it.stbl()).unwrap();
+                assert_eq!((stbl.stsz.is_some(), stbl.stz2.is_some()), (!allow_stz2, allow_stz2));
+                assert_eq!((0..6).map(|it| stbl.sample_size(it).unwrap()).collect::<Vec<_>>(), sizes);
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_defragment_initial_decode_times() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let mut remuxer = Remuxer::new(futures::io::Cursor::new(progressive()?), Duration::from_secs(2)).await?;
+            let mut writer = FragmentWriter::new(vec![]);
+            remuxer.remux(&mut writer).await?;
+            // a live recording: the video starts at 5s and the audio at 2s
+            let mut demuxer = Demuxer::new(futures::io::Cursor::new(writer.into_inner()));
+            let mut writer = FragmentWriter::new(vec![]);
+            let mut ftyp = None;
+            let mut moof = None;
+            while let Some(event) = demuxer.next().await? {
+                match event {
+                    Mp4Event::Ftyp(it) => ftyp = Some(it),
+                    Mp4Event::Moov(moov) => { writer.write_init(ftyp.as_ref().unwrap(), &moov)?; }
+                    Mp4Event::Moof(it) => moof = Some(it),
+                    Mp4Event::Mdat(mdat) => {
+                        let mut moof = moof.take().unwrap();
+                        for traf in &mut moof.trafs {
+                            let shift = if traf.track_id() == Some(1) { 5000 } else { 4000 };
+                            let tfdt = traf.tfdt.as_mut().unwrap();
+                            tfdt.base_media_decode_time = (*tfdt.base_media_decode_time + shift).into();
+                        }
+                        writer.write_fragment(&mut Fragment::new(moof, mdat))?;
+                    }
+                    _ => {}
+                }
+            }
+            let mut buf = vec![];
+            defragment(futures::io::Cursor::new(writer.into_inner()), false, &mut buf).await?;
+
+            let moov = Demuxer::new(futures::io::Cursor::new(buf)).read_moov().await?.clone();
+            let edits = |track_id: u32| moov.trak(track_id).and_then(|it| it.elst())
+                .map(|it| it.entries.iter().map(|it| (it.segment_duration, it.media_time)).collect::<Vec<_>>());
+            assert_eq!(edits(1), Some(vec![(3000, -1), (6000, 1000)]));
+            assert_eq!(edits(2), None);
+            for track_id in [1, 2] {
+                let table = SampleTable::new(moov.trak(track_id).and_then(|it| it.stbl()).unwrap())?;
+                assert_eq!(table.sample(0).map(|it| it.decode_time), Some(0));
+            }
+            assert_eq!(moov.mvhd.as_ref().map(|it| it.duration.0), Some(Some(9000)));
+            Ok(())
+        })
+    }
+
+}
@@ -0,0 +1,416 @@
+use crate::error::MP4Error;
+use crate::mp4box::stbl::Stbl;
+use crate::mp4box::stss::Stss;
+use crate::mp4box::stsz::Stsz;
+use crate::mp4box::traf::ResolvedSample;
+use crate::mp4box::trex::SampleFlags;
+
+/// A sample of a progressive track resolved from the sample table boxes, times are in the track timescale.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct TableSample {
+    /// 0-based sample number
+    pub number: u32,
+    /// position of the sample data in the file
+    pub offset: u64,
+    pub size: u32,
+    pub decode_time: u64,
+    pub duration: u32,
+    pub sample_description_index: u32,
+    /// composition time minus decode time, from the `ctts`
+    pub composition_offset: i32,
+    pub sync: bool,
+}
+
+impl From<TableSample> for ResolvedSample {
+    fn from(sample: TableSample) -> Self {
+        Self {
+            duration: sample.duration,
+            size: sample.size,
+            flags: if sample.sync { SampleFlags::sync() } else { SampleFlags::non_sync_depends() },
+            composition_offset: sample.composition_offset as i64,
+            offset: sample.offset,
+            decode_time: Some(sample.decode_time)
+        }
+    }
+}
+
+/// A `stts` entry with the sample number and decode time it starts at.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct TimeRun {
+    first_sample: u32,
+    first_time: u64,
+    sample_count: u32,
+    sample_delta: u32,
+}
+
+/// A `ctts` entry with the sample number it starts at.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct CompositionRun {
+    first_sample: u32,
+    sample_count: u32,
+    sample_offset: i32,
+}
+
+/// A `stsc` entry with the sample number it starts at, chunks are 0-based.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct ChunkRun {
+    first_chunk: u32,
+    first_sample: u32,
+    samples_per_chunk: u32,
+    sample_description_index: u32,
+}
+
+/// Number of samples between two of the sums kept along the sample sizes.
+const PREFIX_INTERVAL: usize = 64;
+
+/// The sample sizes as stored in the `stsz` or the `stz2`.
+/// `prefixes[i]` is the sum of the sizes of the samples before `i * PREFIX_INTERVAL`, so any sum adds at most `PREFIX_INTERVAL` sizes.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+enum SampleSizes {
+    Constant(u32),
+    Stsz { sizes: Vec<u32>, prefixes: Vec<u64> },
+    Stz2 { sizes: Vec<u16>, prefixes: Vec<u64> },
+}
+
+fn prefixes<T: Copy + Into<u64>>(sizes: &[T]) -> Vec<u64> {
+    let mut prefixes = vec![0];
+    let mut sum = 0u64;
+    for chunk in sizes.chunks(PREFIX_INTERVAL) {
+        sum += chunk.iter().map(|it| (*it).into()).sum::<u64>();
+        prefixes.push(sum);
+    }
+    prefixes
+}
+
+/// Sum of the sizes of the samples before `number`, which is at most the sample count.
+fn size_before<T: Copy + Into<u64>>(sizes: &[T], prefixes: &[u64], number: usize) -> u64 {
+    let index = number / PREFIX_INTERVAL;
+    prefixes[index] + sizes[index * PREFIX_INTERVAL..number].iter().map(|it| (*it).into()).sum::<u64>()
+}
+
+/// Random access over the samples of a `stbl`, built from `stts`, `stsc`, `stsz`/`stz2`, `stco`/`co64`, `ctts` and `stss`.
+/// The run-length tables are kept as runs, every lookup is a binary search over them
+/// plus a sum of at most `PREFIX_INTERVAL` sample sizes for the offset of the sample in its chunk.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SampleTable {
+    sample_count: u32,
+    time_runs: Vec<TimeRun>,
+    composition_runs: Vec<CompositionRun>,
+    chunk_runs: Vec<ChunkRun>,
+    chunk_offsets: Vec<u64>,
+    sizes: SampleSizes,
+    /// `None` when every sample is a sync sample
+    stss: Option<Stss>,
+}
+
+impl SampleTable {
+
+    pub fn new(stbl: &Stbl) -> Result<Self, MP4Error> {
+        let (sample_count, sizes) = match (&stbl.stsz, &stbl.stz2) {
+            (Some(stsz), _) => (stsz.sample_count(), match &stsz.inner.inner {
+                Stsz::Simple { sample_size, .. } => SampleSizes::Constant(*sample_size),
+                Stsz::Advanced { sample_sizes } => SampleSizes::Stsz {
+                    sizes: sample_sizes.0.clone(),
+                    prefixes: prefixes(&sample_sizes.0)
+                }
+            }),
+            (None, Some(stz2)) => (stz2.sample_count(), SampleSizes::Stz2 {
+                sizes: stz2.inner.inner.sample_sizes.clone(),
+                prefixes: prefixes(&stz2.inner.inner.sample_sizes)
+            }),
+            (None, None) => return Err(MP4Error::Custom("The sample table has no stsz or stz2".to_string()))
+        };
+
+        let mut time_runs = vec![];
+        let mut first_sample = 0u32;
+        let mut first_time = 0u64;
+        for entry in stbl.stts.iter().flat_map(|it| it.samples.0.iter()) {
+            if entry.sample_count == 0 {
+                continue;
+            }
+            time_runs.push(TimeRun {
+                first_sample,
+                first_time,
+                sample_count: entry.sample_count,
+                sample_delta: entry.sample_delta
+            });
+            first_sample = first_sample.saturating_add(entry.sample_count);
+            first_time += entry.sample_count as u64 * entry.sample_delta as u64;
+        }
+
+        let mut composition_runs = vec![];
+        let mut first_sample = 0u32;
+        for entry in stbl.ctts.iter().flat_map(|it| it.inner.inner.entries.iter()) {
+            if entry.sample_count == 0 {
+                continue;
+            }
+            composition_runs.push(CompositionRun {
+                first_sample,
+                sample_count: entry.sample_count,
+                sample_offset: entry.sample_offset
+            });
+            first_sample = first_sample.saturating_add(entry.sample_count);
+        }
+
+        let chunk_offsets = match (&stbl.stco, &stbl.co64) {
+            (Some(stco), _) => stco.entries.0.iter().map(|it| it.chunk_offset as u64).collect::<Vec<_>>(),
+            (None, Some(co64)) => co64.entries.0.iter().map(|it| it.chunk_offset).collect(),
+            (None, None) => return Err(MP4Error::Custom("The sample table has no stco or co64".to_string()))
+        };
+
+        let mut chunk_runs: Vec<ChunkRun> = vec![];
+        let mut first_sample = 0u32;
+        for entry in stbl.stsc.iter().flat_map(|it| it.entries.0.iter()) {
+            let first_chunk = entry.first_chunk.checked_sub(1)
+                .ok_or_else(|| MP4Error::Custom("The stsc chunks are 1-based".to_string()))?;
+            if let Some(previous) = chunk_runs.last() {
+                let chunks = first_chunk.checked_sub(previous.first_chunk)
+                    .ok_or_else(|| MP4Error::Custom("The stsc entries are not sorted".to_string()))?;
+                first_sample = first_sample.saturating_add(chunks.saturating_mul(previous.samples_per_chunk));
+            }
+            chunk_runs.push(ChunkRun {
+                first_chunk,
+                first_sample,
+                samples_per_chunk: entry.samples_per_chunk,
+                sample_description_index: entry.sample_description_index
+            });
+        }
+
+        Ok(Self {
+            sample_count,
+            time_runs,
+            composition_runs,
+            chunk_runs,
+            chunk_offsets,
+            sizes,
+            stss: stbl.stss.as_ref().map(|it| it.inner.inner.clone())
+        })
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Sum of the sample durations.
+    pub fn duration(&self) -> u64 {
+        self.time_runs.last()
+            .map(|it| it.first_time + it.sample_count as u64 * it.sample_delta as u64)
+            .unwrap_or_default()
+    }
+
+    fn size(&self, number: u32) -> Option<u32> {
+        match &self.sizes {
+            SampleSizes::Constant(size) => Some(*size),
+            SampleSizes::Stsz { sizes, .. } => sizes.get(number as usize).copied(),
+            SampleSizes::Stz2 { sizes, .. } => sizes.get(number as usize).map(|it| *it as u32)
+        }
+    }
+
+    /// Total size of the samples in `first..last`, both within the table.
+    fn size_between(&self, first: u32, last: u32) -> u64 {
+        let (first, last) = (first as usize, last as usize);
+        match &self.sizes {
+            SampleSizes::Constant(size) => (last - first) as u64 * *size as u64,
+            SampleSizes::Stsz { sizes, prefixes } => size_before(sizes, prefixes, last) - size_before(sizes, prefixes, first),
+            SampleSizes::Stz2 { sizes, prefixes } => size_before(sizes, prefixes, last) - size_before(sizes, prefixes, first)
+        }
+    }
+
+    /// Resolves a 0-based sample, `None` past the end of any of the tables.
+    pub fn sample(&self, number: u32) -> Option<TableSample> {
+        self.resolve(number, None).map(|(sample, _)| sample)
+    }
+
+    /// Resolves a sample along with its chunk, `previous` is the chunk and end offset of the sample before it.
+    fn resolve(&self, number: u32, previous: Option<(u32, u64)>) -> Option<(TableSample, u32)> {
+        if number >= self.sample_count {
+            return None;
+        }
+        let time = self.time_runs[self.time_runs.partition_point(|it| it.first_sample <= number).checked_sub(1)?];
+        if number - time.first_sample >= time.sample_count {
+            return None;
+        }
+        let chunk = self.chunk_runs[self.chunk_runs.partition_point(|it| it.first_sample <= number).checked_sub(1)?];
+        if chunk.samples_per_chunk == 0 {
+            return None;
+        }
+        let chunk_index = chunk.first_chunk + (number - chunk.first_sample) / chunk.samples_per_chunk;
+        let first_in_chunk = chunk.first_sample + (chunk_index - chunk.first_chunk) * chunk.samples_per_chunk;
+        let offset = match previous {
+            Some((previous_chunk, end)) if previous_chunk == chunk_index => end,
+            _ => self.chunk_offsets.get(chunk_index as usize)?.saturating_add(self.size_between(first_in_chunk, number))
+        };
+        let sample = TableSample {
+            number,
+            offset,
+            size: self.size(number)?,
+            decode_time: time.first_time + (number - time.first_sample) as u64 * time.sample_delta as u64,
+            duration: time.sample_delta,
+            sample_description_index: chunk.sample_description_index,
+            composition_offset: self.composition_offset(number),
+            sync: self.is_sync(number)
+        };
+        Some((sample, chunk_index))
+    }
+
+    /// The `ctts` offset of a sample, 0 without `ctts` or past its end.
+    pub fn composition_offset(&self, number: u32) -> i32 {
+        match self.composition_runs.partition_point(|it| it.first_sample <= number).checked_sub(1) {
+            Some(index) if number - self.composition_runs[index].first_sample < self.composition_runs[index].sample_count =>
+                self.composition_runs[index].sample_offset,
+            _ => 0
+        }
+    }
+
+    pub fn is_sync(&self, number: u32) -> bool {
+        self.stss.as_ref().map(|it| it.is_sync(number)).unwrap_or(true)
+    }
+
+    /// The last sync sample at or before `number`.
+    pub fn sync_sample_before(&self, number: u32) -> Option<u32> {
+        match &self.stss {
+            Some(stss) => stss.sync_sample_before(number),
+            None => Some(number)
+        }
+    }
+
+    /// The first sync sample at or after `number`.
+    pub fn sync_sample_after(&self, number: u32) -> Option<u32> {
+        match &self.stss {
+            Some(stss) => stss.sync_sample_after(number),
+            None => Some(number)
+        }
+    }
+
+    /// The sample being decoded at `decode_time`, `None` past the end of the track.
+    pub fn sample_at(&self, decode_time: u64) -> Option<u32> {
+        let time = self.time_runs[self.time_runs.partition_point(|it| it.first_time <= decode_time).checked_sub(1)?];
+        let index = match time.sample_delta {
+            0 => 0,
+            delta => (decode_time - time.first_time) / delta as u64
+        };
+        if index >= time.sample_count as u64 {
+            return None;
+        }
+        Some(time.first_sample + index as u32).filter(|it| *it < self.sample_count)
+    }
+
+    /// Iterates the samples in order, the samples after the first of a chunk follow the one before them.
+    pub fn samples(&self) -> impl Iterator<Item=TableSample> + '_ {
+        let mut previous = None;
+        (0..self.sample_count).map_while(move |number| {
+            let (sample, chunk) = self.resolve(number, previous)?;
+            previous = Some((chunk, sample.offset.saturating_add(sample.size as u64)));
+            Some(sample)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mp4box::co64::{Co64, StcoEntry};
+    use crate::mp4box::ctts::{Ctts, CttsEntry};
+    use crate::mp4box::stbl::Stbl;
+    use crate::mp4box::stsc::{Stsc, StscEntry};
+    use crate::mp4box::stss::Stss;
+    use crate::mp4box::stts::{Stts, SttsEntry};
+    use crate::sample_table::SampleTable;
+
+    #[test]
+    pub fn test_sample_table() {
+        let mut stbl = Stbl {
+            co64: Some(Co64 {
+                entries: vec![
+                    StcoEntry { chunk_offset: 1000 },
+                    StcoEntry { chunk_offset: 2000 },
+                    StcoEntry { chunk_offset: u32::MAX as u64 + 10 },
+                ].into()
+            }.into()),
+            stco: None,
+            stsc: Some(Stsc {
+                entries: vec![
+                    StscEntry { first_chunk: 1, samples_per_chunk: 2, sample_description_index: 1 },
+                    StscEntry { first_chunk: 3, samples_per_chunk: 3, sample_description_index: 2 },
+                ].into()
+            }.into()),
+            stsd: None,
+            stss: None,
+            stsz: None,
+            stz2: None,
+            stts: Some(Stts {
+                samples: vec![
+                    SttsEntry { sample_count: 4, sample_delta: 1000 },
+                    SttsEntry { sample_count: 3, sample_delta: 500 },
+                ].into()
+            }.into()),
+            ctts: None,
+            sdtp: None,
+            sbgps: vec![],
+            sgpds: vec![]
+        };
+        stbl.set_sample_sizes(&[10, 20, 30, 40, 50, 60, 70], false);
+        let table = SampleTable::new(&stbl).unwrap();
+        assert_eq!(table.sample_count(), 7);
+        assert_eq!(table.duration(), 5500);
+
+        let sample = table.sample(3).unwrap();
+        assert_eq!((sample.offset, sample.size, sample.decode_time, sample.duration), (2030, 40, 3000, 1000));
+        let sample = table.sample(6).unwrap();
+        assert_eq!((sample.offset, sample.decode_time, sample.duration), (u32::MAX as u64 + 10 + 110, 5000, 500));
+        assert_eq!(sample.sample_description_index, 2);
+        assert_eq!(table.sample(7), None);
+
+        assert_eq!(table.sample_at(0), Some(0));
+        assert_eq!(table.sample_at(3999), Some(3));
+        assert_eq!(table.sample_at(4000), Some(4));
+        assert_eq!(table.sample_at(5499), Some(6));
+        assert_eq!(table.sample_at(5500), None);
+        assert_eq!(table.samples().count(), 7);
+        assert!(table.sample(6).unwrap().sync);
+
+        stbl.stss = Some(Stss { sample_numbers: vec![1, 5].into() }.into());
+        let table = SampleTable::new(&stbl).unwrap();
+        assert_eq!(table.sync_sample_before(3), Some(0));
+        assert_eq!(table.sync_sample_before(6), Some(4));
+        assert_eq!(table.sync_sample_after(1), Some(4));
+        assert_eq!(table.sync_sample_after(5), None);
+        assert!(!table.sample(6).unwrap().sync);
+
+        stbl.ctts = Some(Ctts {
+            entries: vec![
+                CttsEntry { sample_count: 2, sample_offset: 2000 },
+                CttsEntry { sample_count: 1, sample_offset: -1000 },
+            ]
+        }.into());
+        let table = SampleTable::new(&stbl).unwrap();
+        assert_eq!(table.sample(1).map(|it| it.composition_offset), Some(2000));
+        assert_eq!(table.sample(2).map(|it| it.composition_offset), Some(-1000));
+        assert_eq!(table.composition_offset(3), 0);
+
+        stbl.set_sample_sizes(&[10; 7], false);
+        let table = SampleTable::new(&stbl).unwrap();
+        assert_eq!(table.sample(5).map(|it| it.offset), Some(u32::MAX as u64 + 10 + 10));
+
+        stbl.set_sample_sizes(&[10, 20, 30, 40, 50, 60, 70], true);
+        assert!(stbl.stz2.is_some());
+        let table = SampleTable::new(&stbl).unwrap();
+        let offsets = table.samples().map(|it| it.offset).collect::<Vec<_>>();
+        assert_eq!(offsets, vec![1000, 1010, 2000, 2030, u32::MAX as u64 + 10, u32::MAX as u64 + 60, u32::MAX as u64 + 120]);
+        assert_eq!(offsets, (0..7).map(|it| table.sample(it).unwrap().offset).collect::<Vec<_>>());
+
+        // a single chunk spanning several prefix intervals
+        let sizes = (0..200).map(|it| it % 7 + 1).collect::<Vec<u32>>();
+        stbl.stsc = Some(Stsc { entries: vec![StscEntry { first_chunk: 1, samples_per_chunk: 200, sample_description_index: 1 }].into() }.into());
+        stbl.stts = Some(Stts { samples: vec![SttsEntry { sample_count: 200, sample_delta: 1000 }].into() }.into());
+        stbl.ctts = None;
+        for allow_stz2 in [false, true] {
+            stbl.set_sample_sizes(&sizes, allow_stz2);
+            let table = SampleTable::new(&stbl).unwrap();
+            for number in [0, 63, 64, 65, 128, 199] {
+                let expected = 1000 + sizes[..number as usize].iter().map(|it| *it as u64).sum::<u64>();
+                assert_eq!(table.sample(number).map(|it| it.offset), Some(expected));
+            }
+        }
+    }
+
+}
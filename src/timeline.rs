@@ -0,0 +1,191 @@
+use crate::mp4box::moof::Moof;
+use crate::mp4box::moov::Moov;
+use crate::mp4box::tfdt::Tfdt;
+use crate::mp4box::trex::Trex;
+
+/// A discontinuity between the decode time of a fragment and the end of the previous fragment of the same track.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TimelineIssue {
+    /// the fragment starts after the previous one ended, data was dropped
+    Gap { track_id: u32, expected: u64, actual: u64 },
+    /// the fragment starts before the previous one ended
+    Overlap { track_id: u32, expected: u64, actual: u64 },
+    /// the fragment has no `tfdt`, the expected decode time is assumed
+    MissingDecodeTime { track_id: u32, expected: u64 },
+}
+
+/// Decode time state of a track, in the track timescale.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct TrackTimeline {
+    pub track_id: u32,
+    pub timescale: u32,
+    pub trex: Option<Trex>,
+    /// earliest decode time of the fragments
+    pub start: Option<u64>,
+    /// decode time the next fragment must start at
+    pub end: u64,
+}
+
+impl TrackTimeline {
+    pub fn duration(&self) -> u64 {
+        self.end.saturating_sub(self.start.unwrap_or(self.end))
+    }
+}
+
+/// Tracks the decode time of every track across fragments, to validate or generate their `tfdt`.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Timeline {
+    pub tracks: Vec<TrackTimeline>,
+}
+
+impl Timeline {
+
+    pub fn from_moov(moov: &Moov) -> Self {
+        Self {
+            tracks: moov.traks.iter().filter_map(|trak| {
+                let track_id = trak.track_id()?;
+                Some(TrackTimeline {
+                    track_id,
+                    timescale: trak.timescale().unwrap_or_default(),
+                    trex: moov.trex(track_id).map(|it| it.inner.inner.clone()),
+                    start: None,
+                    end: 0
+                })
+            }).collect()
+        }
+    }
+
+    pub fn track(&self, track_id: u32) -> Option<&TrackTimeline> {
+        self.tracks.iter().find(|it| it.track_id == track_id)
+    }
+
+    fn track_mut(&mut self, track_id: u32) -> &mut TrackTimeline {
+        match self.tracks.iter().position(|it| it.track_id == track_id) {
+            Some(i) => &mut self.tracks[i],
+            None => {
+                self.tracks.push(TrackTimeline { track_id, ..Default::default() });
+                self.tracks.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Checks the `tfdt` of every `traf` against the end of the previous fragment of its track, then advances the tracks.
+    /// The timeline follows the fragment's decode times, so a gap is only reported once.
+    pub fn check(&mut self, moof: &Moof) -> Vec<TimelineIssue> {
+        let mut issues = vec![];
+        for traf in &moof.trafs {
+            let track_id = match traf.track_id() {
+                Some(track_id) => track_id,
+                None => continue
+            };
+            let track = self.track_mut(track_id);
+            let expected = track.end;
+            let actual = match &traf.tfdt {
+                Some(tfdt) => *tfdt.base_media_decode_time,
+                None => {
+                    issues.push(TimelineIssue::MissingDecodeTime { track_id, expected });
+                    expected
+                }
+            };
+            if track.start.is_some() {
+                if actual > expected {
+                    issues.push(TimelineIssue::Gap { track_id, expected, actual });
+                } else if actual < expected {
+                    issues.push(TimelineIssue::Overlap { track_id, expected, actual });
+                }
+            }
+            track.start = Some(track.start.map_or(actual, |it| it.min(actual)));
+            track.end = actual + traf.duration(track.trex.as_ref());
+        }
+        issues
+    }
+
+    /// Sets the `tfdt` of every `traf` to the end of the previous fragment of its track, then advances the tracks.
+    /// The decode time is written on 64 bits once it no longer fits 32.
+    pub fn stamp(&mut self, moof: &mut Moof) {
+        for traf in &mut moof.trafs {
+            let track_id = match traf.track_id() {
+                Some(track_id) => track_id,
+                None => continue
+            };
+            let track = self.track_mut(track_id);
+            let decode_time = track.end;
+            traf.tfdt = Some(Tfdt { base_media_decode_time: decode_time.into() }.into());
+            track.start.get_or_insert(decode_time);
+            track.end = decode_time + traf.duration(track.trex.as_ref());
+        }
+    }
+
+    /// Duration of the longest track in `timescale` units, the `fragment_duration` of a `mehd`.
+    pub fn fragment_duration(&self, timescale: u32) -> u64 {
+        self.tracks.iter()
+            .filter(|it| it.timescale != 0)
+            .map(|it| (it.duration() as u128 * timescale as u128 / it.timescale as u128) as u64)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mp4box::box_full::FullBoxInfo;
+    use crate::mp4box::moof::Moof;
+    use crate::mp4box::tfdt::Tfdt;
+    use crate::mp4box::tfhd::Tfhd;
+    use crate::mp4box::traf::Traf;
+    use crate::mp4box::trun::{Trun, TrunEntry};
+    use crate::timeline::{Timeline, TimelineIssue, TrackTimeline};
+    use crate::types::array::Mp4VersionedOffsetArray;
+
+    fn moof(decode_time: Option<u64>, durations: &[u32]) -> Moof {
+        Moof {
+            mfhd: None,
+            trafs: vec![Traf {
+                tfhd: Some(Tfhd {
+                    track_id: 1,
+                    base_data_offset: Default::default(),
+                    sample_description_index: Default::default(),
+                    default_sample_duration: Default::default(),
+                    default_sample_size: Default::default(),
+                    default_sample_flags: Default::default(),
+                    flags: Default::default()
+                }.into()),
+                tfdt: decode_time.map(|it| Tfdt { base_media_decode_time: it.into() }.into()),
+                sdtp: None,
+                sbgps: vec![],
+                sgpds: vec![],
+                truns: vec![Trun {
+                    entries: Mp4VersionedOffsetArray::new(durations.iter().map(|duration| TrunEntry {
+                        sample_duration: (*duration).into(),
+                        ..Default::default()
+                    }).collect(), Default::default())
+                }.into()]
+            }.into()]
+        }
+    }
+
+    #[test]
+    pub fn test_timeline() {
+        let mut timeline = Timeline {
+            tracks: vec![TrackTimeline { track_id: 1, timescale: 90000, ..Default::default() }]
+        };
+        let mut first = moof(None, &[3000, 3000]);
+        timeline.stamp(&mut first);
+        assert_eq!(first.traf(1).and_then(|it| it.tfdt.as_ref()).map(|it| *it.base_media_decode_time), Some(0));
+        assert!(timeline.check(&moof(Some(6000), &[3000])).is_empty());
+        assert_eq!(timeline.check(&moof(Some(12000), &[3000])), vec![TimelineIssue::Gap { track_id: 1, expected: 9000, actual: 12000 }]);
+        assert_eq!(timeline.check(&moof(Some(14000), &[3000])), vec![TimelineIssue::Overlap { track_id: 1, expected: 15000, actual: 14000 }]);
+        assert_eq!(timeline.fragment_duration(1000), 17000 * 1000 / 90000);
+
+        let mut late = moof(None, &[3000]);
+        timeline.tracks[0].end = u32::MAX as u64 + 1;
+        timeline.stamp(&mut late);
+        assert_eq!(late.trafs[0].tfdt.as_ref().map(|it| it.version()), Some(1));
+
+        let mut timeline = Timeline::default();
+        assert!(timeline.check(&moof(Some(9000), &[3000])).is_empty());
+        assert_eq!(timeline.check(&moof(Some(0), &[3000])), vec![TimelineIssue::Overlap { track_id: 1, expected: 12000, actual: 0 }]);
+        assert_eq!(timeline.track(1).map(|it| it.duration()), Some(3000));
+    }
+
+}
@@ -4,6 +4,7 @@ use std::fmt::{Display, Formatter};
 use crate::id::BoxId;
 
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(untagged))]
 pub enum BoxType {
     Id(BoxId),
     UUID(Uuid)
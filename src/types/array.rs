@@ -7,7 +7,8 @@ use async_trait::async_trait;
 use num_traits::{AsPrimitive};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Default)]
-pub struct Mp4Array<I, T>(pub Vec<T>, pub PhantomData<I>)
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct Mp4Array<I, T>(pub Vec<T>, #[cfg_attr(feature = "serde", serde(skip))] pub PhantomData<I>)
     where
         I: AsPrimitive<usize> + Mp4Readable + Mp4Writable,
         T: Mp4Readable + Mp4Writable,
@@ -60,6 +61,7 @@ impl<I, T> Mp4Writable for Mp4Array<I, T>
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mp4OffsetArray<I, O, T>
     where
         I: AsPrimitive<usize> + Mp4Readable + Mp4Writable,
@@ -67,6 +69,7 @@ pub struct Mp4OffsetArray<I, O, T>
 {
     pub data: Vec<T>,
     pub offset: O,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub _p: PhantomData<I>,
 }
 
@@ -120,6 +123,7 @@ impl<I, O, T> Mp4Writable for Mp4OffsetArray<I, O, T> where
 }
 
 #[derive(Default, Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mp4VersionedOffsetArray<I, O, T>
     where
         I: AsPrimitive<usize> + Mp4Readable + Mp4Writable,
@@ -127,6 +131,7 @@ pub struct Mp4VersionedOffsetArray<I, O, T>
 {
     pub data: Vec<T>,
     pub offset: O,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub _p: PhantomData<I>,
 }
 
@@ -51,3 +51,18 @@ impl<F: FlagTrait> Mp4VersionedWritable<F> for Mp4DateTime {
         self.0.versioned_write(version, flags, writer)
     }
 }
+
+/// Serialized as an ISO 8601 date.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Mp4DateTime {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        DateTime::<Utc>::from(*self).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Mp4DateTime {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(DateTime::<Utc>::deserialize(deserializer)?.into())
+    }
+}
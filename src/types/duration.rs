@@ -5,6 +5,7 @@ use crate::bytes_write::{FlagTrait, Mp4VersionedWritable, Mp4Writable, WriteMp4}
 use crate::error::MP4Error;
 
 #[derive(Copy, Clone, Debug, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mp4Duration(pub Option<u64>);
 
 #[async_trait]
@@ -46,3 +47,17 @@ impl<F: FlagTrait> Mp4VersionedWritable<F> for Mp4Duration {
         })
     }
 }
+
+/// Converts a number of `timescale` ticks into a wall clock duration, `None` if the timescale is 0.
+pub fn duration_from_ticks(ticks: u64, timescale: u32) -> Option<std::time::Duration> {
+    if timescale == 0 {
+        return None;
+    }
+    let nanos = ticks as u128 * 1_000_000_000 / timescale as u128;
+    Some(std::time::Duration::new((nanos / 1_000_000_000) as u64, (nanos % 1_000_000_000) as u32))
+}
+
+/// Converts a wall clock duration into `timescale` ticks, rounding down.
+pub fn ticks_from_duration(duration: std::time::Duration, timescale: u32) -> u64 {
+    (duration.as_nanos() * timescale as u128 / 1_000_000_000) as u64
+}
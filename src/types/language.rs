@@ -40,15 +40,18 @@ impl From<Mp4LanguageCode> for Option<LanguageCode> {
 
 
 impl Mp4LanguageCode {
-    fn to_language_code(&self) -> Option<LanguageCode> {
+    fn letters(&self) -> [u8; 3] {
         const MASK: u8 = 0b11111;
         let value = self.0;
-        let data = [
+        [
             ((value >> 10) as u8 & MASK) + 0x60,
             ((value >> 05) as u8 & MASK) + 0x60,
             ((value >> 00) as u8 & MASK) + 0x60
-        ];
-        LanguageCode::from_str(std::str::from_utf8(&data).ok()?).ok()
+        ]
+    }
+
+    fn to_language_code(&self) -> Option<LanguageCode> {
+        LanguageCode::from_str(std::str::from_utf8(&self.letters()).ok()?).ok()
     }
 
     fn from_language_code(code: Option<LanguageCode>) -> Self {
@@ -69,3 +72,24 @@ impl Mp4LanguageCode {
         })
     }
 }
+
+/// Serialized as its three letters, which may not be a known ISO 639-2 code.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Mp4LanguageCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(std::str::from_utf8(&self.letters()).unwrap_or_default())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Mp4LanguageCode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        match code.as_bytes() {
+            [a, b, c] if [a, b, c].iter().all(|it| (0x60..0x80).contains(*it)) => {
+                Ok(Self(((*a as u16 - 0x60) << 10) | ((*b as u16 - 0x60) << 5) | (*c as u16 - 0x60)))
+            }
+            _ => Err(serde::de::Error::custom(format!("Invalid language code: {}", code)))
+        }
+    }
+}
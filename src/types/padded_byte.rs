@@ -6,6 +6,7 @@ use async_trait::async_trait;
 use crate::bytes_write::{Mp4Writable, WriteMp4};
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PaddedByte<const N: usize, const V: usize = 0>(u8);
 
 impl<const N: usize, const V: usize> Default for PaddedByte<N, V> {
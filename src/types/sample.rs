@@ -4,6 +4,7 @@ use crate::mp4_data;
 
 mp4_data! {
     #[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct SampleEntry {
         pub _r1: [u8; 6],
         pub data_reference_index: u16
@@ -12,6 +13,7 @@ mp4_data! {
 
 mp4_data! {
     #[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct VisualSampleEntry {
         pub sample_entry: SampleEntry,
         pub _r1: [u32; 4],
@@ -37,6 +39,7 @@ impl Deref for VisualSampleEntry {
 
 mp4_data! {
     #[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct AudioSampleEntry {
         pub sample_entry: SampleEntry,
         pub _r1: [u32; 2],
@@ -5,6 +5,7 @@ use crate::bytes_write::{FlagTrait, Mp4VersionedWritable, Mp4Writable, WriteMp4}
 use crate::error::{MP4Error};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VersionedSignedU32 {
     Unsigned(u32),
     Signed(i32)
@@ -7,6 +7,7 @@ use crate::error::MP4Error;
 
 #[repr(transparent)]
 #[derive(Debug, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VersionedU32U64(pub u64);
 
 impl From<u32> for VersionedU32U64 {
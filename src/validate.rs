@@ -0,0 +1,410 @@
+use std::fmt::{Display, Formatter};
+use std::io::SeekFrom;
+use std::ops::Range;
+use futures::AsyncSeekExt;
+use crate::bytes_read::ReadMp4;
+use crate::bytes_write::Mp4Writable;
+use crate::demux::Demuxer;
+use crate::error::MP4Error;
+use crate::mp4box::box_trait::{BoxRead, IBox};
+use crate::mp4box::ftyp::FtypBox;
+use crate::mp4box::mdat::MdatBox;
+use crate::mp4box::moof::MoofBox;
+use crate::mp4box::moov::{Moov, MoovBox};
+use crate::mp4box::stbl::Stbl;
+use crate::mp4box::trak::Trak;
+use crate::sample_table::SampleTable;
+
+/// Brands of the CMAF structural constraints (ISO 23000-19 § 7).
+const CMAF_BRANDS: [[u8; 4]; 2] = [*b"cmfc", *b"cmf2"];
+
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum Severity {
+    /// the file is readable but unusual, or breaks a recommendation
+    Warning,
+    /// the file breaks the specification, players may reject it
+    Error,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "warning: {}", self.message),
+            Severity::Error => write!(f, "error: {}", self.message)
+        }
+    }
+}
+
+/// The top level boxes of a file that [`validate`] looks at.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Mp4File {
+    pub ftyp: Option<FtypBox>,
+    pub moov: Option<MoovBox>,
+    /// every `moof` with its position
+    pub moofs: Vec<(u64, MoofBox)>,
+    /// the payload of every `mdat`, without its header
+    pub mdats: Vec<Range<u64>>,
+}
+
+impl Mp4File {
+
+    /// Reads the top level boxes, skipping the content of the `mdat`s.
+    pub async fn read<R: ReadMp4>(reader: R) -> Result<Self, MP4Error> {
+        let mut demuxer = Demuxer::new(reader);
+        let mut file = Self::default();
+        let mut next = Some(0);
+        while let Some((header, following)) = match next {
+            Some(position) => demuxer.top_level_header(position).await?,
+            None => None
+        } {
+            let position = next.unwrap_or_default();
+            match header.id {
+                FtypBox::ID => file.ftyp = Some(FtypBox::read(header, demuxer.get_mut()).await?),
+                MoovBox::ID => file.moov = Some(MoovBox::read(header, demuxer.get_mut()).await?),
+                MoofBox::ID => file.moofs.push((position, MoofBox::read(header, demuxer.get_mut()).await?)),
+                MdatBox::ID => {
+                    let end = match following {
+                        Some(end) => end,
+                        None => demuxer.get_mut().seek(SeekFrom::End(0)).await?
+                    };
+                    file.mdats.push(position + header.byte_size() as u64..end);
+                }
+                _ => {}
+            }
+            next = following;
+        }
+        Ok(file)
+    }
+
+    fn brands(&self) -> impl Iterator<Item=&[u8; 4]> {
+        self.ftyp.iter().flat_map(|it| std::iter::once(&it.major_brand).chain(it.compatible_brands.iter()))
+    }
+
+    fn contains(&self, offset: u64, size: u32) -> bool {
+        self.mdats.iter().any(|it| offset.checked_add(size as u64).is_some_and(|end| it.start <= offset && end <= it.end))
+    }
+}
+
+/// Checks the structure of a file: the mandatory boxes, the consistency of the sample tables and that their data is
+/// in a `mdat`, the `trex`, `mfhd` and `tfdt` of fragmented files, and the CMAF constraints when it has a CMAF brand.
+/// An empty list means nothing was found.
+pub fn validate(file: &Mp4File) -> Vec<Issue> {
+    let mut issues = Issues::default();
+    let cmaf = file.brands().any(|it| CMAF_BRANDS.contains(it));
+    if file.ftyp.is_none() {
+        issues.warning("The file has no ftyp".to_string());
+    }
+    let moov = match &file.moov {
+        Some(moov) => moov,
+        None => {
+            issues.error("The file has no moov".to_string());
+            return issues.0;
+        }
+    };
+    if moov.mvhd.is_none() {
+        issues.error("The moov has no mvhd".to_string());
+    }
+    if moov.traks.is_empty() {
+        issues.warning("The moov has no trak".to_string());
+    }
+    for (index, trak) in moov.traks.iter().enumerate() {
+        validate_trak(file, index, trak, &mut issues);
+    }
+
+    let fragmented = moov.mvex.is_some() || !file.moofs.is_empty();
+    if fragmented {
+        validate_fragments(file, moov, cmaf, &mut issues);
+    }
+
+    if cmaf {
+        if moov.traks.len() != 1 {
+            issues.error(format!("CMAF: the moov must have exactly one trak, found {}", moov.traks.len()));
+        }
+        if !fragmented {
+            issues.error("CMAF: the file is not fragmented".to_string());
+        }
+        for trak in &moov.traks {
+            if trak.stbl().and_then(|it| it.sample_count()).unwrap_or_default() != 0 {
+                issues.error(format!("CMAF: {} has samples in its sample table", name(trak)));
+            }
+        }
+    }
+    issues.0
+}
+
+#[derive(Default)]
+struct Issues(Vec<Issue>);
+
+impl Issues {
+    fn warning(&mut self, message: String) {
+        self.0.push(Issue { severity: Severity::Warning, message });
+    }
+
+    fn error(&mut self, message: String) {
+        self.0.push(Issue { severity: Severity::Error, message });
+    }
+}
+
+fn name(trak: &Trak) -> String {
+    match trak.track_id() {
+        Some(track_id) => format!("track {}", track_id),
+        None => "a track without tkhd".to_string()
+    }
+}
+
+fn validate_trak(file: &Mp4File, index: usize, trak: &Trak, issues: &mut Issues) {
+    let name = name(trak);
+    if trak.tkhd.is_none() {
+        issues.error(format!("trak {} has no tkhd", index + 1));
+    }
+    let mdia = match &trak.mdia {
+        Some(mdia) => mdia,
+        None => return issues.error(format!("{} has no mdia", name))
+    };
+    if mdia.mdhd.is_none() {
+        issues.error(format!("{} has no mdhd", name));
+    }
+    if mdia.hdlr.is_none() {
+        issues.error(format!("{} has no hdlr", name));
+    }
+    let stbl = match mdia.minf.as_ref().and_then(|it| it.stbl.as_ref()) {
+        Some(stbl) => stbl,
+        None => return issues.error(format!("{} has no minf or stbl", name))
+    };
+    match &stbl.stsd {
+        Some(stsd) if stsd.entries.0.is_empty() => issues.error(format!("{} has an empty stsd", name)),
+        Some(_) => {}
+        None => issues.error(format!("{} has no stsd", name))
+    }
+    if validate_sample_counts(&name, stbl, issues) {
+        validate_chunk_offsets(file, &name, stbl, issues);
+    }
+}
+
+/// Compares the number of samples of the `stsz`, `stts` and `stsc`, returns whether they match.
+fn validate_sample_counts(name: &str, stbl: &Stbl, issues: &mut Issues) -> bool {
+    let sizes = stbl.sample_count();
+    let times = stbl.stts.as_ref().map(|it| it.samples.0.iter().map(|it| it.sample_count as u64).sum::<u64>());
+    let chunk_count = match (&stbl.stco, &stbl.co64) {
+        (Some(stco), _) => Some(stco.entries.0.len() as u64),
+        (None, Some(co64)) => Some(co64.entries.0.len() as u64),
+        (None, None) => None
+    };
+    let chunks = stbl.stsc.as_ref().zip(chunk_count).map(|(stsc, chunk_count)| {
+        let entries = &stsc.entries.0;
+        entries.iter().enumerate().map(|(i, entry)| {
+            let next = entries.get(i + 1).map(|it| it.first_chunk as u64).unwrap_or(chunk_count + 1);
+            next.saturating_sub(entry.first_chunk as u64) * entry.samples_per_chunk as u64
+        }).sum::<u64>()
+    });
+
+    let mut valid = true;
+    for (present, child) in [(sizes.is_some(), "stsz or stz2"), (times.is_some(), "stts"), (stbl.stsc.is_some(), "stsc"), (chunk_count.is_some(), "stco or co64")] {
+        if !present {
+            issues.error(format!("{} has no {}", name, child));
+            valid = false;
+        }
+    }
+    if let (Some(sizes), Some(times)) = (sizes, times) {
+        if sizes as u64 != times {
+            issues.error(format!("{} has {} samples in its stts but {} in its stsz", name, times, sizes));
+            valid = false;
+        }
+    }
+    if let (Some(sizes), Some(chunks)) = (sizes, chunks) {
+        if sizes as u64 != chunks {
+            issues.error(format!("{} has {} samples in its stsc but {} in its stsz", name, chunks, sizes));
+            valid = false;
+        }
+    }
+    valid
+}
+
+fn validate_chunk_offsets(file: &Mp4File, name: &str, stbl: &Stbl, issues: &mut Issues) {
+    let table = match SampleTable::new(stbl) {
+        Ok(table) => table,
+        Err(e) => return issues.error(format!("{} has an invalid sample table: {}", name, e))
+    };
+    let outside = table.samples().filter(|it| it.size != 0 && !file.contains(it.offset, it.size)).collect::<Vec<_>>();
+    if let Some(first) = outside.first() {
+        issues.error(format!("{} has {} samples outside of any mdat, the first at offset {}", name, outside.len(), first.offset));
+    }
+}
+
+fn validate_fragments(file: &Mp4File, moov: &Moov, cmaf: bool, issues: &mut Issues) {
+    if moov.mvex.is_none() {
+        issues.error("The file has moof boxes but its moov has no mvex".to_string());
+    } else {
+        for trak in &moov.traks {
+            if trak.track_id().and_then(|id| moov.trex(id)).is_none() {
+                issues.error(format!("{} has no trex", name(trak)));
+            }
+        }
+    }
+
+    let mut sequence_number = None;
+    // decode time at the end of the previous fragment of each trak
+    let mut ends: Vec<Option<u64>> = vec![None; moov.traks.len()];
+    for (position, moof) in &file.moofs {
+        match &moof.mfhd {
+            Some(mfhd) => {
+                if let Some(previous) = sequence_number.filter(|it| mfhd.sequence_number <= *it) {
+                    issues.error(format!("moof at {} has sequence number {} after {}", position, mfhd.sequence_number, previous));
+                }
+                sequence_number = Some(mfhd.sequence_number);
+            }
+            None => issues.error(format!("moof at {} has no mfhd", position))
+        }
+        if cmaf && moof.trafs.len() != 1 {
+            issues.error(format!("CMAF: moof at {} must have exactly one traf, found {}", position, moof.trafs.len()));
+        }
+
+        for (traf, samples) in moof.trafs.iter().zip(moof.samples(*position, Some(moov))) {
+            let track_id = match traf.track_id() {
+                Some(track_id) => track_id,
+                None => {
+                    issues.error(format!("moof at {} has a traf without tfhd", position));
+                    continue;
+                }
+            };
+            let index = match moov.traks.iter().position(|it| it.track_id() == Some(track_id)) {
+                Some(index) => index,
+                None => {
+                    issues.error(format!("moof at {} has a traf for track {} which is not in the moov", position, track_id));
+                    continue;
+                }
+            };
+
+            match &traf.tfdt {
+                Some(tfdt) => {
+                    let time = tfdt.base_media_decode_time.0;
+                    if let Some(end) = ends[index] {
+                        if time < end {
+                            issues.error(format!("moof at {}: the tfdt of track {} is {} but its previous fragment ends at {}", position, track_id, time, end));
+                        } else if time > end {
+                            issues.warning(format!("moof at {}: track {} has a gap of {} before its tfdt", position, track_id, time - end));
+                        }
+                    }
+                    ends[index] = Some(time + samples.iter().map(|it| it.duration as u64).sum::<u64>());
+                }
+                None if cmaf => issues.error(format!("CMAF: moof at {} has no tfdt for track {}", position, track_id)),
+                None => issues.warning(format!("moof at {} has no tfdt for track {}", position, track_id))
+            }
+
+            let outside = samples.iter().filter(|it| it.size != 0 && !file.contains(it.offset, it.size)).collect::<Vec<_>>();
+            if let Some(first) = outside.first() {
+                issues.error(format!("moof at {}: track {} has {} samples outside of any mdat, the first at offset {}", position, track_id, outside.len(), first.offset));
+            }
+            if cmaf && samples.first().is_some_and(|it| !it.flags.is_sync()) {
+                issues.error(format!("CMAF: moof at {}: the fragment of track {} does not start with a sync sample", position, track_id));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::demux::Demuxer;
+    use crate::error::MP4Error;
+    use crate::fragment::FragmentWriter;
+    use crate::id::BoxId;
+    use crate::mp4box::box_trait::{BoxWrite, IBox};
+    use crate::mp4box::box_unknown::UnknownBox;
+    use crate::mp4box::co64::{Co64, StcoEntry};
+    use crate::mp4box::hdlr::Hdlr;
+    use crate::mp4box::stsd::{Stsd, StsdSampleEntry};
+    use crate::r#type::BoxType;
+    use crate::remux::Remuxer;
+    use crate::remux::test::progressive;
+    use crate::validate::{Issue, Mp4File, Severity, validate};
+
+    /// The test file with a `hdlr` and a `stsd` in every track.
+    async fn complete() -> Result<Vec<u8>, MP4Error> {
+        let mut buf = progressive()?;
+        let mut moov = Demuxer::new(futures::io::Cursor::new(buf.clone())).read_moov().await?.clone();
+        buf.truncate(buf.len() - moov.byte_size());
+        for (trak, handler) in moov.traks.iter_mut().zip([*b"vide", *b"soun"]) {
+            let mdia = trak.mdia.as_mut().unwrap();
+            mdia.hdlr = Some(Hdlr { handler_type: handler, ..Default::default() }.into());
+            let entry = StsdSampleEntry::Unknown(UnknownBox { id: BoxType::Id(BoxId(handler)), data: vec![] });
+            mdia.minf.as_mut().and_then(|it| it.stbl.as_mut()).unwrap().stsd = Some(Stsd { entries: vec![entry].into() }.into());
+        }
+        moov.write(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn errors(issues: Vec<Issue>) -> Vec<String> {
+        issues.into_iter().filter(|it| it.severity == Severity::Error).map(|it| it.message).collect()
+    }
+
+    #[test]
+    pub fn test_validate_progressive() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let file = Mp4File::read(futures::io::Cursor::new(progressive()?)).await?;
+            assert_eq!(errors(validate(&file)), [
+                "track 1 has no hdlr", "track 1 has no stsd", "track 2 has no hdlr", "track 2 has no stsd"
+            ]);
+
+            let mut file = Mp4File::read(futures::io::Cursor::new(complete().await?)).await?;
+            assert_eq!(validate(&file), []);
+
+            let moov = file.moov.as_mut().unwrap();
+            let stbl = moov.traks[0].mdia.as_mut().and_then(|it| it.minf.as_mut()).and_then(|it| it.stbl.as_mut()).unwrap();
+            stbl.stco.as_mut().unwrap().inner.inner.entries.0[0].chunk_offset += 40;
+            let stbl = moov.traks[1].mdia.as_mut().and_then(|it| it.minf.as_mut()).and_then(|it| it.stbl.as_mut()).unwrap();
+            stbl.stts.as_mut().unwrap().inner.inner.samples.0[0].sample_count = 11;
+            assert_eq!(errors(validate(&file)), [
+                "track 1 has 4 samples outside of any mdat, the first at offset 76",
+                "track 2 has 11 samples in its stts but 12 in its stsz"
+            ]);
+
+            let mut file = Mp4File::read(futures::io::Cursor::new(complete().await?)).await?;
+            let moov = file.moov.as_mut().unwrap();
+            let stbl = moov.traks[1].mdia.as_mut().and_then(|it| it.minf.as_mut()).and_then(|it| it.stbl.as_mut()).unwrap();
+            let chunks = stbl.stco.take().unwrap().inner.inner.entries.0.len();
+            stbl.co64 = Some(Co64 { entries: vec![StcoEntry { chunk_offset: u64::MAX - 2 }; chunks].into() }.into());
+            assert_eq!(errors(validate(&file)), [
+                format!("track 2 has 12 samples outside of any mdat, the first at offset {}", u64::MAX - 2)
+            ]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    pub fn test_validate_fragmented() -> Result<(), MP4Error> {
+        futures::executor::block_on(async {
+            let mut remuxer = Remuxer::new(futures::io::Cursor::new(complete().await?), Duration::from_secs(2)).await?;
+            let mut writer = FragmentWriter::new(vec![]);
+            remuxer.remux(&mut writer).await?;
+            let mut file = Mp4File::read(futures::io::Cursor::new(writer.into_inner())).await?;
+            assert_eq!(file.moofs.len(), 2);
+            assert_eq!(validate(&file), []);
+            let positions = file.moofs.iter().map(|it| it.0).collect::<Vec<_>>();
+
+            let mut cmaf = file.clone();
+            cmaf.ftyp.as_mut().unwrap().compatible_brands.push(*b"cmfc");
+            assert_eq!(errors(validate(&cmaf)), [
+                format!("CMAF: moof at {} must have exactly one traf, found 2", positions[0]),
+                format!("CMAF: moof at {} must have exactly one traf, found 2", positions[1]),
+                "CMAF: the moov must have exactly one trak, found 2".to_string()
+            ]);
+
+            file.moofs[1].1.mfhd.as_mut().unwrap().sequence_number = 1;
+            file.moofs[1].1.trafs[0].tfdt.as_mut().unwrap().base_media_decode_time = 1000u64.into();
+            file.moov.as_mut().and_then(|it| it.mvex.as_mut()).unwrap().trex.pop();
+            assert_eq!(errors(validate(&file)), [
+                "track 2 has no trex".to_string(),
+                format!("moof at {} has sequence number 1 after 1", positions[1]),
+                format!("moof at {}: the tfdt of track 1 is 1000 but its previous fragment ends at 3000", positions[1])
+            ]);
+            Ok(())
+        })
+    }
+}